@@ -3,38 +3,43 @@ const cors = require('cors');
 const helmet = require('helmet');
 const morgan = require('morgan');
 const { ethers } = require('ethers');
+const path = require('path');
 require('dotenv').config();
 
 const app = express();
 const PORT = process.env.PORT || 3001;
 
-// Load the Rust CLOB binding
-let clobBinding;
+// Load the Rust CLOB napi module built by `npm run build` (cargo, feature "napi")
+const loadClobModule = () => {
+  const library = { darwin: 'libclob.dylib', win32: 'clob.dll' }[process.platform] || 'libclob.so';
+  const native = { exports: {} };
+  process.dlopen(native, path.join(__dirname, '../clob/target/release', library));
+  return native.exports;
+};
+
+// Markets created through this API are opened on behalf of this admin
+const CLOB_ADMIN = process.env.CLOB_ADMIN || 'backend';
+
+// The one engine instance every route works on
+let clob = null;
 try {
-  clobBinding = require('./build/Release/clob_binding');
-  console.log('✅ Rust CLOB binding loaded successfully');
+  const { Clob } = loadClobModule();
+  clob = new Clob();
+  clob.addAdmin(CLOB_ADMIN);
+  console.log('✅ Rust CLOB engine initialized');
 } catch (error) {
-  console.error('❌ Failed to load Rust CLOB binding:', error.message);
+  console.error('❌ Failed to load Rust CLOB module:', error.message);
   console.log('⚠️  Falling back to JavaScript implementation');
-  clobBinding = null;
+  clob = null;
 }
 
-// Initialize CLOB if available
-if (clobBinding) {
-  try {
-    const clob = new clobBinding.CLOBBinding();
-    const initResult = clob.init();
-    if (initResult) {
-      console.log('✅ Rust CLOB engine initialized');
-    } else {
-      console.error('❌ Failed to initialize Rust CLOB engine');
-      clobBinding = null;
-    }
-  } catch (error) {
-    console.error('❌ Error initializing Rust CLOB:', error);
-    clobBinding = null;
-  }
-}
+// Order ids are BigInts on the Rust side; JSON carries them as strings
+let nextOrderId = 1n;
+const tradeJson = (trade) => ({
+  ...trade,
+  buyOrderId: String(trade.buyOrderId),
+  sellOrderId: String(trade.sellOrderId)
+});
 
 // Middleware
 app.use(helmet());
@@ -46,32 +51,18 @@ app.use(express.json());
 let markets = new Map();
 let orders = new Map();
 let trades = new Map();
-let userOrders = new Map(); // Track orders by user
+let userOrders = new Map(); // Track orders by id (as a string)
 
 // Helper function to process trades and update order statuses
-const processTrade = (trade, order) => {
-  // Find the orders involved in this trade
-  const buyOrder = Array.from(userOrders.values()).find(o => 
-    o.user === trade.buyer && o.side === 'Buy' && o.market === trade.market
-  );
-  const sellOrder = Array.from(userOrders.values()).find(o => 
-    o.user === trade.seller && o.side === 'Sell' && o.market === trade.market
-  );
-  
-  // Update remaining quantities
-  if (buyOrder) {
-    buyOrder.remainingQty -= trade.qty;
-    if (buyOrder.remainingQty <= 0) {
-      buyOrder.status = 'filled';
-      userOrders.delete(buyOrder.id);
-    }
-  }
-  
-  if (sellOrder) {
-    sellOrder.remainingQty -= trade.qty;
-    if (sellOrder.remainingQty <= 0) {
-      sellOrder.status = 'filled';
-      userOrders.delete(sellOrder.id);
+const processTrade = (trade) => {
+  // Each trade names the two orders it filled
+  for (const id of [trade.buyOrderId, trade.sellOrderId]) {
+    const order = userOrders.get(String(id));
+    if (!order) continue;
+    order.remainingQty -= trade.qty;
+    if (order.remainingQty <= 1e-9) {
+      order.status = 'filled';
+      userOrders.delete(String(id));
     }
   }
 };
@@ -147,6 +138,8 @@ const matchOrders = (marketId, marketType) => {
       id: Date.now() + Math.random(),
       buyer: bidOrder.user,
       seller: askOrder.user,
+      buyOrderId: bidOrder.id,
+      sellOrderId: askOrder.id,
       qty: tradeQty,
       price: tradePrice,
       market: marketType,
@@ -188,16 +181,15 @@ app.get('/health', (req, res) => {
   res.json({ 
     status: 'OK', 
     timestamp: new Date().toISOString(),
-    clobEngine: clobBinding ? 'Rust' : 'JavaScript (Fallback)'
+    clobEngine: clob ? 'Rust' : 'JavaScript (Fallback)'
   });
 });
 
 // Get all markets
 app.get('/api/markets', (req, res) => {
-  if (clobBinding) {
+  if (clob) {
     // Use Rust CLOB to get markets
     try {
-      const clob = new clobBinding.CLOBBinding();
       // For now, return a simple response - in a real implementation,
       // you'd query the Rust CLOB for market information
       res.json([{ id: 'default_market', createdAt: new Date().toISOString() }]);
@@ -228,16 +220,13 @@ app.post('/api/markets', (req, res) => {
     return res.status(400).json({ error: 'Market ID is required' });
   }
   
-  if (clobBinding) {
+  if (clob) {
     // Use Rust CLOB to create market
     try {
-      const clob = new clobBinding.CLOBBinding();
-      const result = clob.createMarket(marketId);
-      if (result) {
-        res.json({ message: 'Market created successfully', marketId });
-      } else {
-        res.status(500).json({ error: 'Failed to create market in CLOB engine' });
-      }
+      // New markets start pre-open and refuse orders until opened
+      clob.createMarket(marketId);
+      clob.marketCommand(CLOB_ADMIN, marketId, 'Open');
+      res.json({ message: 'Market created successfully', marketId });
     } catch (error) {
       console.error('Error creating market in Rust CLOB:', error);
       res.status(500).json({ error: 'Failed to create market in CLOB engine' });
@@ -254,29 +243,18 @@ app.get('/api/markets/:marketId/orderbook', (req, res) => {
   const { marketId } = req.params;
   const { market } = req.query; // 'YES' or 'NO'
   
-  if (clobBinding) {
+  if (clob) {
     // Use Rust CLOB to get order book
     try {
-      const clob = new clobBinding.CLOBBinding();
       const orderBook = clob.getOrderBookDepth(marketId, market);
-      
-      if (orderBook) {
-        res.json({
-          marketId,
-          market,
-          bids: [{ price: orderBook.bestBid, totalQty: orderBook.bidCount }],
-          asks: [{ price: orderBook.bestAsk, totalQty: orderBook.askCount }],
-          timestamp: new Date().toISOString()
-        });
-      } else {
-        res.json({
-          marketId,
-          market,
-          bids: [],
-          asks: [],
-          timestamp: new Date().toISOString()
-        });
-      }
+      const levels = (side) => side.map(({ price, qty }) => ({ price, totalQty: qty }));
+      res.json({
+        marketId,
+        market,
+        bids: levels(orderBook.bids),
+        asks: levels(orderBook.asks),
+        timestamp: new Date().toISOString()
+      });
     } catch (error) {
       console.error('Error getting order book from Rust CLOB:', error);
       res.status(500).json({ error: 'Failed to get order book from CLOB engine' });
@@ -328,7 +306,7 @@ app.get('/api/markets/:marketId/orderbook', (req, res) => {
 });
 
 // Place order
-app.post('/api/orders', (req, res) => {
+app.post('/api/orders', async (req, res) => {
   const { marketId, market, side, price, qty, user } = req.body;
   
   if (!marketId || !market || !side || price === undefined || !qty || !user) {
@@ -347,62 +325,43 @@ app.post('/api/orders', (req, res) => {
     return res.status(400).json({ error: 'Side must be Buy or Sell' });
   }
   
-  if (clobBinding) {
+  if (clob) {
     // Use Rust CLOB to place order
+    const order = {
+      id: nextOrderId++,
+      user,
+      side,
+      price,
+      qty,
+      timestamp: Math.floor(Date.now() / 1000),
+      market,
+      marketId
+    };
+
+    const orderId = String(order.id);
+
     try {
-      const clob = new clobBinding.CLOBBinding();
-      
-      const order = {
-        id: Date.now() + Math.random(),
-        user,
-        side,
-        price,
-        qty,
-        timestamp: Math.floor(Date.now() / 1000),
-        market,
-        marketId
-      };
-      
       // Store order in userOrders for tracking
-      userOrders.set(order.id, {
+      userOrders.set(orderId, {
         ...order,
+        id: orderId,
         status: 'active',
         remainingQty: order.qty
       });
       
-      const trade = clob.placeOrder(order);
+      // Every trade the order made, including any it set off
+      const trades = await clob.placeOrder(order);
+      trades.forEach(processTrade);
       
-      if (trade) {
-        // Convert trade to the expected format
-        const tradeObj = {
-          id: trade.id,
-          buyer: trade.buyer,
-          seller: trade.seller,
-          qty: trade.qty,
-          price: trade.price,
-          market: trade.market,
-          marketId: trade.marketId,
-          timestamp: trade.timestamp
-        };
-        
-        // Process the trade to update order statuses
-        processTrade(tradeObj, order);
-        
-        res.json({
-          message: 'Order placed successfully',
-          orderId: order.id,
-          trades: [tradeObj]
-        });
-      } else {
-        res.json({
-          message: 'Order placed successfully',
-          orderId: order.id,
-          trades: []
-        });
-      }
+      res.json({
+        message: 'Order placed successfully',
+        orderId,
+        trades: trades.map(tradeJson)
+      });
     } catch (error) {
-      console.error('Error placing order in Rust CLOB:', error);
-      res.status(500).json({ error: 'Failed to place order in CLOB engine' });
+      // The engine refused the order
+      userOrders.delete(orderId);
+      res.status(400).json({ error: error.message });
     }
   } else {
     // Fallback to JavaScript implementation
@@ -425,7 +384,7 @@ app.post('/api/orders', (req, res) => {
     
     // Add to orders map
     orders.set(order.id, order);
-    userOrders.set(order.id, order); // Add to userOrders map
+    userOrders.set(String(order.id), order); // Add to userOrders map
     
     // Add to appropriate order book
     const marketData = markets.get(marketId);
@@ -442,7 +401,7 @@ app.post('/api/orders', (req, res) => {
     
     // Process trades to update order statuses
     newTrades.forEach(trade => {
-      processTrade(trade, order);
+      processTrade(trade);
     });
     
     // Add trades to trades map
@@ -471,18 +430,11 @@ app.delete('/api/orders/:orderId', (req, res) => {
     return res.status(404).json({ error: 'Order not found' });
   }
   
-  if (clobBinding) {
+  if (clob) {
     // Use Rust CLOB to cancel order
     try {
-      const clob = new clobBinding.CLOBBinding();
-      
-      // Cancel order in the specific market
-      const marketId = order.marketId;
-      const market = order.market;
-      
-      // For Rust CLOB, we need to cancel in the specific YES/NO market
-      const fullMarketId = `${marketId}_${market}`;
-      const result = clob.cancelOrder(fullMarketId, order.id);
+      // The engine looks through every outcome book of the market
+      const result = clob.cancelOrder(order.marketId, BigInt(order.id));
       
       if (result) {
         // Remove from userOrders tracking
@@ -515,7 +467,7 @@ app.delete('/api/orders/:orderId', (req, res) => {
     // Remove from orders map
     orders.delete(orderId);
     // Remove from userOrders tracking
-    userOrders.delete(parseInt(orderId));
+    userOrders.delete(String(jsOrder.id));
     
     res.json({ message: 'Order cancelled successfully' });
   }
@@ -525,7 +477,7 @@ app.delete('/api/orders/:orderId', (req, res) => {
 app.get('/api/users/:user/orders', (req, res) => {
   const { user } = req.params;
   
-  if (clobBinding) {
+  if (clob) {
     // Return tracked user orders from userOrders map
     const userOrdersList = Array.from(userOrders.values()).filter(o => o.user === user);
     res.json(userOrdersList);
@@ -540,7 +492,7 @@ app.get('/api/users/:user/orders', (req, res) => {
 app.get('/api/trades', (req, res) => {
   const { marketId, market, limit = 100 } = req.query;
   
-  if (clobBinding) {
+  if (clob) {
     // Rust CLOB doesn't have trade history yet
     // Return empty array for now
     res.json([]);
@@ -564,6 +516,23 @@ app.get('/api/trades', (req, res) => {
   }
 });
 
+// Drive the engine's timed work (stop triggers, auctions, batches, trading end).
+// A tick may wait on the price feed, so a slow one is not stacked on.
+if (clob) {
+  let ticking = false;
+  setInterval(async () => {
+    if (ticking) return;
+    ticking = true;
+    try {
+      (await clob.tick(Math.floor(Date.now() / 1000))).forEach(processTrade);
+    } catch (error) {
+      console.error('CLOB tick failed:', error);
+    } finally {
+      ticking = false;
+    }
+  }, 1000);
+}
+
 // Error handling middleware
 app.use((err, req, res, next) => {
   console.error(err.stack);
@@ -580,7 +549,7 @@ app.listen(PORT, () => {
   console.log(`CLOB Backend API server running on port ${PORT}`);
   console.log(`Health check: http://localhost:${PORT}/health`);
   console.log(`API docs: http://localhost:${PORT}/api/markets`);
-  console.log(`CLOB Engine: ${clobBinding ? 'Rust' : 'JavaScript (Fallback)'}`);
+  console.log(`CLOB Engine: ${clob ? 'Rust' : 'JavaScript (Fallback)'}`);
 });
 
 module.exports = app;
\ No newline at end of file
@@ -0,0 +1,5 @@
+fn main() {
+    // Node resolves the N-API symbols when it loads the module
+    #[cfg(feature = "napi")]
+    napi_build::setup();
+}
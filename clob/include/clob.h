@@ -0,0 +1,124 @@
+/* Generated by cbindgen from src/ffi.rs. Do not edit by hand. */
+
+#ifndef CLOB_H
+#define CLOB_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#define CLOB_ABI_VERSION 3
+
+typedef struct FFIOrder {
+  uint32_t struct_size;
+  uint32_t version;
+  uint64_t id;
+  char *user;
+  uint8_t side;
+  double price;
+  double qty;
+  uint64_t timestamp;
+  char *market;
+  char *market_id;
+} FFIOrder;
+
+typedef struct FFITrade {
+  uint32_t struct_size;
+  uint32_t version;
+  uint64_t id;
+  char *buyer;
+  char *seller;
+  double qty;
+  double price;
+  char *market;
+  char *market_id;
+  uint64_t timestamp;
+} FFITrade;
+
+typedef struct FFITradeList {
+  uint32_t struct_size;
+  uint32_t version;
+  struct FFITrade *trades;
+  size_t len;
+} FFITradeList;
+
+typedef struct FFIOrderBook {
+  uint32_t struct_size;
+  uint32_t version;
+  double best_bid;
+  double best_ask;
+  uint32_t bid_count;
+  uint32_t ask_count;
+} FFIOrderBook;
+
+typedef struct FFIOrderIds {
+  uint32_t struct_size;
+  uint32_t version;
+  uint64_t *ids;
+  size_t len;
+} FFIOrderIds;
+
+typedef struct FFICancel {
+  uint32_t struct_size;
+  uint32_t version;
+  char *market_id;
+  char *market;
+  uint64_t order_id;
+} FFICancel;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+uint32_t clob_abi_version(void);
+
+int32_t clob_init(void);
+
+int32_t clob_create_market(const char *market_id);
+
+int32_t clob_place_order(const struct FFIOrder *ffi_order, struct FFITrade **trade);
+
+struct FFITradeList clob_tick(uint64_t now);
+
+int32_t clob_cancel_order(const char *market_id, uint64_t order_id);
+
+struct FFIOrderBook clob_get_top_of_book(const char *market_id, const char *market);
+
+int32_t clob_add_admin(const char *admin);
+
+int32_t clob_market_command(const char *caller,
+                            const char *market_id,
+                            uint8_t command,
+                            uint8_t winner);
+
+int32_t clob_market_state(const char *market_id);
+
+struct FFIOrderIds clob_cancel_all(const char *user, const char *market_id);
+
+struct FFIOrderIds clob_cancel_side(const char *user, const char *market_id, uint8_t side);
+
+struct FFIOrderIds clob_cancel_price_range(const char *user,
+                                           const char *market_id,
+                                           uint8_t side,
+                                           double low,
+                                           double high);
+
+void clob_free_order_ids(struct FFIOrderIds order_ids);
+
+struct FFITradeList clob_place_orders(const struct FFIOrder *orders, size_t len, int32_t *results);
+
+int32_t clob_cancel_orders(const struct FFICancel *cancels, size_t len, int32_t *results);
+
+void clob_free_trades(struct FFITradeList list);
+
+void clob_free_trade(struct FFITrade *trade);
+
+struct FFIOrderBook *clob_get_order_book_depth(const char *market_id, const char *market);
+
+void clob_free_order_book(struct FFIOrderBook *order_book);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CLOB_H */
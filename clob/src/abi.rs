@@ -0,0 +1,54 @@
+use sha3::{Digest, Keccak256};
+
+// --------------------- ABI Encoding ---------------------
+pub type Address = [u8; 20];
+pub type Word = [u8; 32];
+
+pub fn keccak(data: &[u8]) -> Word {
+    Keccak256::digest(data).into()
+}
+
+// Left-pad an unsigned integer to a 32-byte ABI word
+pub fn word(value: u128) -> Word {
+    let mut word = [0u8; 32];
+    word[16..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+pub fn address_word(address: &Address) -> Word {
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(address);
+    word
+}
+
+// First four bytes of keccak256 of the canonical signature, e.g. "transfer(address,uint256)"
+pub fn selector(signature: &str) -> [u8; 4] {
+    keccak(signature.as_bytes())[..4].try_into().unwrap()
+}
+
+// Calldata for a function whose arguments are all static (one word each)
+pub fn encode_call(signature: &str, args: &[Word]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(4 + 32 * args.len());
+    data.extend(selector(signature));
+    for arg in args {
+        data.extend(arg);
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_selectors() {
+        assert_eq!(
+            selector("transfer(address,uint256)"),
+            [0xa9, 0x05, 0x9c, 0xbb]
+        );
+        assert_eq!(
+            selector("transferFrom(address,address,uint256)"),
+            [0x23, 0xb8, 0x72, 0xdd]
+        );
+    }
+}
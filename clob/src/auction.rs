@@ -0,0 +1,151 @@
+use crate::{OrderBook, Price, Trade};
+use std::collections::BTreeSet;
+
+// --------------------- Call Auction ---------------------
+impl OrderBook {
+    // Enter a call period: orders keep resting without matching until `uncross`
+    pub fn start_auction(&mut self) {
+        self.in_auction = true;
+    }
+
+    // Price and volume the book would uncross at if the call ended now
+    pub fn indicative_uncross(&self) -> Option<(Price, f64)> {
+        let mut candidates: BTreeSet<Price> = self.bids.keys().copied().collect();
+        candidates.extend(self.asks.keys().copied());
+
+        // (price, executable volume, imbalance) for every candidate price
+        let quotes: Vec<(Price, f64, f64)> = candidates
+            .into_iter()
+            .map(|price| {
+                // Hidden iceberg reserve executes in the uncross too
+                let demand: f64 = self
+                    .bids
+                    .range(price..)
+                    .flat_map(|(_, orders)| orders.iter())
+                    .map(|o| o.total_qty())
+                    .sum();
+                let supply: f64 = self
+                    .asks
+                    .range(..=price)
+                    .flat_map(|(_, orders)| orders.iter())
+                    .map(|o| o.total_qty())
+                    .sum();
+                (price, demand.min(supply), (demand - supply).abs())
+            })
+            .collect();
+
+        // Maximise executed volume, then minimise the leftover imbalance
+        let max_volume = quotes.iter().map(|q| q.1).fold(0.0, f64::max);
+        if max_volume <= 0.0 {
+            return None;
+        }
+        let min_imbalance = quotes
+            .iter()
+            .filter(|q| q.1 == max_volume)
+            .map(|q| q.2)
+            .fold(f64::INFINITY, f64::min);
+        let best: Vec<Price> = quotes
+            .iter()
+            .filter(|q| q.1 == max_volume && q.2 == min_imbalance)
+            .map(|q| q.0)
+            .collect();
+
+        // Remaining ties span a price range; take its (lower) middle
+        Some((best[(best.len() - 1) / 2], max_volume))
+    }
+
+    // End the call period: execute everything marketable at the single clearing
+    // price, then return to continuous matching for whatever is left
+    pub fn uncross(&mut self, now: u64) -> Vec<Trade> {
+        self.in_auction = false;
+        let mut trades = match self.indicative_uncross() {
+            Some((price, _)) => self.match_at(Some(price), now),
+            None => Vec::new(),
+        };
+        trades.extend(self.match_orders_at(now));
+        trades
+    }
+
+    // Frequent batch mode: clear one batch at its uniform price. The book stays
+    // in call mode, so anything left keeps resting for the next batch.
+    pub fn clear_batch(&mut self, now: u64) -> Vec<Trade> {
+        match self.indicative_uncross() {
+            Some((price, _)) => self.match_at(Some(price), now),
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_test_order;
+    use crate::{OrderBook, Price, Side, YES};
+
+    #[test]
+    fn test_auction_collects_without_matching() {
+        let mut book = OrderBook::new("test_market", YES);
+        book.start_auction();
+        book.add_order(create_test_order(1, "user1", Side::Buy, 0.6, 100.0));
+        book.add_order(create_test_order(2, "user2", Side::Sell, 0.4, 100.0));
+
+        assert_eq!(book.bids.len(), 1);
+        assert_eq!(book.asks.len(), 1);
+        assert_eq!(book.indicative_uncross(), Some((Price(0.4), 100.0)));
+    }
+
+    #[test]
+    fn test_uncross_maximises_volume_at_single_price() {
+        let mut book = OrderBook::new("test_market", YES);
+        book.start_auction();
+        book.add_order(create_test_order(1, "user1", Side::Buy, 0.7, 50.0));
+        book.add_order(create_test_order(2, "user2", Side::Buy, 0.6, 50.0));
+        book.add_order(create_test_order(3, "user3", Side::Buy, 0.5, 50.0));
+        book.add_order(create_test_order(4, "user4", Side::Sell, 0.4, 40.0));
+        book.add_order(create_test_order(5, "user5", Side::Sell, 0.6, 60.0));
+        book.add_order(create_test_order(6, "user6", Side::Sell, 0.8, 50.0));
+
+        // At 0.6: demand 100, supply 100
+        assert_eq!(book.indicative_uncross(), Some((Price(0.6), 100.0)));
+
+        let trades = book.uncross(100);
+        assert!(!book.in_auction);
+        assert!(trades
+            .iter()
+            .all(|t| t.price == Price(0.6) && t.timestamp == 100));
+        assert_eq!(trades.iter().map(|t| t.qty).sum::<f64>(), 100.0);
+
+        // Only the bid at 0.5 and the ask at 0.8 remain
+        assert_eq!(book.get_top_of_book(), (Price(0.5), Price(0.8)));
+    }
+
+    #[test]
+    fn test_clear_batch_stays_in_call_mode() {
+        let mut book = OrderBook::new("test_market", YES);
+        book.start_auction();
+        book.add_order(create_test_order(1, "user1", Side::Buy, 0.7, 100.0));
+        book.add_order(create_test_order(2, "user2", Side::Sell, 0.5, 60.0));
+
+        let trades = book.clear_batch(0);
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].qty, 60.0);
+        assert!(book.in_auction);
+
+        // Next batch's orders still rest until cleared
+        book.add_order(create_test_order(3, "user3", Side::Sell, 0.6, 40.0));
+        assert_eq!(book.asks.len(), 1);
+        assert_eq!(book.clear_batch(0)[0].price, Price(0.6));
+        assert!(book.bids.is_empty() && book.asks.is_empty());
+    }
+
+    #[test]
+    fn test_uncross_without_crossing_orders() {
+        let mut book = OrderBook::new("test_market", YES);
+        book.start_auction();
+        book.add_order(create_test_order(1, "user1", Side::Buy, 0.4, 10.0));
+        book.add_order(create_test_order(2, "user2", Side::Sell, 0.6, 10.0));
+
+        assert_eq!(book.indicative_uncross(), None);
+        assert!(book.uncross(0).is_empty());
+        assert_eq!(book.bids.len(), 1);
+    }
+}
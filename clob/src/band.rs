@@ -0,0 +1,122 @@
+use crate::error::EngineError;
+use crate::market::{OutcomeId, NO, YES};
+
+// --------------------- Band Markets ---------------------
+// Outcome codes used by the factory's claim ids
+pub const WITHIN: OutcomeId = YES; // basket settles inside [lower, upper]
+pub const OUTSIDE: OutcomeId = NO; // basket settles below lower or above upper
+
+// 1e18 fixed point, as used by BasketPricer and the market contracts
+pub const ONE_E18: i128 = 1_000_000_000_000_000_000;
+pub const MAX_BAND_BPS: u16 = 10_000;
+
+// The definition the factory creates a market from. Prices and weights are
+// 1e18 fixed point, the same values the `MarketCreated` event carries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BandMarket {
+    pub symbols: Vec<String>,
+    pub weights_1e18: Vec<i128>,
+    pub strike_1e18: i128, // basket price when the market was created
+    pub lower_1e18: i128,
+    pub upper_1e18: i128,
+    pub band_bps: u16,
+    pub settle_ts: u64, // unix seconds; trading ends and the market settles here
+}
+
+impl BandMarket {
+    // Validate like `EtfPredictionMarketFactory.create` and derive the bounds
+    // with `BasketPricer.boundsForBand`
+    pub fn new(
+        symbols: Vec<String>,
+        weights_1e18: Vec<i128>,
+        strike_1e18: i128,
+        band_bps: u16,
+        settle_ts: u64,
+    ) -> Result<Self, EngineError> {
+        if band_bps == 0 {
+            return Err(EngineError::InvalidBand("bad band".to_string()));
+        }
+        if symbols.is_empty() || symbols.len() != weights_1e18.len() {
+            return Err(EngineError::InvalidBand("len mismatch".to_string()));
+        }
+        if weights_1e18.iter().any(|&w| w < 0) {
+            return Err(EngineError::InvalidBand("weight<0".to_string()));
+        }
+        let (lower_1e18, upper_1e18) = bounds_for_band(strike_1e18, band_bps)?;
+        Ok(Self {
+            symbols,
+            weights_1e18,
+            strike_1e18,
+            lower_1e18,
+            upper_1e18,
+            band_bps,
+            settle_ts,
+        })
+    }
+
+    // Winning outcome for a final basket price, as in `EtfPredictionMarket.settle`
+    pub fn outcome_at(&self, final_price_1e18: i128) -> OutcomeId {
+        if final_price_1e18 < self.lower_1e18 || final_price_1e18 > self.upper_1e18 {
+            OUTSIDE
+        } else {
+            WITHIN
+        }
+    }
+}
+
+// Port of `BasketPricer.boundsForBand`; division truncates toward zero like Solidity
+pub fn bounds_for_band(price_1e18: i128, band_bps: u16) -> Result<(i128, i128), EngineError> {
+    if price_1e18 <= 0 {
+        return Err(EngineError::InvalidBand("nav<=0".to_string()));
+    }
+    if band_bps > MAX_BAND_BPS {
+        return Err(EngineError::InvalidBand("band>100%".to_string()));
+    }
+    let band = band_bps as i128;
+    let lower = price_1e18 * (10_000 - band) / 10_000;
+    let upper = price_1e18 * (10_000 + band) / 10_000;
+    Ok((lower, upper))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(band_bps: u16) -> Result<BandMarket, EngineError> {
+        BandMarket::new(
+            vec!["SPY".to_string(), "QQQ".to_string()],
+            vec![ONE_E18 / 2, ONE_E18 / 2],
+            500 * ONE_E18,
+            band_bps,
+            1_700_000_000,
+        )
+    }
+
+    #[test]
+    fn test_bounds_match_basket_pricer() {
+        let market = definition(250).unwrap();
+        assert_eq!(market.lower_1e18, 4875 * ONE_E18 / 10);
+        assert_eq!(market.upper_1e18, 5125 * ONE_E18 / 10);
+
+        // Truncates like Solidity's signed division
+        assert_eq!(bounds_for_band(3, 5_000).unwrap(), (1, 4));
+    }
+
+    #[test]
+    fn test_rejects_what_the_factory_rejects() {
+        assert!(definition(0).is_err());
+        assert!(definition(10_001).is_err());
+        assert!(bounds_for_band(0, 100).is_err());
+        assert!(BandMarket::new(vec!["SPY".to_string()], vec![], ONE_E18, 100, 0).is_err());
+        assert!(BandMarket::new(vec!["SPY".to_string()], vec![-1], ONE_E18, 100, 0).is_err());
+    }
+
+    #[test]
+    fn test_outcome_at_bounds_is_within() {
+        let market = definition(100).unwrap();
+        assert_eq!(market.outcome_at(market.lower_1e18), WITHIN);
+        assert_eq!(market.outcome_at(market.upper_1e18), WITHIN);
+        assert_eq!(market.outcome_at(market.lower_1e18 - 1), OUTSIDE);
+        assert_eq!(market.outcome_at(market.upper_1e18 + 1), OUTSIDE);
+    }
+}
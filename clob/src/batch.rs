@@ -0,0 +1,176 @@
+use crate::error::EngineError;
+use crate::market::OutcomeId;
+use crate::{MatchingEngine, Order, Trade};
+
+// --------------------- Batch Orders ---------------------
+#[derive(Clone, Debug)]
+pub struct BatchOrder {
+    pub market_id: String,
+    pub outcome: OutcomeId,
+    pub order: Order,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchCancel {
+    pub market_id: String,
+    pub outcome: OutcomeId,
+    pub order_id: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BatchResult {
+    pub results: Vec<Result<(), EngineError>>, // one per order, in batch order
+    pub trades: Vec<Trade>,
+}
+
+// Market ids in order of first appearance, each with the batch positions it owns
+fn group_by_market<'a>(market_ids: impl Iterator<Item = &'a str>) -> Vec<(&'a str, Vec<usize>)> {
+    let mut groups: Vec<(&str, Vec<usize>)> = Vec::new();
+    for (i, market_id) in market_ids.enumerate() {
+        match groups.iter_mut().find(|(id, _)| *id == market_id) {
+            Some((_, positions)) => positions.push(i),
+            None => groups.push((market_id, vec![i])),
+        }
+    }
+    groups
+}
+
+impl MatchingEngine {
+    // Place many orders in one call. The orders of each market go in all
+    // together or not at all: if one is rejected, that market is restored and
+    // the rest of its orders fail with `BatchRejected`. Other markets are unaffected.
+    pub fn place_orders(&mut self, batch: Vec<BatchOrder>) -> BatchResult {
+        let groups = group_by_market(batch.iter().map(|b| b.market_id.as_str()));
+        let mut results: Vec<Result<(), EngineError>> = vec![Ok(()); batch.len()];
+        let mut trades = Vec::new();
+
+        for (market_id, positions) in groups {
+            let mut failed = 0;
+            let placed = self.undo_on_error(market_id, |engine| {
+                let mut market_trades = Vec::new();
+                for &i in &positions {
+                    failed = i;
+                    let entry = &batch[i];
+                    market_trades.extend(engine.place_order(
+                        market_id,
+                        entry.outcome,
+                        entry.order.clone(),
+                    )?);
+                }
+                Ok(market_trades)
+            });
+
+            match placed {
+                Ok(market_trades) => trades.extend(market_trades),
+                Err(error) => {
+                    // A tripped breaker stays tripped even though the orders are undone
+                    for &i in &positions {
+                        results[i] = Err(EngineError::BatchRejected(market_id.to_string()));
+                    }
+                    results[failed] = Err(error);
+                }
+            }
+        }
+        BatchResult { results, trades }
+    }
+
+    // Cancel many orders in one call, returning whether each one was found.
+    // Nothing matches between the cancels, so a market's quotes are pulled together.
+    pub fn cancel_orders(&mut self, batch: &[BatchCancel]) -> Vec<bool> {
+        batch
+            .iter()
+            .map(|c| self.cancel_order(&c.market_id, c.outcome, c.order_id))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_test_order;
+    use crate::{MarketCommand, Price, Side, YES};
+
+    fn entry(market_id: &str, id: u64, side: Side, price: f64) -> BatchOrder {
+        BatchOrder {
+            market_id: market_id.to_string(),
+            outcome: YES,
+            order: create_test_order(id, "mm", side, price, 10.0),
+        }
+    }
+
+    #[test]
+    fn test_batch_is_atomic_per_market() {
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        for market in ["a", "b"] {
+            engine.create_market(market);
+            engine
+                .apply_market_command("admin", market, MarketCommand::Open)
+                .unwrap();
+        }
+
+        let result = engine.place_orders(vec![
+            entry("a", 1, Side::Buy, 0.4),
+            entry("b", 2, Side::Buy, 0.4),
+            entry("a", 3, Side::Sell, 0.4), // crosses order 1
+            entry("b", 4, Side::Sell, 1.5), // invalid price
+        ]);
+        assert_eq!(result.trades.len(), 1);
+        assert_eq!(result.results[0], Ok(()));
+        assert_eq!(result.results[2], Ok(()));
+        assert_eq!(
+            result.results[1],
+            Err(EngineError::BatchRejected("b".to_string()))
+        );
+        assert_eq!(result.results[3], Err(EngineError::InvalidPrice(1.5)));
+        assert!(engine.book("b", YES).unwrap().bids.is_empty());
+
+        engine.place_orders(vec![
+            entry("b", 5, Side::Buy, 0.3),
+            entry("b", 6, Side::Sell, 0.7),
+        ]);
+        let cancels = [5, 6, 7].map(|order_id| BatchCancel {
+            market_id: "b".to_string(),
+            outcome: YES,
+            order_id,
+        });
+        assert_eq!(engine.cancel_orders(&cancels), vec![true, true, false]);
+        assert_eq!(engine.get_top_of_book("b", YES), (Price(0.0), Price(1.0)));
+    }
+
+    #[test]
+    fn test_rejected_batch_undoes_its_trades() {
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        engine.create_market("a");
+        engine
+            .apply_market_command("admin", "a", MarketCommand::Open)
+            .unwrap();
+        for id in [1, 2] {
+            engine
+                .place_yes_order("a", create_test_order(id, "alice", Side::Sell, 0.5, 10.0))
+                .unwrap();
+        }
+
+        // The first order takes all of alice's order 1 and part of order 2
+        let result = engine.place_orders(vec![
+            BatchOrder {
+                market_id: "a".to_string(),
+                outcome: YES,
+                order: create_test_order(3, "bob", Side::Buy, 0.5, 15.0),
+            },
+            entry("a", 4, Side::Sell, 1.5),
+        ]);
+        assert!(result.trades.is_empty());
+        assert_eq!(result.results[1], Err(EngineError::InvalidPrice(1.5)));
+
+        // Both asks are back in their old order, and nothing is owed
+        let book = engine.book("a", YES).unwrap();
+        let ids: Vec<u64> = book.asks[&Price(0.5)].iter().map(|o| o.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+        assert!(book.asks[&Price(0.5)].iter().all(|o| o.qty == 10.0));
+        assert!(book.bids.is_empty());
+        assert_eq!(book.last_trade_price, None);
+        assert!(engine.unsettled.is_empty() && engine.unsettled_claims.is_empty());
+    }
+}
@@ -0,0 +1,59 @@
+// Standalone CLOB server: the REST API and WebSocket feed the frontend talks
+// to, without the Node backend in between.
+//
+//   cargo run --features server --bin clob-server
+//
+// Listens on $PORT (default 3001, the Node backend's port). Opens the
+// comma-separated markets in $CLOB_MARKETS at startup; POST /api/markets
+// creates more only with `Authorization: Bearer $CLOB_ADMIN_TOKEN`, and is
+// off when that is unset.
+
+use clob::server::{router, ServerState};
+use clob::MatchingEngine;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[tokio::main]
+async fn main() {
+    let port: u16 = std::env::var("PORT")
+        .ok()
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(3001);
+    let admin_token = std::env::var("CLOB_ADMIN_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty());
+    let state = ServerState::new(MatchingEngine::new(), admin_token);
+    for market_id in std::env::var("CLOB_MARKETS").unwrap_or_default().split(',') {
+        let market_id = market_id.trim();
+        if !market_id.is_empty() {
+            state.open_market(market_id).expect("failed to open market");
+        }
+    }
+
+    // Auctions, batches, session expiry and settlement run off the clock.
+    // Settlement may call out to the price feed, so the tick runs off the
+    // async workers, and without the engine locked while it waits.
+    let ticker = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            interval.tick().await;
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            let ticker = ticker.clone();
+            if let Err(e) = tokio::task::spawn_blocking(move || ticker.tick(now)).await {
+                eprintln!("tick failed: {}", e);
+            }
+        }
+    });
+
+    let listener = tokio::net::TcpListener::bind(("0.0.0.0", port))
+        .await
+        .expect("failed to bind port");
+    println!("CLOB server running on port {}", port);
+    println!("Health check: http://localhost:{}/health", port);
+    axum::serve(listener, router(state))
+        .await
+        .expect("server error");
+}
@@ -0,0 +1,118 @@
+use crate::signing::same_user;
+use crate::stops::StopOrder;
+use crate::{MatchingEngine, Order, Price, Side};
+
+// --------------------- Mass Cancel ---------------------
+impl MatchingEngine {
+    // Cancel resting and pending stop orders selected by `cancel`, in one
+    // market or in all of them
+    fn cancel_matching(
+        &mut self,
+        market_id: Option<&str>,
+        cancel: impl Fn(&Order) -> bool,
+    ) -> Vec<Order> {
+        let mut cancelled = Vec::new();
+        let markets = self
+            .markets
+            .values_mut()
+            .filter(|m| market_id.is_none_or(|id| m.id == id));
+        for market in markets {
+            for book in market.books.values_mut() {
+                cancelled.extend(book.cancel_where(&cancel));
+            }
+            for triggers in market.triggers.values_mut() {
+                let (gone, kept): (Vec<StopOrder>, Vec<StopOrder>) =
+                    triggers.stops.drain(..).partition(|s| cancel(&s.order));
+                triggers.stops = kept;
+                cancelled.extend(gone.into_iter().map(|s| s.order));
+            }
+        }
+        cancelled
+    }
+
+    // Kill switch: every order of `user`, in one market or everywhere
+    pub fn cancel_all(&mut self, user: &str, market_id: Option<&str>) -> Vec<Order> {
+        self.cancel_matching(market_id, |o| same_user(&o.user, user))
+    }
+
+    pub fn cancel_side(&mut self, user: &str, market_id: &str, side: Side) -> Vec<Order> {
+        self.cancel_matching(Some(market_id), |o| {
+            same_user(&o.user, user) && o.side == side
+        })
+    }
+
+    // Orders of one side priced within [low, high]
+    pub fn cancel_price_range(
+        &mut self,
+        user: &str,
+        market_id: &str,
+        side: Side,
+        low: Price,
+        high: Price,
+    ) -> Vec<Order> {
+        self.cancel_matching(Some(market_id), |o| {
+            same_user(&o.user, user) && o.side == side && o.price >= low && o.price <= high
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_test_order;
+    use crate::{MarketCommand, MatchingEngine, Order, Price, Side, StopKind, StopOrder, NO, YES};
+
+    fn ids(orders: Vec<Order>) -> Vec<u64> {
+        let mut ids: Vec<u64> = orders.iter().map(|o| o.id).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_mass_cancel() {
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        for market in ["a", "b"] {
+            engine.create_market(market);
+            engine
+                .apply_market_command("admin", market, MarketCommand::Open)
+                .unwrap();
+        }
+        engine
+            .place_order("a", YES, create_test_order(1, "mm", Side::Buy, 0.40, 10.0))
+            .unwrap();
+        engine
+            .place_order("a", YES, create_test_order(2, "mm", Side::Buy, 0.45, 10.0))
+            .unwrap();
+        engine
+            .place_order("a", NO, create_test_order(3, "mm", Side::Sell, 0.70, 10.0))
+            .unwrap();
+        engine
+            .place_order(
+                "a",
+                YES,
+                create_test_order(4, "alice", Side::Buy, 0.45, 10.0),
+            )
+            .unwrap();
+        engine
+            .place_order("b", YES, create_test_order(5, "mm", Side::Sell, 0.60, 10.0))
+            .unwrap();
+        let stop = StopOrder {
+            order: create_test_order(6, "mm", Side::Buy, 0.9, 10.0),
+            trigger: Price(0.8),
+            kind: StopKind::Limit,
+        };
+        engine.place_stop_order("b", YES, stop).unwrap();
+
+        assert_eq!(
+            ids(engine.cancel_price_range("mm", "a", Side::Buy, Price(0.42), Price(0.5))),
+            vec![2]
+        );
+        assert_eq!(ids(engine.cancel_side("mm", "a", Side::Sell)), vec![3]);
+        assert_eq!(ids(engine.cancel_all("mm", Some("a"))), vec![1]);
+        assert_eq!(ids(engine.cancel_all("mm", None)), vec![5, 6]);
+        assert!(engine.cancel_all("mm", None).is_empty());
+
+        // Other users' orders are untouched
+        assert_eq!(engine.get_top_of_book("a", YES).0, Price(0.45));
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{Order, OrderBook, Price, Side, Trade};
+use std::collections::VecDeque;
+
+// --------------------- Circuit Breakers ---------------------
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CircuitBreaker {
+    pub max_move: f64,       // halt if the last trade price moves more than this...
+    pub window_secs: u64,    // ...within this many seconds
+    pub band: f64,           // max distance from the reference price an aggressive order may reach
+    pub cool_down_secs: u64, // halt length before trading resumes automatically
+    pub auction_secs: u64,   // re-opening call run when the cool-down ends
+}
+
+// Recent trade prices of one book, oldest first
+#[derive(Clone, Debug, Default)]
+pub struct PriceHistory {
+    trades: VecDeque<(u64, Price)>,
+}
+
+impl PriceHistory {
+    // Last traded price; bands are measured from here
+    pub fn reference_price(&self) -> Option<Price> {
+        self.trades.back().map(|(_, price)| *price)
+    }
+
+    pub fn record(&mut self, now: u64, trades: &[Trade], window_secs: u64) {
+        self.trades
+            .extend(trades.iter().map(|trade| (now, trade.price)));
+        while self
+            .trades
+            .front()
+            .is_some_and(|(at, _)| at + window_secs < now)
+        {
+            self.trades.pop_front();
+        }
+    }
+
+    // High minus low of the trades still inside the window
+    pub fn range(&self) -> f64 {
+        let prices = self.trades.iter().map(|(_, price)| price.0);
+        let high = prices.clone().fold(f64::MIN, f64::max);
+        let low = prices.fold(f64::MAX, f64::min);
+        (high - low).max(0.0)
+    }
+
+    // Forget the window after a trip but keep the reference price
+    pub fn reset(&mut self) {
+        let last = self.trades.pop_back();
+        self.trades.clear();
+        self.trades.extend(last);
+    }
+}
+
+impl CircuitBreaker {
+    pub fn move_exceeded(&self, history: &PriceHistory) -> bool {
+        history.range() > self.max_move
+    }
+
+    pub fn beyond_band(&self, price: Price, reference: Price) -> bool {
+        (price.0 - reference.0).abs() > self.band
+    }
+
+    // True if `order` would trade further than `band` from the reference price
+    pub fn walks_beyond_band(&self, book: &OrderBook, order: &Order, reference: Price) -> bool {
+        book.sweep_price(order)
+            .is_some_and(|worst| self.beyond_band(worst, reference))
+    }
+}
+
+impl OrderBook {
+    // Worst price an incoming order would reach if it traded against the book
+    // now. An iceberg keeps refilling as it trades, so all of it counts.
+    pub fn sweep_price(&self, order: &Order) -> Option<Price> {
+        let levels: Box<dyn Iterator<Item = (&Price, &Vec<Order>)>> = match order.side {
+            Side::Buy => Box::new(self.asks.range(..=order.price)),
+            Side::Sell => Box::new(self.bids.range(order.price..).rev()),
+        };
+
+        let mut remaining = order.total_qty();
+        let mut worst = None;
+        for (price, orders) in levels {
+            if remaining <= 0.0 {
+                break;
+            }
+            worst = Some(*price);
+            remaining -= orders.iter().map(|o| o.total_qty()).sum::<f64>();
+        }
+        worst
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_test_order;
+    use crate::YES;
+
+    fn trade(price: f64) -> Trade {
+        Trade {
+            buyer: "alice".to_string(),
+            seller: "bob".to_string(),
+            buy_order_id: 1,
+            sell_order_id: 2,
+            qty: 1.0,
+            price: Price(price),
+            market_id: "test_market".to_string(),
+            outcome: YES,
+            timestamp: 0,
+        }
+    }
+
+    const BREAKER: CircuitBreaker = CircuitBreaker {
+        max_move: 0.1,
+        window_secs: 10,
+        band: 0.1,
+        cool_down_secs: 30,
+        auction_secs: 5,
+    };
+
+    #[test]
+    fn test_sweep_price_walks_levels() {
+        let mut book = OrderBook::new("test_market", YES);
+        book.add_order(create_test_order(1, "user1", Side::Sell, 0.50, 10.0));
+        book.add_order(create_test_order(2, "user2", Side::Sell, 0.55, 10.0));
+        book.add_order(create_test_order(3, "user3", Side::Sell, 0.70, 10.0));
+
+        assert_eq!(
+            book.sweep_price(&create_test_order(4, "user4", Side::Buy, 0.9, 5.0)),
+            Some(Price(0.50))
+        );
+        assert_eq!(
+            book.sweep_price(&create_test_order(5, "user5", Side::Buy, 0.9, 15.0)),
+            Some(Price(0.55))
+        );
+        assert_eq!(
+            book.sweep_price(&create_test_order(6, "user6", Side::Buy, 0.6, 50.0)),
+            Some(Price(0.55))
+        );
+        assert_eq!(
+            book.sweep_price(&create_test_order(7, "user7", Side::Sell, 0.1, 5.0)),
+            None
+        );
+
+        let within = create_test_order(8, "user8", Side::Buy, 0.9, 15.0);
+        let beyond = create_test_order(9, "user9", Side::Buy, 0.9, 25.0);
+        assert!(!BREAKER.walks_beyond_band(&book, &within, Price(0.5)));
+        assert!(BREAKER.walks_beyond_band(&book, &beyond, Price(0.5)));
+
+        // Showing 5 of 25 still sweeps all 25
+        let iceberg = beyond.with_iceberg(5.0).unwrap();
+        assert_eq!(book.sweep_price(&iceberg), Some(Price(0.70)));
+        assert!(BREAKER.walks_beyond_band(&book, &iceberg, Price(0.5)));
+    }
+
+    #[test]
+    fn test_move_within_window() {
+        let mut history = PriceHistory::default();
+        history.record(100, &[trade(0.50)], BREAKER.window_secs);
+        history.record(105, &[trade(0.58)], BREAKER.window_secs);
+        assert!(!BREAKER.move_exceeded(&history));
+
+        history.record(108, &[trade(0.62)], BREAKER.window_secs);
+        assert!(BREAKER.move_exceeded(&history));
+
+        // The 0.50 print has left the window
+        history.record(111, &[trade(0.60)], BREAKER.window_secs);
+        assert!(!BREAKER.move_exceeded(&history));
+
+        history.reset();
+        assert_eq!(history.reference_price(), Some(Price(0.60)));
+        assert_eq!(history.range(), 0.0);
+    }
+}
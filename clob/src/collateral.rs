@@ -0,0 +1,389 @@
+use crate::error::EngineError;
+use crate::ledger::VaultLog;
+use crate::market::OutcomeId;
+use crate::settlement::claim_id;
+use crate::signing::{format_address, parse_address, same_user, user_key};
+use crate::stops::StopKind;
+use crate::{EngineEvent, MatchingEngine, Order, Side, Trade};
+use std::collections::{BTreeSet, HashMap};
+
+// --------------------- Withdrawal Safety ---------------------
+impl MatchingEngine {
+    // Collateral a user has promised: resting and pending stop bids at their
+    // limit (stop-markets at 1.0) plus trades bought but not yet settled
+    pub fn committed(&self, user: &str) -> f64 {
+        let resting: f64 = self
+            .markets
+            .values()
+            .flat_map(|m| m.books.values())
+            .flat_map(|book| book.bids.values().flatten())
+            .filter(|o| same_user(&o.user, user))
+            .map(|o| o.total_qty() * o.price.0)
+            .sum();
+        let stops: f64 = self
+            .markets
+            .values()
+            .flat_map(|m| m.triggers.values())
+            .flat_map(|triggers| triggers.stops.iter())
+            .filter(|s| same_user(&s.order.user, user) && s.order.side == Side::Buy)
+            .map(|s| match s.kind {
+                StopKind::Market => s.order.total_qty(),
+                StopKind::Limit => s.order.total_qty() * s.order.price.0,
+            })
+            .sum();
+        resting + stops + self.unsettled.get(&user_key(user)).copied().unwrap_or(0.0)
+    }
+
+    // Claims a user has promised on one book: resting and pending stop asks
+    // plus trades sold but not yet settled
+    pub fn committed_claims(&self, user: &str, market_id: &str, outcome: OutcomeId) -> f64 {
+        let Some(market) = self.markets.get(market_id) else {
+            return 0.0;
+        };
+        let resting: f64 = market
+            .books
+            .get(&outcome)
+            .into_iter()
+            .flat_map(|book| book.asks.values().flatten())
+            .filter(|o| same_user(&o.user, user))
+            .map(|o| o.total_qty())
+            .sum();
+        let stops: f64 = market
+            .triggers
+            .get(&outcome)
+            .into_iter()
+            .flat_map(|triggers| triggers.stops.iter())
+            .filter(|s| same_user(&s.order.user, user) && s.order.side == Side::Sell)
+            .map(|s| s.order.total_qty())
+            .sum();
+        let sold = self
+            .unsettled_claims
+            .get(&(user_key(user), market_id.to_string(), outcome))
+            .copied()
+            .unwrap_or(0.0);
+        resting + stops + sold
+    }
+
+    // Vault collateral of a user, in price units
+    pub fn vault_balance(&self, user: &str) -> f64 {
+        parse_address(user).map_or(0.0, |address| {
+            self.ledger.cash(&address) as f64 / self.collateral_units
+        })
+    }
+
+    // Vault claims of a user on one book, in shares. Only markets named by
+    // their contract address have claims in the vault.
+    pub fn vault_claims(&self, user: &str, market_id: &str, outcome: OutcomeId) -> f64 {
+        match (parse_address(user), parse_address(market_id)) {
+            (Ok(user), Ok(market)) => {
+                self.ledger.claims(&user, &claim_id(&market, outcome)) as f64 / self.claim_units
+            }
+            _ => 0.0,
+        }
+    }
+
+    // What the user can take out of the vault without leaving orders or fills unfunded
+    pub fn withdrawable(&self, user: &str) -> f64 {
+        (self.vault_balance(user) - self.committed(user)).max(0.0)
+    }
+
+    // Claims the user can take out of the vault without leaving asks unbacked
+    pub fn withdrawable_claims(&self, user: &str, market_id: &str, outcome: OutcomeId) -> f64 {
+        (self.vault_claims(user, market_id, outcome)
+            - self.committed_claims(user, market_id, outcome))
+        .max(0.0)
+    }
+
+    // Apply vault logs to the ledger, then cancel every order of a user whose
+    // balance no longer covers their commitments. Returns the cancelled orders.
+    // Logs that fail leave the ledger, and so every order, as it was.
+    pub fn ingest_vault_logs(&mut self, logs: &[VaultLog]) -> Result<Vec<Order>, EngineError> {
+        self.ledger.ingest(logs)?;
+        let users: BTreeSet<String> = logs
+            .iter()
+            .map(|log| format_address(&log.event.user()))
+            .collect();
+        Ok(users
+            .iter()
+            .flat_map(|user| self.enforce_collateral(user))
+            .collect())
+    }
+
+    pub fn enforce_collateral(&mut self, user: &str) -> Vec<Order> {
+        let claims_short = self
+            .markets
+            .values()
+            .filter(|m| parse_address(&m.id).is_ok())
+            .flat_map(|m| m.outcomes.iter().map(move |&outcome| (&m.id, outcome)))
+            .any(|(market_id, outcome)| {
+                self.vault_claims(user, market_id, outcome)
+                    < self.committed_claims(user, market_id, outcome)
+            });
+        if !claims_short && self.vault_balance(user) >= self.committed(user) {
+            return Vec::new();
+        }
+        self.cancel_all(user, None)
+    }
+
+    pub(crate) fn track_unsettled(&mut self, trades: &[Trade]) {
+        for trade in trades {
+            let (buyer, sold) = (user_key(&trade.buyer), sold_key(trade));
+            self.log_unsettled(&buyer, &sold);
+            *self.unsettled.entry(buyer).or_default() += trade.qty * trade.price.0;
+            *self.unsettled_claims.entry(sold).or_default() += trade.qty;
+        }
+    }
+
+    pub(crate) fn track_event_trades(&mut self, events: &[EngineEvent]) {
+        let trades: Vec<Trade> = events
+            .iter()
+            .filter_map(|event| match event {
+                EngineEvent::Trade(trade) => Some(trade.clone()),
+                _ => None,
+            })
+            .collect();
+        self.track_unsettled(&trades);
+    }
+
+    // Release the commitments of trades once their settlement batch has landed
+    pub fn mark_settled(&mut self, trades: &[Trade]) {
+        for trade in trades {
+            release(
+                &mut self.unsettled,
+                user_key(&trade.buyer),
+                trade.qty * trade.price.0,
+            );
+            release(&mut self.unsettled_claims, sold_key(trade), trade.qty);
+        }
+    }
+}
+
+// The seller and book whose claims a trade owes
+fn sold_key(trade: &Trade) -> (String, String, OutcomeId) {
+    (
+        user_key(&trade.seller),
+        trade.market_id.clone(),
+        trade.outcome,
+    )
+}
+
+fn release<K: std::hash::Hash + Eq>(owed: &mut HashMap<K, f64>, key: K, amount: f64) {
+    if let Some(left) = owed.get_mut(&key) {
+        *left = (*left - amount).max(0.0);
+        if *left <= f64::EPSILON {
+            owed.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::abi::Address;
+    use crate::create_test_order;
+    use crate::ledger::{VaultEvent, VaultLog};
+    use crate::settlement::claim_id;
+    use crate::signing::format_address;
+    use crate::{MarketCommand, MatchingEngine, Side, YES};
+
+    const ALICE: Address = [0x01; 20];
+    const MARKET: Address = [0xaa; 20];
+
+    fn log(block: u64, event: VaultEvent) -> VaultLog {
+        VaultLog {
+            block_number: block,
+            block_hash: [block as u8; 32],
+            log_index: 0,
+            removed: false,
+            event,
+        }
+    }
+
+    #[test]
+    fn test_withdrawable_and_auto_cancel() {
+        let alice = format_address(&ALICE);
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        engine.create_market("m");
+        engine
+            .apply_market_command("admin", "m", MarketCommand::Open)
+            .unwrap();
+        engine
+            .ingest_vault_logs(&[log(
+                1,
+                VaultEvent::Deposited {
+                    user: ALICE,
+                    amount: 100_000_000,
+                },
+            )])
+            .unwrap();
+        assert_eq!(engine.withdrawable(&alice), 100.0);
+
+        // 100 @ 0.5 resting, then 20 of it fills against bob
+        engine
+            .place_yes_order("m", create_test_order(1, &alice, Side::Buy, 0.5, 100.0))
+            .unwrap();
+        let trades = engine
+            .place_yes_order("m", create_test_order(2, "bob", Side::Sell, 0.5, 20.0))
+            .unwrap();
+        assert_eq!(engine.committed(&alice), 50.0);
+        assert_eq!(engine.withdrawable(&alice), 50.0);
+        engine.mark_settled(&trades);
+        assert_eq!(engine.committed(&alice), 40.0);
+
+        // Withdrawing 70 on-chain leaves 30 against 40 committed
+        let cancelled = engine
+            .ingest_vault_logs(&[log(
+                2,
+                VaultEvent::Withdrawn {
+                    user: ALICE,
+                    amount: 70_000_000,
+                },
+            )])
+            .unwrap();
+        assert_eq!(cancelled.len(), 1);
+        assert_eq!(cancelled[0].qty, 80.0);
+        assert!(engine.book("m", crate::YES).unwrap().bids.is_empty());
+        assert_eq!(engine.withdrawable(&alice), 30.0);
+    }
+
+    #[test]
+    fn test_resting_asks_reserve_claims() {
+        let alice = format_address(&ALICE);
+        let market = format_address(&MARKET);
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        engine.create_market(&market);
+        engine
+            .apply_market_command("admin", &market, MarketCommand::Open)
+            .unwrap();
+        let id = claim_id(&MARKET, YES);
+        engine
+            .ingest_vault_logs(&[log(
+                1,
+                VaultEvent::ClaimsDeposited {
+                    user: ALICE,
+                    id,
+                    amount: 50,
+                },
+            )])
+            .unwrap();
+
+        engine
+            .place_yes_order(&market, create_test_order(1, &alice, Side::Sell, 0.6, 30.0))
+            .unwrap();
+        assert_eq!(engine.committed_claims(&alice, &market, YES), 30.0);
+        assert_eq!(engine.withdrawable_claims(&alice, &market, YES), 20.0);
+
+        // Taking out 30 leaves 20 claims behind a 30 ask
+        let cancelled = engine
+            .ingest_vault_logs(&[log(
+                2,
+                VaultEvent::ClaimsWithdrawn {
+                    user: ALICE,
+                    id,
+                    amount: 30,
+                },
+            )])
+            .unwrap();
+        assert_eq!(cancelled.len(), 1);
+        assert!(engine.book(&market, YES).unwrap().asks.is_empty());
+        assert_eq!(engine.withdrawable_claims(&alice, &market, YES), 20.0);
+    }
+
+    #[test]
+    fn test_checksummed_users_are_enforced() {
+        // Orders may name the user in any case; logs name it in lowercase
+        let carol: Address = [0xab; 20];
+        let shouting = format!("0x{}", hex::encode_upper(carol));
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        engine.create_market("m");
+        engine
+            .apply_market_command("admin", "m", MarketCommand::Open)
+            .unwrap();
+        engine
+            .ingest_vault_logs(&[log(
+                1,
+                VaultEvent::Deposited {
+                    user: carol,
+                    amount: 50_000_000,
+                },
+            )])
+            .unwrap();
+        engine
+            .place_yes_order("m", create_test_order(1, &shouting, Side::Buy, 0.5, 80.0))
+            .unwrap();
+        assert_eq!(engine.committed(&format_address(&carol)), 40.0);
+
+        let cancelled = engine
+            .ingest_vault_logs(&[log(
+                2,
+                VaultEvent::Withdrawn {
+                    user: carol,
+                    amount: 20_000_000,
+                },
+            )])
+            .unwrap();
+        assert_eq!(cancelled.len(), 1);
+        assert!(engine.book("m", YES).unwrap().bids.is_empty());
+    }
+
+    #[test]
+    fn test_failed_ingest_changes_nothing() {
+        let alice = format_address(&ALICE);
+        let mut engine = MatchingEngine::new();
+        let deposit = log(
+            1,
+            VaultEvent::Deposited {
+                user: ALICE,
+                amount: 10_000_000,
+            },
+        );
+        let overdraw = log(
+            2,
+            VaultEvent::Withdrawn {
+                user: ALICE,
+                amount: 20_000_000,
+            },
+        );
+        assert!(engine.ingest_vault_logs(&[deposit, overdraw]).is_err());
+        assert_eq!(engine.withdrawable(&alice), 0.0);
+        assert_eq!(engine.ledger.last_block(), None);
+    }
+
+    #[test]
+    fn test_unsettled_sales_reserve_claims() {
+        let alice = format_address(&ALICE);
+        let market = format_address(&MARKET);
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        engine.create_market(&market);
+        engine
+            .apply_market_command("admin", &market, MarketCommand::Open)
+            .unwrap();
+        engine
+            .ingest_vault_logs(&[log(
+                1,
+                VaultEvent::ClaimsDeposited {
+                    user: ALICE,
+                    id: claim_id(&MARKET, YES),
+                    amount: 50,
+                },
+            )])
+            .unwrap();
+
+        // The sold claims stay promised until the settlement batch lands
+        engine
+            .place_yes_order(&market, create_test_order(1, "bob", Side::Buy, 0.6, 30.0))
+            .unwrap();
+        let trades = engine
+            .place_yes_order(&market, create_test_order(2, &alice, Side::Sell, 0.6, 30.0))
+            .unwrap();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(engine.committed_claims(&alice, &market, YES), 30.0);
+        assert_eq!(engine.withdrawable_claims(&alice, &market, YES), 20.0);
+
+        engine.mark_settled(&trades);
+        assert_eq!(engine.committed_claims(&alice, &market, YES), 0.0);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::market::{MarketCommand, MarketState, OutcomeId};
+use std::fmt;
+
+// Errors returned by the matching engine
+#[derive(Clone, Debug, PartialEq)]
+pub enum EngineError {
+    MarketNotFound(String),
+    UnknownOutcome {
+        market_id: String,
+        outcome: OutcomeId,
+    },
+    InvalidOutcomes(String), // a market needs at least two distinct outcomes
+    InvalidPrice(f64),       // outcome shares trade between 0.0 and 1.0
+    InvalidIceberg(String),  // display or reserve size of an iceberg order
+    InvalidBand(String),     // band market definition the factory would reject
+    Feed(String),            // price feed unavailable or answered garbage
+    SuspectFeed(String),     // feed answered but failed a staleness or deviation check
+    Disputed(String),        // automatic resolution blocked pending manual review
+    Ledger(String),          // vault log that cannot be decoded or applied
+    Fix(String),             // malformed FIX message
+    Wire(String),            // malformed binary protocol frame
+    SettleTooEarly {
+        market_id: String,
+        settle_ts: u64,
+    },
+    MarketNotOpen {
+        market_id: String,
+        state: MarketState,
+    },
+    InvalidTransition {
+        from: MarketState,
+        command: MarketCommand,
+    },
+    Unauthorized(String),
+    SessionNotFound(u64),
+    SessionUserMismatch {
+        session: u64,
+        user: String,
+    },
+    BatchRejected(String), // another order of the same market in the batch failed
+    InvalidQuote(String),
+    ReservedOrderId(u64), // client order id inside the engine's quote id range
+    InvalidSignature(String), // signer does not match the order's maker
+    SignatureRequired(String), // market only takes signed orders
+    OrderExpired {
+        order_id: u64,
+        expiry: u64,
+    },
+    NonceUsed {
+        user: String,
+        nonce: u64,
+    },
+    CircuitBreakerTripped(String),
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::MarketNotFound(id) => write!(f, "market {} not found", id),
+            EngineError::UnknownOutcome { market_id, outcome } => {
+                write!(f, "market {} has no outcome {}", market_id, outcome)
+            }
+            EngineError::InvalidOutcomes(id) => {
+                write!(f, "market {} needs at least two distinct outcomes", id)
+            }
+            EngineError::InvalidPrice(price) => {
+                write!(f, "price {} is outside 0.0-1.0", price)
+            }
+            EngineError::InvalidIceberg(reason) => write!(f, "invalid iceberg: {}", reason),
+            EngineError::InvalidBand(reason) => write!(f, "invalid band market: {}", reason),
+            EngineError::Feed(reason) => write!(f, "price feed error: {}", reason),
+            EngineError::SuspectFeed(reason) => write!(f, "suspect feed data: {}", reason),
+            EngineError::Ledger(reason) => write!(f, "ledger error: {}", reason),
+            EngineError::Fix(reason) => write!(f, "FIX error: {}", reason),
+            EngineError::Wire(reason) => write!(f, "binary protocol error: {}", reason),
+            EngineError::Disputed(id) => write!(f, "resolution of market {} is disputed", id),
+            EngineError::SettleTooEarly {
+                market_id,
+                settle_ts,
+            } => write!(f, "market {} settles at {}", market_id, settle_ts),
+            EngineError::MarketNotOpen { market_id, state } => {
+                write!(f, "market {} is not open (state: {:?})", market_id, state)
+            }
+            EngineError::InvalidTransition { from, command } => {
+                write!(
+                    f,
+                    "cannot apply {:?} to a market in state {:?}",
+                    command, from
+                )
+            }
+            EngineError::Unauthorized(caller) => write!(f, "{} is not an admin", caller),
+            EngineError::BatchRejected(id) => {
+                write!(f, "batch for market {} was rejected", id)
+            }
+            EngineError::InvalidQuote(reason) => write!(f, "invalid quote: {}", reason),
+            EngineError::ReservedOrderId(id) => {
+                write!(f, "order id {} is reserved for engine quotes", id)
+            }
+            EngineError::SessionNotFound(id) => write!(f, "session {} not found", id),
+            EngineError::SessionUserMismatch { session, user } => {
+                write!(f, "session {} does not belong to {}", session, user)
+            }
+            EngineError::InvalidSignature(reason) => write!(f, "invalid signature: {}", reason),
+            EngineError::SignatureRequired(id) => {
+                write!(f, "market {} only accepts signed orders", id)
+            }
+            EngineError::OrderExpired { order_id, expiry } => {
+                write!(f, "order {} expired at {}", order_id, expiry)
+            }
+            EngineError::NonceUsed { user, nonce } => {
+                write!(f, "nonce {} of {} was already used", nonce, user)
+            }
+            EngineError::CircuitBreakerTripped(id) => {
+                write!(f, "order would breach the price band of market {}", id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
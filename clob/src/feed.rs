@@ -0,0 +1,257 @@
+use crate::error::EngineError;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::time::Duration;
+
+// --------------------- Price Feeds ---------------------
+// One symbol's answer from `EtfFeedAggregator.getPrices`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeedPrice {
+    pub price: i128,
+    pub decimals: u8,
+    pub updated_at: u64, // unix seconds
+}
+
+// Source of raw feed prices, answered in the order of `symbols`, as of `now`.
+// Replayed feeds look the prices up; live feeds only know the latest ones and
+// refuse to answer for a time they have already moved past.
+// Feeds are `Send + Sync` so an engine can be shared across server threads
+// and Python threads alike.
+pub trait PriceFeed: Send + Sync {
+    fn get_prices(&mut self, symbols: &[String], now: u64) -> Result<Vec<FeedPrice>, EngineError>;
+}
+
+// Rows are `symbol,price,decimals,updated_at`; blank lines, `#` comments and
+// a `symbol,...` header are skipped
+fn parse_rows(text: &str) -> Result<Vec<(String, FeedPrice)>, EngineError> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with("symbol,"))
+        .map(|line| {
+            let bad_row = || EngineError::Feed(format!("bad feed row: {}", line));
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let [symbol, price, decimals, updated_at] = fields[..] else {
+                return Err(bad_row());
+            };
+            let price = FeedPrice {
+                price: price.parse().map_err(|_| bad_row())?,
+                decimals: decimals.parse().map_err(|_| bad_row())?,
+                updated_at: updated_at.parse().map_err(|_| bad_row())?,
+            };
+            Ok((symbol.to_string(), price))
+        })
+        .collect()
+}
+
+// Latest row per symbol, answered in the order asked for
+fn latest(
+    rows: &[(String, FeedPrice)],
+    symbols: &[String],
+    now: Option<u64>,
+) -> Result<Vec<FeedPrice>, EngineError> {
+    let mut latest: HashMap<&str, FeedPrice> = HashMap::new();
+    for (symbol, price) in rows {
+        if now.is_some_and(|now| price.updated_at > now) {
+            continue;
+        }
+        let entry = latest.entry(symbol.as_str()).or_insert(*price);
+        if price.updated_at >= entry.updated_at {
+            *entry = *price;
+        }
+    }
+    symbols
+        .iter()
+        .map(|symbol| {
+            latest
+                .get(symbol.as_str())
+                .copied()
+                .ok_or_else(|| EngineError::Feed(format!("no price for {}", symbol)))
+        })
+        .collect()
+}
+
+// A live feed's latest prices, unless one of them is newer than `now`
+fn live(
+    rows: &[(String, FeedPrice)],
+    symbols: &[String],
+    now: u64,
+) -> Result<Vec<FeedPrice>, EngineError> {
+    let prices = latest(rows, symbols, None)?;
+    if let Some((symbol, price)) = symbols
+        .iter()
+        .zip(&prices)
+        .find(|(_, price)| price.updated_at > now)
+    {
+        return Err(EngineError::Feed(format!(
+            "{} was updated at {}, after {}",
+            symbol, price.updated_at, now
+        )));
+    }
+    Ok(prices)
+}
+
+// Snapshot file rewritten by an external process; re-read on every request
+pub struct FileFeed {
+    pub path: PathBuf,
+}
+
+impl PriceFeed for FileFeed {
+    fn get_prices(&mut self, symbols: &[String], now: u64) -> Result<Vec<FeedPrice>, EngineError> {
+        let text = fs::read_to_string(&self.path)
+            .map_err(|e| EngineError::Feed(format!("{}: {}", self.path.display(), e)))?;
+        live(&parse_rows(&text)?, symbols, now)
+    }
+}
+
+// Historical updates replayed against the engine clock: each request sees
+// the last update of every symbol at or before `now`
+pub struct CsvReplayFeed {
+    rows: Vec<(String, FeedPrice)>,
+}
+
+impl CsvReplayFeed {
+    pub fn from_csv(text: &str) -> Result<Self, EngineError> {
+        Ok(Self {
+            rows: parse_rows(text)?,
+        })
+    }
+}
+
+impl PriceFeed for CsvReplayFeed {
+    fn get_prices(&mut self, symbols: &[String], now: u64) -> Result<Vec<FeedPrice>, EngineError> {
+        latest(&self.rows, symbols, Some(now))
+    }
+}
+
+// Local stub server answering `GET /prices?symbols=A,B` with feed rows
+pub struct HttpFeed {
+    pub addr: String, // host:port
+}
+
+// A feed that stops answering must not stall the engine that asked it
+const HTTP_FEED_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl PriceFeed for HttpFeed {
+    fn get_prices(&mut self, symbols: &[String], now: u64) -> Result<Vec<FeedPrice>, EngineError> {
+        let feed_error = |e: std::io::Error| EngineError::Feed(format!("{}: {}", self.addr, e));
+        let addr = self
+            .addr
+            .to_socket_addrs()
+            .map_err(feed_error)?
+            .next()
+            .ok_or_else(|| EngineError::Feed(format!("{}: no address", self.addr)))?;
+        let mut stream =
+            TcpStream::connect_timeout(&addr, HTTP_FEED_TIMEOUT).map_err(feed_error)?;
+        stream
+            .set_read_timeout(Some(HTTP_FEED_TIMEOUT))
+            .map_err(feed_error)?;
+        stream
+            .set_write_timeout(Some(HTTP_FEED_TIMEOUT))
+            .map_err(feed_error)?;
+        let request = format!(
+            "GET /prices?symbols={} HTTP/1.0\r\nHost: {}\r\n\r\n",
+            symbols.join(","),
+            self.addr
+        );
+        stream.write_all(request.as_bytes()).map_err(feed_error)?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).map_err(feed_error)?;
+
+        let (head, body) = response
+            .split_once("\r\n\r\n")
+            .ok_or_else(|| EngineError::Feed("malformed feed response".to_string()))?;
+        let status = head.lines().next().unwrap_or_default();
+        if status.split_whitespace().nth(1) != Some("200") {
+            return Err(EngineError::Feed(format!("feed answered {}", status)));
+        }
+        live(&parse_rows(body)?, symbols, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn symbols(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_replay_returns_prices_as_of_now() {
+        let mut feed = CsvReplayFeed::from_csv(
+            "symbol,price,decimals,updated_at\n\
+             SPY,50000000000,8,100\n\
+             QQQ,40000000000,8,100\n\
+             SPY,51000000000,8,200\n",
+        )
+        .unwrap();
+
+        let prices = feed.get_prices(&symbols(&["QQQ", "SPY"]), 150).unwrap();
+        assert_eq!(prices[0].price, 40000000000);
+        assert_eq!(prices[1].price, 50000000000);
+        let prices = feed.get_prices(&symbols(&["SPY"]), 250).unwrap();
+        assert_eq!(prices[0].updated_at, 200);
+        assert!(feed.get_prices(&symbols(&["SPY"]), 50).is_err());
+        assert!(CsvReplayFeed::from_csv("SPY,1,8").is_err());
+    }
+
+    #[test]
+    fn test_live_feed_refuses_the_past() {
+        let path = std::env::temp_dir().join(format!("feed-{}.csv", std::process::id()));
+        fs::write(
+            &path,
+            "SPY,50000000000,8,100
+SPY,51000000000,8,200
+",
+        )
+        .unwrap();
+        let mut feed = FileFeed { path: path.clone() };
+
+        assert_eq!(
+            feed.get_prices(&symbols(&["SPY"]), 200).unwrap()[0].updated_at,
+            200
+        );
+        // The file no longer holds what SPY was at 150
+        assert!(matches!(
+            feed.get_prices(&symbols(&["SPY"]), 150),
+            Err(EngineError::Feed(_))
+        ));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_http_feed_reads_stub_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 512];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            assert!(request.starts_with(b"GET /prices?symbols=SPY "));
+            stream
+                .write_all(b"HTTP/1.0 200 OK\r\n\r\nSPY,500000000,6,42\n")
+                .unwrap();
+        });
+
+        let mut feed = HttpFeed { addr };
+        let prices = feed.get_prices(&symbols(&["SPY"]), 100).unwrap();
+        server.join().unwrap();
+        assert_eq!(
+            prices,
+            vec![FeedPrice {
+                price: 500000000,
+                decimals: 6,
+                updated_at: 42
+            }]
+        );
+    }
+}
@@ -1,26 +1,43 @@
-use crate::{MatchingEngine, Order, Price, Side};
+// C callers are responsible for passing valid, NUL-terminated strings
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use crate::{
+    BatchCancel, BatchOrder, EngineEvent, MarketCommand, MarketState, MatchingEngine, Order,
+    OutcomeId, Price, Side, Trade, NO, YES,
+};
 use std::convert::AsRef;
 use std::ffi::{CStr, CString};
+use std::mem::size_of;
 use std::os::raw::c_char;
 use std::ptr;
 use std::sync::atomic::{AtomicU64, Ordering};
 
-// FFI-safe order structure
+// Bumped whenever an exported struct or function signature changes. The C
+// header is generated from this file (`include/clob.h`); callers compare its
+// CLOB_ABI_VERSION with `clob_abi_version()` when loading the library.
+pub const CLOB_ABI_VERSION: u32 = 3;
+
+// FFI-safe order structure. Callers set `struct_size` to sizeof(FFIOrder) and
+// `version` to CLOB_ABI_VERSION; orders from other builds are refused.
 #[repr(C)]
 pub struct FFIOrder {
+    pub struct_size: u32,
+    pub version: u32,
     pub id: u64,
     pub user: *mut c_char,
     pub side: u8, // 0 = Buy, 1 = Sell
     pub price: f64,
     pub qty: f64,
     pub timestamp: u64,
-    pub market: *mut c_char, // "YES" or "NO"
+    pub market: *mut c_char, // "YES", "NO" or a numeric outcome id
     pub market_id: *mut c_char,
 }
 
-// FFI-safe trade structure
+// FFI-safe trade structure, stamped with this build's size and version
 #[repr(C)]
 pub struct FFITrade {
+    pub struct_size: u32,
+    pub version: u32,
     pub id: u64,
     pub buyer: *mut c_char,
     pub seller: *mut c_char,
@@ -31,21 +48,204 @@ pub struct FFITrade {
     pub timestamp: u64,
 }
 
-// FFI-safe order book structure
+// FFI-safe list of trades, stamped with this build's size and version; free
+// with `clob_free_trades`
+#[repr(C)]
+pub struct FFITradeList {
+    pub struct_size: u32,
+    pub version: u32,
+    pub trades: *mut FFITrade,
+    pub len: usize,
+}
+
+// FFI-safe cancel request for `clob_cancel_orders`. Callers set `struct_size`
+// to sizeof(FFICancel) and `version` to CLOB_ABI_VERSION.
+#[repr(C)]
+pub struct FFICancel {
+    pub struct_size: u32,
+    pub version: u32,
+    pub market_id: *mut c_char,
+    pub market: *mut c_char, // "YES", "NO" or a numeric outcome id
+    pub order_id: u64,
+}
+
+// FFI-safe order book structure, stamped with this build's size and version
 #[repr(C)]
 pub struct FFIOrderBook {
+    pub struct_size: u32,
+    pub version: u32,
     pub best_bid: f64,
     pub best_ask: f64,
     pub bid_count: u32,
     pub ask_count: u32,
 }
 
+// FFI-safe list of cancelled order ids, stamped with this build's size and
+// version; free with `clob_free_order_ids`
+#[repr(C)]
+pub struct FFIOrderIds {
+    pub struct_size: u32,
+    pub version: u32,
+    pub ids: *mut u64,
+    pub len: usize,
+}
+
 // Global matching engine instance
 static mut ENGINE: Option<MatchingEngine> = None;
 
 // Static counter for generating unique trade IDs
 static TRADE_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
 
+fn engine() -> Option<&'static mut MatchingEngine> {
+    unsafe { (*ptr::addr_of_mut!(ENGINE)).as_mut() }
+}
+
+fn state_code(state: MarketState) -> i32 {
+    match state {
+        MarketState::PreOpen => 0,
+        MarketState::Open => 1,
+        MarketState::Halted => 2,
+        MarketState::Closed => 3,
+        MarketState::Resolved => 4,
+    }
+}
+
+// "YES" and "NO" name the binary outcomes; categorical outcomes are passed by number
+pub(crate) fn parse_outcome(market: &str) -> Option<OutcomeId> {
+    match market {
+        "YES" => Some(YES),
+        "NO" => Some(NO),
+        other => other.parse().ok(),
+    }
+}
+
+pub(crate) fn outcome_label(outcome: OutcomeId) -> String {
+    match outcome {
+        YES => "YES".to_string(),
+        NO => "NO".to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn side_from(side: u8) -> Side {
+    if side == 0 {
+        Side::Buy
+    } else {
+        Side::Sell
+    }
+}
+
+fn order_ids(orders: Vec<Order>) -> FFIOrderIds {
+    let ids: Box<[u64]> = orders.iter().map(|o| o.id).collect();
+    let len = ids.len();
+    FFIOrderIds {
+        ids: Box::into_raw(ids) as *mut u64,
+        len,
+        ..NO_ORDER_IDS
+    }
+}
+
+const NO_TRADES: FFITradeList = FFITradeList {
+    struct_size: size_of::<FFITradeList>() as u32,
+    version: CLOB_ABI_VERSION,
+    trades: ptr::null_mut(),
+    len: 0,
+};
+
+fn trade_list(trades: Box<[FFITrade]>) -> FFITradeList {
+    if trades.is_empty() {
+        return NO_TRADES;
+    }
+    FFITradeList {
+        len: trades.len(),
+        trades: Box::into_raw(trades) as *mut FFITrade,
+        ..NO_TRADES
+    }
+}
+
+const NO_ORDER_IDS: FFIOrderIds = FFIOrderIds {
+    struct_size: size_of::<FFIOrderIds>() as u32,
+    version: CLOB_ABI_VERSION,
+    ids: ptr::null_mut(),
+    len: 0,
+};
+
+// Every versioned struct starts with `struct_size` and `version`, and only
+// those two fields are read until the size is known to match this build's
+unsafe fn compatible<T>(value: *const T) -> bool {
+    let [struct_size, version] = (value as *const [u32; 2]).read();
+    struct_size as usize == size_of::<T>() && version == CLOB_ABI_VERSION
+}
+
+fn ffi_book(best_bid: f64, best_ask: f64, bid_count: u32, ask_count: u32) -> FFIOrderBook {
+    FFIOrderBook {
+        struct_size: size_of::<FFIOrderBook>() as u32,
+        version: CLOB_ABI_VERSION,
+        best_bid,
+        best_ask,
+        bid_count,
+        ask_count,
+    }
+}
+
+unsafe fn c_string(s: *const c_char) -> String {
+    CStr::from_ptr(s).to_string_lossy().into_owned()
+}
+
+// Convert an FFI order to the market it targets, its outcome and the Rust order
+unsafe fn order_from(ffi_order: &FFIOrder) -> (String, Option<OutcomeId>, Order) {
+    let order = Order {
+        id: ffi_order.id,
+        user: c_string(ffi_order.user),
+        side: side_from(ffi_order.side),
+        price: Price(ffi_order.price),
+        qty: ffi_order.qty,
+        timestamp: ffi_order.timestamp,
+        iceberg: None,
+    };
+    (
+        c_string(ffi_order.market_id),
+        parse_outcome(&c_string(ffi_order.market)),
+        order,
+    )
+}
+
+fn ffi_trade(trade: &Trade) -> FFITrade {
+    FFITrade {
+        struct_size: size_of::<FFITrade>() as u32,
+        version: CLOB_ABI_VERSION,
+        id: TRADE_ID_COUNTER.fetch_add(1, Ordering::SeqCst), // Generate a unique ID for the trade
+        buyer: CString::new(AsRef::<str>::as_ref(&trade.buyer))
+            .unwrap()
+            .into_raw(),
+        seller: CString::new(AsRef::<str>::as_ref(&trade.seller))
+            .unwrap()
+            .into_raw(),
+        qty: trade.qty,
+        price: trade.price.0,
+        market: CString::new(outcome_label(trade.outcome))
+            .unwrap()
+            .into_raw(),
+        market_id: CString::new(AsRef::<str>::as_ref(&trade.market_id))
+            .unwrap()
+            .into_raw(),
+        timestamp: trade.timestamp,
+    }
+}
+
+unsafe fn free_trade_strings(trade: &FFITrade) {
+    let _ = CString::from_raw(trade.buyer);
+    let _ = CString::from_raw(trade.seller);
+    let _ = CString::from_raw(trade.market);
+    let _ = CString::from_raw(trade.market_id);
+}
+
+// ABI version this library was built with
+#[no_mangle]
+pub extern "C" fn clob_abi_version() -> u32 {
+    CLOB_ABI_VERSION
+}
+
 // Initialize the matching engine
 #[no_mangle]
 pub extern "C" fn clob_init() -> i32 {
@@ -55,11 +255,12 @@ pub extern "C" fn clob_init() -> i32 {
     }
 }
 
-// Create a market
+// Create a market. It starts pre-open: an admin opens it with
+// `clob_market_command` before orders are accepted.
 #[no_mangle]
 pub extern "C" fn clob_create_market(market_id: *const c_char) -> i32 {
     unsafe {
-        if let Some(engine) = &mut ENGINE {
+        if let Some(engine) = engine() {
             let market_id_str = CStr::from_ptr(market_id).to_string_lossy();
             engine.create_market(&market_id_str);
             0 // Success
@@ -69,78 +270,84 @@ pub extern "C" fn clob_create_market(market_id: *const c_char) -> i32 {
     }
 }
 
-// Place an order
+// Place an order. Returns 0 = placed, -1 = engine not initialized,
+// -2 = unknown outcome label, -3 = rejected by the engine, -4 = built against
+// another ABI version. When placed, `trade` (if not null) receives the first
+// trade, or null if the order did not match; free it with `clob_free_trade`.
+// A null order is refused like one from another build.
 #[no_mangle]
-pub extern "C" fn clob_place_order(ffi_order: FFIOrder) -> *mut FFITrade {
-    unsafe {
-        if let Some(engine) = &mut ENGINE {
-            // Convert FFI order to Rust order
-            let rust_order = Order {
-                id: ffi_order.id,
-                user: CStr::from_ptr(ffi_order.user).to_string_lossy().to_string(),
-                side: if ffi_order.side == 0 {
-                    Side::Buy
-                } else {
-                    Side::Sell
-                },
-                price: Price(ffi_order.price),
-                qty: ffi_order.qty,
-                timestamp: ffi_order.timestamp,
-            };
-
-            let market_id = CStr::from_ptr(ffi_order.market_id).to_string_lossy();
-            let market = CStr::from_ptr(ffi_order.market).to_string_lossy();
-
-            // Ensure market exists before placing order
-            engine.create_market(&market_id);
-
-            // Place order based on market type
-            let trades = if market == "YES" {
-                engine.place_yes_order(&market_id, rust_order)
-            } else {
-                engine.place_no_order(&market_id, rust_order)
-            };
-
-            // Convert first trade to FFI format (if any)
-            if let Some(trade) = trades.first() {
-                let ffi_trade = Box::new(FFITrade {
-                    id: TRADE_ID_COUNTER.fetch_add(1, Ordering::SeqCst), // Generate a unique ID for the trade
-                    buyer: CString::new(AsRef::<str>::as_ref(&trade.buyer))
-                        .unwrap()
-                        .into_raw(),
-                    seller: CString::new(AsRef::<str>::as_ref(&trade.seller))
-                        .unwrap()
-                        .into_raw(),
-                    qty: trade.qty,
-                    price: trade.price.0,
-                    market: CString::new(AsRef::<str>::as_ref(&market))
-                        .unwrap()
-                        .into_raw(), // Use the market parameter
-                    market_id: CString::new(AsRef::<str>::as_ref(&trade.market_id))
-                        .unwrap()
-                        .into_raw(),
-                    timestamp: trade.timestamp,
-                });
-                Box::into_raw(ffi_trade)
-            } else {
-                ptr::null_mut()
-            }
-        } else {
-            ptr::null_mut()
-        }
+pub extern "C" fn clob_place_order(ffi_order: *const FFIOrder, trade: *mut *mut FFITrade) -> i32 {
+    if !trade.is_null() {
+        unsafe { *trade = ptr::null_mut() };
+    }
+    if ffi_order.is_null() || !unsafe { compatible(ffi_order) } {
+        return -4; // Built against another version of the header
+    }
+    let Some(engine) = engine() else {
+        return -1; // Engine not initialized
+    };
+    let (market_id, outcome, rust_order) = unsafe { order_from(&*ffi_order) };
+    let Some(outcome) = outcome else {
+        return -2;
+    };
+    let Ok(trades) = engine.place_order(&market_id, outcome, rust_order) else {
+        return -3; // Bad price or quantity, market not open, ...
+    };
+
+    // Convert first trade to FFI format (if any)
+    if let (Some(first), false) = (trades.first(), trade.is_null()) {
+        unsafe { *trade = Box::into_raw(Box::new(ffi_trade(first))) };
     }
+    0
 }
 
-// Cancel an order
+// Run the engine's timed work (stops, auctions, batches, trading end) up to
+// `now`. Returns the trades it produced; free them with `clob_free_trades`.
+#[no_mangle]
+pub extern "C" fn clob_tick(now: u64) -> FFITradeList {
+    let Some(engine) = engine() else {
+        return NO_TRADES;
+    };
+    let trades: Box<[FFITrade]> = engine
+        .tick(now)
+        .iter()
+        .filter_map(|event| match event {
+            EngineEvent::Trade(trade) => Some(ffi_trade(trade)),
+            _ => None,
+        })
+        .collect();
+    trade_list(trades)
+}
+
+// The market and outcome books a cancel targets: a bare market id means every
+// outcome, "<market id>_<outcome>" (e.g. "m_YES") just that one
+fn cancel_target(engine: &MatchingEngine, target: &str) -> Option<(String, Vec<OutcomeId>)> {
+    if let Some(market) = engine.markets.get(target) {
+        return Some((target.to_string(), market.outcomes.clone()));
+    }
+    let (market_id, label) = target.rsplit_once('_')?;
+    let outcome = parse_outcome(label)?;
+    engine
+        .markets
+        .get(market_id)
+        .filter(|m| m.outcomes.contains(&outcome))
+        .map(|_| (market_id.to_string(), vec![outcome]))
+}
+
+// Cancel an order, by market id or "<market id>_<outcome>"
 #[no_mangle]
 pub extern "C" fn clob_cancel_order(market_id: *const c_char, order_id: u64) -> i32 {
     unsafe {
-        if let Some(engine) = &mut ENGINE {
+        if let Some(engine) = engine() {
             let market_id_str = CStr::from_ptr(market_id).to_string_lossy();
-            
-            // Try to cancel from the specific market
-            let result = engine.cancel_order(&market_id_str, order_id);
-            
+
+            let result =
+                cancel_target(engine, &market_id_str).is_some_and(|(market_id, outcomes)| {
+                    outcomes
+                        .into_iter()
+                        .any(|outcome| engine.cancel_order(&market_id, outcome, order_id))
+                });
+
             if result {
                 0 // Success
             } else {
@@ -159,31 +366,255 @@ pub extern "C" fn clob_get_top_of_book(
     market: *const c_char,
 ) -> FFIOrderBook {
     unsafe {
-        if let Some(engine) = &mut ENGINE {
+        if let Some(engine) = engine() {
             let market_id_str = CStr::from_ptr(market_id).to_string_lossy();
             let market_str = CStr::from_ptr(market).to_string_lossy();
 
-            // Ensure market exists before accessing it
-            engine.create_market(&market_id_str);
+            let book =
+                parse_outcome(&market_str).and_then(|outcome| engine.book(&market_id_str, outcome));
 
-            let (best_bid, best_ask) = if market_str == "YES" {
-                engine.get_yes_top_of_book(&market_id_str)
+            if let Some(book) = book {
+                let (best_bid, best_ask) = book.get_top_of_book();
+                ffi_book(best_bid.0, best_ask.0, 0, 0) // TODO: Implement order counting
             } else {
-                engine.get_no_top_of_book(&market_id_str)
-            };
-
-            FFIOrderBook {
-                best_bid: best_bid.0,
-                best_ask: best_ask.0,
-                bid_count: 0, // TODO: Implement order counting
-                ask_count: 0,
+                ffi_book(0.0, 1.0, 0, 0)
             }
         } else {
-            FFIOrderBook {
-                best_bid: 0.0,
-                best_ask: 1.0,
-                bid_count: 0,
-                ask_count: 0,
+            ffi_book(0.0, 1.0, 0, 0)
+        }
+    }
+}
+
+// Register a wallet allowed to run market commands
+#[no_mangle]
+pub extern "C" fn clob_add_admin(admin: *const c_char) -> i32 {
+    if let Some(engine) = engine() {
+        let admin_str = unsafe { CStr::from_ptr(admin) }.to_string_lossy();
+        engine.add_admin(&admin_str);
+        0 // Success
+    } else {
+        -1 // Engine not initialized
+    }
+}
+
+// Run a lifecycle command on a market.
+// command: 0 = open, 1 = halt, 2 = resume, 3 = close, 4 = resolve (winner is the winning outcome id)
+// Returns the new state (0 = pre-open, 1 = open, 2 = halted, 3 = closed, 4 = resolved)
+#[no_mangle]
+pub extern "C" fn clob_market_command(
+    caller: *const c_char,
+    market_id: *const c_char,
+    command: u8,
+    winner: u8,
+) -> i32 {
+    let Some(engine) = engine() else {
+        return -1; // Engine not initialized
+    };
+    let command = match command {
+        0 => MarketCommand::Open,
+        1 => MarketCommand::Halt,
+        2 => MarketCommand::Resume,
+        3 => MarketCommand::Close,
+        4 => MarketCommand::Resolve { winner },
+        _ => return -2, // Unknown command
+    };
+    let caller_str = unsafe { CStr::from_ptr(caller) }.to_string_lossy();
+    let market_id_str = unsafe { CStr::from_ptr(market_id) }.to_string_lossy();
+
+    match engine.apply_market_command(&caller_str, &market_id_str, command) {
+        Ok(state) => state_code(state),
+        Err(_) => -3, // Unauthorized, unknown market or invalid transition
+    }
+}
+
+// Current state of a market, or -1 if unknown
+#[no_mangle]
+pub extern "C" fn clob_market_state(market_id: *const c_char) -> i32 {
+    let Some(engine) = engine() else {
+        return -1;
+    };
+    let market_id_str = unsafe { CStr::from_ptr(market_id) }.to_string_lossy();
+    engine.market_state(&market_id_str).map_or(-1, state_code)
+}
+
+// Cancel every order of a user; a null market_id cancels across all markets
+#[no_mangle]
+pub extern "C" fn clob_cancel_all(user: *const c_char, market_id: *const c_char) -> FFIOrderIds {
+    let Some(engine) = engine() else {
+        return NO_ORDER_IDS;
+    };
+    let user_str = unsafe { CStr::from_ptr(user) }.to_string_lossy();
+    let market_id_str =
+        (!market_id.is_null()).then(|| unsafe { CStr::from_ptr(market_id) }.to_string_lossy());
+    order_ids(engine.cancel_all(&user_str, market_id_str.as_deref()))
+}
+
+// Cancel one side (0 = Buy, 1 = Sell) of a user's orders in a market
+#[no_mangle]
+pub extern "C" fn clob_cancel_side(
+    user: *const c_char,
+    market_id: *const c_char,
+    side: u8,
+) -> FFIOrderIds {
+    let Some(engine) = engine() else {
+        return NO_ORDER_IDS;
+    };
+    let user_str = unsafe { CStr::from_ptr(user) }.to_string_lossy();
+    let market_id_str = unsafe { CStr::from_ptr(market_id) }.to_string_lossy();
+    order_ids(engine.cancel_side(&user_str, &market_id_str, side_from(side)))
+}
+
+// Cancel a user's orders on one side priced within [low, high]
+#[no_mangle]
+pub extern "C" fn clob_cancel_price_range(
+    user: *const c_char,
+    market_id: *const c_char,
+    side: u8,
+    low: f64,
+    high: f64,
+) -> FFIOrderIds {
+    let Some(engine) = engine() else {
+        return NO_ORDER_IDS;
+    };
+    let user_str = unsafe { CStr::from_ptr(user) }.to_string_lossy();
+    let market_id_str = unsafe { CStr::from_ptr(market_id) }.to_string_lossy();
+    order_ids(engine.cancel_price_range(
+        &user_str,
+        &market_id_str,
+        side_from(side),
+        Price(low),
+        Price(high),
+    ))
+}
+
+// Free a list returned by the mass cancel functions
+#[no_mangle]
+pub extern "C" fn clob_free_order_ids(order_ids: FFIOrderIds) {
+    if !order_ids.ids.is_null() {
+        unsafe {
+            let _ = Box::from_raw(ptr::slice_from_raw_parts_mut(order_ids.ids, order_ids.len));
+        }
+    }
+}
+
+// Place `len` orders in one call, atomically per market. `results` must have
+// room for `len` codes: 0 = placed, -2 = unknown outcome label, -3 = rejected,
+// -4 = built against another ABI version. Returns every resulting trade.
+// An empty batch or a null array places nothing.
+#[no_mangle]
+pub extern "C" fn clob_place_orders(
+    orders: *const FFIOrder,
+    len: usize,
+    results: *mut i32,
+) -> FFITradeList {
+    if len == 0 || orders.is_null() || results.is_null() {
+        return NO_TRADES;
+    }
+    let Some(engine) = engine() else {
+        return NO_TRADES;
+    };
+    let results = unsafe { std::slice::from_raw_parts_mut(results, len) };
+    // With a foreign struct size the array stride is unknown: only the first
+    // order's header can be read safely. Once it matches, every other header
+    // must match too, or the array is not what this build expects.
+    if !unsafe { compatible(orders) } {
+        results.fill(-4);
+        return NO_TRADES;
+    }
+    let orders = unsafe { std::slice::from_raw_parts(orders, len) };
+    if !orders.iter().all(|o| unsafe { compatible(o) }) {
+        results.fill(-4);
+        return NO_TRADES;
+    }
+    let parsed: Vec<_> = orders.iter().map(|o| unsafe { order_from(o) }).collect();
+
+    // A market with an unreadable outcome label is rejected as a whole
+    let bad_markets: Vec<&String> = parsed
+        .iter()
+        .filter(|(_, outcome, _)| outcome.is_none())
+        .map(|(market_id, _, _)| market_id)
+        .collect();
+    let mut batch = Vec::new();
+    let mut positions = Vec::new();
+    for (i, (market_id, outcome, order)) in parsed.iter().enumerate() {
+        match outcome {
+            None => results[i] = -2,
+            Some(_) if bad_markets.contains(&market_id) => results[i] = -3,
+            Some(outcome) => {
+                batch.push(BatchOrder {
+                    market_id: market_id.clone(),
+                    outcome: *outcome,
+                    order: order.clone(),
+                });
+                positions.push(i);
+            }
+        }
+    }
+
+    let placed = engine.place_orders(batch);
+    for (i, result) in positions.into_iter().zip(placed.results) {
+        results[i] = if result.is_ok() { 0 } else { -3 };
+    }
+    trade_list(placed.trades.iter().map(ffi_trade).collect())
+}
+
+// Cancel `len` orders in one call. `results` must have room for `len` codes:
+// 0 = cancelled, -1 = not found, -4 = built against another ABI version.
+// Returns how many were cancelled; an empty batch or a null array cancels nothing.
+#[no_mangle]
+pub extern "C" fn clob_cancel_orders(
+    cancels: *const FFICancel,
+    len: usize,
+    results: *mut i32,
+) -> i32 {
+    if len == 0 || cancels.is_null() || results.is_null() {
+        return 0;
+    }
+    let Some(engine) = engine() else {
+        return -1; // Engine not initialized
+    };
+    let results = unsafe { std::slice::from_raw_parts_mut(results, len) };
+    // As with orders, a foreign struct size leaves only the first header
+    // readable, and past it every header must match
+    if !unsafe { compatible(cancels) } {
+        results.fill(-4);
+        return 0;
+    }
+    let cancels = unsafe { std::slice::from_raw_parts(cancels, len) };
+    if !cancels.iter().all(|c| unsafe { compatible(c) }) {
+        results.fill(-4);
+        return 0;
+    }
+    let batch: Vec<Option<BatchCancel>> = cancels
+        .iter()
+        .map(|c| unsafe {
+            parse_outcome(&c_string(c.market)).map(|outcome| BatchCancel {
+                market_id: c_string(c.market_id),
+                outcome,
+                order_id: c.order_id,
+            })
+        })
+        .collect();
+
+    let valid: Vec<BatchCancel> = batch.iter().flatten().cloned().collect();
+    let mut cancelled = engine.cancel_orders(&valid).into_iter();
+    let mut count = 0;
+    for (result, cancel) in results.iter_mut().zip(&batch) {
+        let found = cancel.is_some() && cancelled.next() == Some(true);
+        *result = if found { 0 } else { -1 };
+        count += found as i32;
+    }
+    count
+}
+
+// Free a list returned by `clob_place_orders` or `clob_tick`
+#[no_mangle]
+pub extern "C" fn clob_free_trades(list: FFITradeList) {
+    if !list.trades.is_null() {
+        unsafe {
+            let trades = Box::from_raw(ptr::slice_from_raw_parts_mut(list.trades, list.len));
+            for trade in trades.iter() {
+                free_trade_strings(trade);
             }
         }
     }
@@ -195,11 +626,7 @@ pub extern "C" fn clob_free_trade(trade: *mut FFITrade) {
     if !trade.is_null() {
         unsafe {
             let trade = Box::from_raw(trade);
-            // Free the CStrings
-            let _ = CString::from_raw(trade.buyer);
-            let _ = CString::from_raw(trade.seller);
-            let _ = CString::from_raw(trade.market);
-            let _ = CString::from_raw(trade.market_id);
+            free_trade_strings(&trade);
         }
     }
 }
@@ -211,27 +638,22 @@ pub extern "C" fn clob_get_order_book_depth(
     market: *const c_char,
 ) -> *mut FFIOrderBook {
     unsafe {
-        if let Some(engine) = &mut ENGINE {
+        if let Some(engine) = engine() {
             let market_id_str = CStr::from_ptr(market_id).to_string_lossy();
             let market_str = CStr::from_ptr(market).to_string_lossy();
 
-            // Ensure market exists before accessing it
-            engine.create_market(&market_id_str);
-
             // Get the order book
-            let order_book = if market_str == "YES" {
-                engine.order_books.get(&format!("{}_YES", market_id_str))
-            } else {
-                engine.order_books.get(&format!("{}_NO", market_id_str))
-            };
+            let order_book =
+                parse_outcome(&market_str).and_then(|outcome| engine.book(&market_id_str, outcome));
 
             if let Some(book) = order_book {
-                let ffi_book = Box::new(FFIOrderBook {
-                    best_bid: book.get_top_of_book().0 .0,
-                    best_ask: book.get_top_of_book().1 .0,
-                    bid_count: book.bids.len() as u32,
-                    ask_count: book.asks.len() as u32,
-                });
+                let (best_bid, best_ask) = book.get_top_of_book();
+                let ffi_book = Box::new(ffi_book(
+                    best_bid.0,
+                    best_ask.0,
+                    book.bids.len() as u32,
+                    book.asks.len() as u32,
+                ));
                 Box::into_raw(ffi_book)
             } else {
                 ptr::null_mut()
@@ -251,3 +673,152 @@ pub extern "C" fn clob_free_order_book(order_book: *mut FFIOrderBook) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    // The checked-in header must match what cbindgen makes of this file
+    #[test]
+    fn test_header_is_up_to_date() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let config = cbindgen::Config::from_file(root.join("cbindgen.toml")).unwrap();
+        let mut generated = Vec::new();
+        cbindgen::Builder::new()
+            .with_config(config)
+            .with_src(root.join("src/ffi.rs"))
+            .generate()
+            .unwrap()
+            .write(&mut generated);
+
+        let path = root.join("include/clob.h");
+        if std::env::var_os("CLOB_UPDATE_HEADER").is_some() {
+            std::fs::write(&path, &generated).unwrap();
+        }
+        let header = std::fs::read(&path).unwrap_or_default();
+        assert!(
+            header == generated,
+            "include/clob.h is stale; rerun with CLOB_UPDATE_HEADER=1"
+        );
+    }
+
+    #[test]
+    fn test_orders_from_another_build_are_refused() {
+        let user = CString::new("alice").unwrap();
+        let market = CString::new("YES").unwrap();
+        let market_id = CString::new("m").unwrap();
+        let order = |struct_size, version| FFIOrder {
+            struct_size,
+            version,
+            id: 1,
+            user: user.as_ptr() as *mut c_char,
+            side: 0,
+            price: 0.5,
+            qty: 1.0,
+            timestamp: 0,
+            market: market.as_ptr() as *mut c_char,
+            market_id: market_id.as_ptr() as *mut c_char,
+        };
+        let size = size_of::<FFIOrder>() as u32;
+        unsafe {
+            assert!(compatible(&order(size, CLOB_ABI_VERSION)));
+            assert!(!compatible(&order(size - 8, CLOB_ABI_VERSION)));
+            assert!(!compatible(&order(size, CLOB_ABI_VERSION - 1)));
+        }
+        assert_eq!(
+            clob_place_order(&order(size, CLOB_ABI_VERSION - 1), ptr::null_mut()),
+            -4
+        );
+        assert_eq!(clob_place_order(ptr::null(), ptr::null_mut()), -4);
+
+        // Lists handed back carry the header too
+        let ids = order_ids(Vec::new());
+        assert_eq!(ids.struct_size as usize, size_of::<FFIOrderIds>());
+        assert_eq!(NO_TRADES.version, CLOB_ABI_VERSION);
+        clob_free_order_ids(ids);
+        assert_eq!(clob_abi_version(), CLOB_ABI_VERSION);
+    }
+
+    // The only test driving the global engine, so nothing races on it
+    #[test]
+    fn test_global_engine_lifecycle() {
+        let c = |s: &str| CString::new(s).unwrap();
+        let (admin, market_id, yes) = (c("admin"), c("m"), c("YES"));
+        let (maker, taker) = (c("maker"), c("taker"));
+        let order = |id, user: &CString, side, qty| FFIOrder {
+            struct_size: size_of::<FFIOrder>() as u32,
+            version: CLOB_ABI_VERSION,
+            id,
+            user: user.as_ptr() as *mut c_char,
+            side,
+            price: 0.5,
+            qty,
+            timestamp: 0,
+            market: yes.as_ptr() as *mut c_char,
+            market_id: market_id.as_ptr() as *mut c_char,
+        };
+        assert_eq!(clob_init(), 0);
+        assert_eq!(clob_create_market(market_id.as_ptr()), 0);
+        assert_eq!(clob_add_admin(admin.as_ptr()), 0);
+
+        // Pre-open markets reject orders, which is not the same as no fill
+        let mut trade = ptr::null_mut();
+        assert_eq!(clob_place_order(&order(1, &maker, 1, 2.0), &mut trade), -3);
+        assert_eq!(
+            clob_market_command(admin.as_ptr(), market_id.as_ptr(), 0, 0),
+            1
+        );
+        assert_eq!(clob_place_order(&order(1, &maker, 1, 2.0), &mut trade), 0);
+        assert!(trade.is_null());
+        assert_eq!(clob_place_order(&order(2, &taker, 0, 1.0), &mut trade), 0);
+        assert_eq!(unsafe { (*trade).qty }, 1.0);
+        clob_free_trade(trade);
+
+        // backend/server.js cancels with `${marketId}_${market}`
+        assert_eq!(clob_cancel_order(c("m_NO").as_ptr(), 1), -1);
+        assert_eq!(clob_cancel_order(c("x_YES").as_ptr(), 1), -1);
+        assert_eq!(clob_cancel_order(c("m_YES").as_ptr(), 1), 0);
+        assert_eq!(clob_cancel_order(market_id.as_ptr(), 1), -1);
+
+        // Cancels are checked against this build like orders
+        let cancel = |version| FFICancel {
+            struct_size: size_of::<FFICancel>() as u32,
+            version,
+            market_id: market_id.as_ptr() as *mut c_char,
+            market: yes.as_ptr() as *mut c_char,
+            order_id: 1,
+        };
+        let mut results = [0; 2];
+        let cancels = [cancel(CLOB_ABI_VERSION - 1), cancel(CLOB_ABI_VERSION)];
+        assert_eq!(
+            clob_cancel_orders(cancels.as_ptr(), 2, results.as_mut_ptr()),
+            0
+        );
+        assert_eq!(results, [-4, -4]);
+        let cancels = [cancel(CLOB_ABI_VERSION), cancel(CLOB_ABI_VERSION - 1)];
+        assert_eq!(
+            clob_cancel_orders(cancels.as_ptr(), 2, results.as_mut_ptr()),
+            0
+        );
+        assert_eq!(results, [-4, -4]);
+
+        // A foreign header past the first spoils an order batch too
+        let mut stale = order(4, &maker, 1, 1.0);
+        stale.version = CLOB_ABI_VERSION - 1;
+        let orders = [order(3, &maker, 1, 1.0), stale];
+        let placed = clob_place_orders(orders.as_ptr(), 2, results.as_mut_ptr());
+        assert_eq!(placed.len, 0);
+        assert_eq!(results, [-4, -4]);
+        assert_eq!(clob_cancel_order(c("m_YES").as_ptr(), 3), -1);
+
+        // Empty batches never touch their (possibly null) arrays
+        let placed = clob_place_orders(ptr::null(), 0, ptr::null_mut());
+        assert!(placed.trades.is_null() && placed.len == 0);
+        assert_eq!(clob_cancel_orders(ptr::null(), 3, ptr::null_mut()), 0);
+
+        let ticked = clob_tick(1);
+        assert_eq!(ticked.len, 0);
+        clob_free_trades(ticked);
+    }
+}
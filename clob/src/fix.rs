@@ -0,0 +1,563 @@
+use crate::error::EngineError;
+use std::collections::BTreeMap;
+
+// --------------------- FIX 4.4 Messages ---------------------
+pub const BEGIN_STRING: &str = "FIX.4.4";
+const SOH: char = '\x01';
+const DEFAULT_HEARTBEAT_SECS: u64 = 30;
+
+pub mod tag {
+    pub const ACCOUNT: u32 = 1;
+    pub const AVG_PX: u32 = 6;
+    pub const BEGIN_SEQ_NO: u32 = 7;
+    pub const BEGIN_STRING: u32 = 8;
+    pub const BODY_LENGTH: u32 = 9;
+    pub const CHECKSUM: u32 = 10;
+    pub const CL_ORD_ID: u32 = 11;
+    pub const CUM_QTY: u32 = 14;
+    pub const END_SEQ_NO: u32 = 16;
+    pub const EXEC_ID: u32 = 17;
+    pub const LAST_PX: u32 = 31;
+    pub const LAST_QTY: u32 = 32;
+    pub const MSG_SEQ_NUM: u32 = 34;
+    pub const MSG_TYPE: u32 = 35;
+    pub const NEW_SEQ_NO: u32 = 36;
+    pub const ORDER_ID: u32 = 37;
+    pub const ORDER_QTY: u32 = 38;
+    pub const ORD_STATUS: u32 = 39;
+    pub const ORD_TYPE: u32 = 40;
+    pub const ORIG_CL_ORD_ID: u32 = 41;
+    pub const POSS_DUP_FLAG: u32 = 43;
+    pub const PRICE: u32 = 44;
+    pub const REF_SEQ_NUM: u32 = 45;
+    pub const SENDER_COMP_ID: u32 = 49;
+    pub const SENDING_TIME: u32 = 52;
+    pub const SIDE: u32 = 54;
+    pub const SYMBOL: u32 = 55;
+    pub const TARGET_COMP_ID: u32 = 56;
+    pub const TEXT: u32 = 58;
+    pub const ENCRYPT_METHOD: u32 = 98;
+    pub const CXL_REJ_REASON: u32 = 102;
+    pub const HEART_BT_INT: u32 = 108;
+    pub const TEST_REQ_ID: u32 = 112;
+    pub const ORIG_SENDING_TIME: u32 = 122;
+    pub const GAP_FILL_FLAG: u32 = 123;
+    pub const RESET_SEQ_NUM_FLAG: u32 = 141;
+    pub const REF_MSG_TYPE: u32 = 372;
+    pub const SESSION_REJECT_REASON: u32 = 373;
+    pub const EXEC_TYPE: u32 = 150;
+    pub const LEAVES_QTY: u32 = 151;
+    pub const CXL_REJ_RESPONSE_TO: u32 = 434;
+    pub const USERNAME: u32 = 553;
+    pub const PASSWORD: u32 = 554;
+    pub const SECURITY_SUB_TYPE: u32 = 762; // outcome label, e.g. YES or WITHIN
+}
+
+// Standard header fields the session layer owns; everything else is body
+const HEADER_TAGS: [u32; 7] = [
+    tag::MSG_TYPE,
+    tag::SENDER_COMP_ID,
+    tag::TARGET_COMP_ID,
+    tag::MSG_SEQ_NUM,
+    tag::SENDING_TIME,
+    tag::POSS_DUP_FLAG,
+    tag::ORIG_SENDING_TIME,
+];
+
+// Body of a message in field order, starting with MsgType. BeginString,
+// BodyLength and CheckSum are added on the wire only.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FixMessage {
+    pub fields: Vec<(u32, String)>,
+}
+
+impl FixMessage {
+    pub fn new(msg_type: &str) -> Self {
+        Self {
+            fields: vec![(tag::MSG_TYPE, msg_type.to_string())],
+        }
+    }
+
+    pub fn with(mut self, tag: u32, value: impl ToString) -> Self {
+        self.fields.push((tag, value.to_string()));
+        self
+    }
+
+    pub fn get(&self, tag: u32) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(t, _)| *t == tag)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn get_f64(&self, tag: u32) -> Option<f64> {
+        self.get(tag)?.parse().ok()
+    }
+
+    pub fn msg_type(&self) -> &str {
+        self.get(tag::MSG_TYPE).unwrap_or_default()
+    }
+
+    pub fn seq_num(&self) -> Option<u64> {
+        self.get(tag::MSG_SEQ_NUM)?.parse().ok()
+    }
+
+    // Session-level messages, which are gap-filled instead of resent
+    pub fn is_admin(&self) -> bool {
+        matches!(self.msg_type(), "0" | "1" | "2" | "3" | "4" | "5" | "A")
+    }
+
+    pub fn encode(&self) -> String {
+        let body: String = self
+            .fields
+            .iter()
+            .map(|(tag, value)| format!("{}={}{}", tag, value, SOH))
+            .collect();
+        let head = format!("8={}{}9={}{}", BEGIN_STRING, SOH, body.len(), SOH);
+        format!("{}{}10={:03}{}", head, body, checksum(&head, &body), SOH)
+    }
+
+    // Parse one complete message, checking BeginString, BodyLength and CheckSum
+    pub fn decode(raw: &str) -> Result<FixMessage, EngineError> {
+        let malformed = |reason: &str| EngineError::Fix(reason.to_string());
+        let trailer = raw
+            .strip_suffix(SOH)
+            .and_then(|r| r.rfind("\x0110="))
+            .ok_or_else(|| malformed("missing CheckSum"))?
+            + 1;
+        let mut fields = raw[..trailer]
+            .split_terminator(SOH)
+            .map(|field| {
+                let (tag, value) = field.split_once('=').ok_or_else(|| malformed(field))?;
+                let tag = tag.parse().map_err(|_| malformed(field))?;
+                Ok((tag, value.to_string()))
+            })
+            .collect::<Result<Vec<(u32, String)>, EngineError>>()?;
+
+        if fields.len() < 3 || fields[0] != (tag::BEGIN_STRING, BEGIN_STRING.to_string()) {
+            return Err(malformed("expected BeginString FIX.4.4"));
+        }
+        if fields[1].0 != tag::BODY_LENGTH || fields[2].0 != tag::MSG_TYPE {
+            return Err(malformed("expected BodyLength and MsgType"));
+        }
+        let body_start = raw.find("\x019=").unwrap() + 4 + fields[1].1.len();
+        let body = &raw[body_start..trailer];
+        if fields[1].1 != body.len().to_string() {
+            return Err(malformed("BodyLength mismatch"));
+        }
+        let expected = format!("{:03}", checksum(&raw[..body_start], body));
+        if raw[trailer + 3..raw.len() - 1] != expected {
+            return Err(malformed("CheckSum mismatch"));
+        }
+        Ok(FixMessage {
+            fields: fields.split_off(2),
+        })
+    }
+}
+
+fn checksum(head: &str, body: &str) -> u32 {
+    head.bytes().chain(body.bytes()).map(u32::from).sum::<u32>() % 256
+}
+
+// Length of the first complete message at the start of `buf`, or None if more
+// bytes are needed
+pub fn frame_len(buf: &[u8]) -> Result<Option<usize>, EngineError> {
+    let mut separators = buf
+        .iter()
+        .enumerate()
+        .filter(|(_, &b)| b == SOH as u8)
+        .map(|(i, _)| i);
+    let (Some(first), Some(second)) = (separators.next(), separators.next()) else {
+        return Ok(None);
+    };
+    let body_length = std::str::from_utf8(&buf[first + 1..second])
+        .ok()
+        .and_then(|field| field.strip_prefix("9="))
+        .and_then(|len| len.parse::<usize>().ok())
+        .ok_or_else(|| EngineError::Fix("expected BodyLength".to_string()))?;
+    // Body, then "10=NNN<SOH>"
+    let total = second + 1 + body_length + 7;
+    Ok((buf.len() >= total).then_some(total))
+}
+
+// UTCTimestamp (YYYYMMDD-HH:MM:SS) of unix seconds
+pub fn utc_timestamp(secs: u64) -> String {
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = (secs / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    let time = secs % 86_400;
+    format!(
+        "{:04}{:02}{:02}-{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+// --------------------- FIX Session ---------------------
+// Session layer of one counterparty: logon, heartbeats, sequence numbers and
+// resends. Works for either end; the initiator starts with `logon`.
+#[derive(Clone, Debug)]
+pub struct FixSession {
+    pub sender_comp_id: String, // our CompID
+    pub target_comp_id: String, // counterparty's CompID
+    pub heartbeat_secs: u64,
+    pub next_out_seq: u64,
+    pub next_in_seq: u64,
+    pub logged_on: bool,
+    pub closed: bool, // logged out or dropped; the connection should close
+    pub credentials: Option<(String, String)>, // Username and Password for our Logon
+    sent: BTreeMap<u64, FixMessage>, // outbound messages by sequence number, for resends
+    logon_sent: bool, // we initiated the logon, so its reply needs no answer
+    logout_sent: bool, // we initiated the logout
+    resend_until: Option<u64>, // inbound gap we asked to have resent
+    test_request_sent: Option<u64>, // when we last probed a silent counterparty
+    last_sent: u64,
+    last_received: u64,
+}
+
+// Outcome of one inbound message
+#[derive(Debug, Default)]
+pub struct Received {
+    pub replies: Vec<FixMessage>, // stamped session messages to send back
+    pub app: Option<FixMessage>,  // in-sequence application message for the gateway
+}
+
+impl FixSession {
+    pub fn new(sender_comp_id: &str, target_comp_id: &str) -> Self {
+        Self {
+            sender_comp_id: sender_comp_id.to_string(),
+            target_comp_id: target_comp_id.to_string(),
+            heartbeat_secs: DEFAULT_HEARTBEAT_SECS,
+            next_out_seq: 1,
+            next_in_seq: 1,
+            logged_on: false,
+            closed: false,
+            credentials: None,
+            sent: BTreeMap::new(),
+            logon_sent: false,
+            logout_sent: false,
+            resend_until: None,
+            test_request_sent: None,
+            last_sent: 0,
+            last_received: 0,
+        }
+    }
+
+    // Initiator side: open the session
+    pub fn logon(&mut self, heartbeat_secs: u64, now: u64) -> FixMessage {
+        self.heartbeat_secs = heartbeat_secs;
+        self.logon_sent = true;
+        self.closed = false;
+        let mut logon = FixMessage::new("A")
+            .with(tag::ENCRYPT_METHOD, 0)
+            .with(tag::HEART_BT_INT, heartbeat_secs);
+        if let Some((username, password)) = &self.credentials {
+            logon = logon
+                .with(tag::USERNAME, username)
+                .with(tag::PASSWORD, password);
+        }
+        self.send(logon, now)
+    }
+
+    // Assign the next sequence number, fill in the header and keep a copy for resends
+    pub fn send(&mut self, msg: FixMessage, now: u64) -> FixMessage {
+        let seq = self.next_out_seq;
+        let stamped = self.stamp(&msg, seq, now, None);
+        self.sent.insert(seq, stamped.clone());
+        self.next_out_seq += 1;
+        self.last_sent = now;
+        stamped
+    }
+
+    pub fn logout(&mut self, text: &str, now: u64) -> FixMessage {
+        self.logout_sent = true;
+        self.send(FixMessage::new("5").with(tag::TEXT, text), now)
+    }
+
+    fn stamp(&self, msg: &FixMessage, seq: u64, now: u64, orig_time: Option<&str>) -> FixMessage {
+        let mut stamped = FixMessage::new(msg.msg_type())
+            .with(tag::SENDER_COMP_ID, &self.sender_comp_id)
+            .with(tag::TARGET_COMP_ID, &self.target_comp_id)
+            .with(tag::MSG_SEQ_NUM, seq)
+            .with(tag::SENDING_TIME, utc_timestamp(now));
+        if let Some(orig_time) = orig_time {
+            stamped = stamped
+                .with(tag::POSS_DUP_FLAG, "Y")
+                .with(tag::ORIG_SENDING_TIME, orig_time);
+        }
+        stamped.fields.extend(
+            msg.fields
+                .iter()
+                .filter(|(tag, _)| !HEADER_TAGS.contains(tag))
+                .cloned(),
+        );
+        stamped
+    }
+
+    // Drop the session after a protocol violation
+    fn terminate(&mut self, text: &str, now: u64, received: &mut Received) {
+        received.replies.push(self.logout(text, now));
+        self.logged_on = false;
+        self.closed = true;
+    }
+
+    pub fn receive(&mut self, msg: FixMessage, now: u64) -> Received {
+        let mut received = Received::default();
+        self.last_received = now;
+        self.test_request_sent = None;
+        let msg_type = msg.msg_type().to_string();
+
+        if msg.get(tag::SENDER_COMP_ID) != Some(self.target_comp_id.as_str())
+            || msg.get(tag::TARGET_COMP_ID) != Some(self.sender_comp_id.as_str())
+        {
+            self.terminate("CompID problem", now, &mut received);
+            return received;
+        }
+        if !self.logged_on && msg_type != "A" {
+            self.terminate("first message must be a Logon", now, &mut received);
+            return received;
+        }
+        let Some(seq) = msg.seq_num() else {
+            self.terminate("missing MsgSeqNum", now, &mut received);
+            return received;
+        };
+        if msg_type == "A" && msg.get(tag::RESET_SEQ_NUM_FLAG) == Some("Y") {
+            self.next_in_seq = 1;
+        }
+        // SequenceReset in reset mode moves the inbound number whatever it is
+        if msg_type == "4" && msg.get(tag::GAP_FILL_FLAG) != Some("Y") {
+            if let Some(new_seq) = msg.get(tag::NEW_SEQ_NO).and_then(|s| s.parse().ok()) {
+                self.next_in_seq = new_seq;
+            }
+            return received;
+        }
+        if seq < self.next_in_seq {
+            if msg.get(tag::POSS_DUP_FLAG) != Some("Y") {
+                let text = format!("MsgSeqNum too low, expecting {}", self.next_in_seq);
+                self.terminate(&text, now, &mut received);
+            }
+            return received;
+        }
+
+        // Ahead of sequence: ask for the gap. Logons and resend requests are
+        // still acted on; anything else comes again in the resend.
+        let in_sequence = seq == self.next_in_seq;
+        if !in_sequence && msg_type != "A" && msg_type != "2" {
+            self.request_resend(seq, now, &mut received);
+            return received;
+        }
+        if in_sequence {
+            self.next_in_seq += 1;
+            if self
+                .resend_until
+                .is_some_and(|until| self.next_in_seq > until)
+            {
+                self.resend_until = None;
+            }
+        }
+
+        match msg_type.as_str() {
+            "A" => {
+                self.logged_on = true;
+                self.closed = false;
+                self.logout_sent = false;
+                if !self.logon_sent {
+                    self.heartbeat_secs = msg
+                        .get(tag::HEART_BT_INT)
+                        .and_then(|h| h.parse().ok())
+                        .unwrap_or(DEFAULT_HEARTBEAT_SECS);
+                    let reply = FixMessage::new("A")
+                        .with(tag::ENCRYPT_METHOD, 0)
+                        .with(tag::HEART_BT_INT, self.heartbeat_secs);
+                    received.replies.push(self.send(reply, now));
+                }
+                self.logon_sent = false;
+            }
+            "1" => {
+                let mut heartbeat = FixMessage::new("0");
+                if let Some(id) = msg.get(tag::TEST_REQ_ID) {
+                    heartbeat = heartbeat.with(tag::TEST_REQ_ID, id);
+                }
+                received.replies.push(self.send(heartbeat, now));
+            }
+            "2" => {
+                let begin = msg.get(tag::BEGIN_SEQ_NO).and_then(|s| s.parse().ok());
+                let end = msg.get(tag::END_SEQ_NO).and_then(|s| s.parse().ok());
+                received
+                    .replies
+                    .extend(self.resend(begin.unwrap_or(1), end.unwrap_or(0), now));
+            }
+            "4" => {
+                if let Some(new_seq) = msg.get(tag::NEW_SEQ_NO).and_then(|s| s.parse().ok()) {
+                    self.next_in_seq = self.next_in_seq.max(new_seq);
+                }
+            }
+            "5" => {
+                if !self.logout_sent {
+                    received.replies.push(self.logout("", now));
+                }
+                self.logged_on = false;
+                self.closed = true;
+            }
+            "0" | "3" => {}
+            _ => received.app = Some(msg),
+        }
+
+        if !in_sequence {
+            self.request_resend(seq, now, &mut received);
+        }
+        received
+    }
+
+    fn request_resend(&mut self, seq: u64, now: u64, received: &mut Received) {
+        if self.resend_until.is_some() {
+            return;
+        }
+        self.resend_until = Some(seq);
+        let request = FixMessage::new("2")
+            .with(tag::BEGIN_SEQ_NO, self.next_in_seq)
+            .with(tag::END_SEQ_NO, 0);
+        received.replies.push(self.send(request, now));
+    }
+
+    // Replay sent application messages as possible duplicates; runs of
+    // session messages are skipped with a SequenceReset-GapFill. `end` 0
+    // means everything sent so far.
+    fn resend(&self, begin: u64, end: u64, now: u64) -> Vec<FixMessage> {
+        let last = self.next_out_seq - 1;
+        let end = if end == 0 { last } else { end.min(last) };
+        let mut messages = Vec::new();
+        let mut gap_start = None;
+        for seq in begin..=end {
+            match self.sent.get(&seq).filter(|msg| !msg.is_admin()) {
+                Some(msg) => {
+                    if let Some(start) = gap_start.take() {
+                        messages.push(self.gap_fill(start, seq, now));
+                    }
+                    let orig_time = msg.get(tag::SENDING_TIME).unwrap_or_default();
+                    messages.push(self.stamp(msg, seq, now, Some(orig_time)));
+                }
+                None => {
+                    gap_start.get_or_insert(seq);
+                }
+            }
+        }
+        if let Some(start) = gap_start {
+            messages.push(self.gap_fill(start, end + 1, now));
+        }
+        messages
+    }
+
+    fn gap_fill(&self, seq: u64, new_seq: u64, now: u64) -> FixMessage {
+        let reset = FixMessage::new("4")
+            .with(tag::GAP_FILL_FLAG, "Y")
+            .with(tag::NEW_SEQ_NO, new_seq);
+        self.stamp(&reset, seq, now, Some(&utc_timestamp(now)))
+    }
+
+    // Heartbeats when we have been quiet, a TestRequest when the counterparty
+    // has, and a logout if it stays silent for another interval
+    pub fn on_timer(&mut self, now: u64) -> Vec<FixMessage> {
+        if !self.logged_on {
+            return Vec::new();
+        }
+        let interval = self.heartbeat_secs.max(1);
+        let mut messages = Vec::new();
+        match self.test_request_sent {
+            Some(at) if now >= at + interval => {
+                messages.push(self.logout("heartbeat timeout", now));
+                self.logged_on = false;
+                self.closed = true;
+                return messages;
+            }
+            None if now > self.last_received + interval => {
+                self.test_request_sent = Some(now);
+                let request = FixMessage::new("1").with(tag::TEST_REQ_ID, now);
+                messages.push(self.send(request, now));
+            }
+            _ => {}
+        }
+        if now >= self.last_sent + interval {
+            messages.push(self.send(FixMessage::new("0"), now));
+        }
+        messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Client and gateway sessions logged on to each other
+    fn logged_on_pair() -> (FixSession, FixSession) {
+        let mut client = FixSession::new("MM1", "CLOB");
+        let mut gateway = FixSession::new("CLOB", "MM1");
+        let logon = client.logon(30, 0);
+        let reply = gateway.receive(logon, 0).replies.remove(0);
+        client.receive(reply, 0);
+        assert!(client.logged_on && gateway.logged_on);
+        (client, gateway)
+    }
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let msg = FixMessage::new("D")
+            .with(tag::CL_ORD_ID, "c1")
+            .with(tag::PRICE, 0.6);
+        let wire = msg.encode();
+        assert!(wire.starts_with("8=FIX.4.4\x019=18\x0135=D\x01"));
+        assert_eq!(frame_len(wire.as_bytes()).unwrap(), Some(wire.len()));
+        assert_eq!(frame_len(&wire.as_bytes()[..10]).unwrap(), None);
+        assert_eq!(FixMessage::decode(&wire).unwrap(), msg);
+
+        let corrupted = wire.replace("0.6", "0.7");
+        assert!(FixMessage::decode(&corrupted).is_err());
+        assert_eq!(utc_timestamp(951_782_400), "20000229-00:00:00");
+    }
+
+    #[test]
+    fn test_gap_triggers_resend_with_gap_fill() {
+        let (mut client, mut gateway) = logged_on_pair();
+        gateway.on_timer(40); // test request, an admin message
+        let report = gateway.send(FixMessage::new("8").with(tag::CL_ORD_ID, "c1"), 41);
+
+        // The client misses everything after the logon and sees the report first
+        let received = client.receive(report, 41);
+        assert!(received.app.is_none());
+        let request = &received.replies[0];
+        assert_eq!(request.msg_type(), "2");
+        assert_eq!(request.get(tag::BEGIN_SEQ_NO), Some("2"));
+
+        let resent = gateway.receive(request.clone(), 41).replies;
+        let types: Vec<&str> = resent.iter().map(|m| m.msg_type()).collect();
+        assert_eq!(types, vec!["4", "8"]);
+        assert_eq!(resent[0].get(tag::NEW_SEQ_NO), Some("3"));
+        assert_eq!(resent[1].get(tag::POSS_DUP_FLAG), Some("Y"));
+
+        assert!(client.receive(resent[0].clone(), 42).app.is_none());
+        let report = client.receive(resent[1].clone(), 42).app.unwrap();
+        assert_eq!(report.get(tag::CL_ORD_ID), Some("c1"));
+        assert_eq!(client.next_in_seq, 4);
+    }
+
+    #[test]
+    fn test_silent_counterparty_is_logged_out() {
+        let (_, mut gateway) = logged_on_pair();
+        let probe = gateway.on_timer(31);
+        assert_eq!(probe[0].msg_type(), "1");
+        assert!(gateway.on_timer(40).is_empty());
+        assert_eq!(gateway.on_timer(61)[0].msg_type(), "5");
+        assert!(gateway.closed);
+    }
+}
@@ -0,0 +1,963 @@
+use crate::ffi::{outcome_label, parse_outcome};
+use crate::fix::{frame_len, tag, FixMessage, FixSession};
+use crate::{
+    check_price, current_timestamp, EngineEvent, MatchingEngine, Order, OutcomeId, Price, Side,
+    Trade,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// --------------------- FIX Order Entry ---------------------
+// Maps NewOrderSingle, OrderCancelRequest and OrderCancelReplaceRequest onto
+// the engine and answers with ExecutionReports. Each counterparty logs on
+// with a CompID registered to the wallet its orders trade as, giving that
+// wallet as Username (553) and its secret as Password (554). Symbol (55) is
+// the market id and SecuritySubType (762) the outcome, YES by default.
+
+// FIX orders get engine ids from their own range, clear of client and quote ids
+pub const FIX_ORDER_ID_BASE: u64 = 1 << 62;
+
+const QTY_EPSILON: f64 = 1e-9;
+
+// Message for the counterparty with the given CompID
+pub type Outbound = (String, FixMessage);
+
+#[derive(Clone, Debug)]
+struct FixOrder {
+    comp_id: String,
+    cl_ord_id: String,
+    market_id: String,
+    outcome: OutcomeId,
+    side: Side,
+    price: f64,
+    qty: f64, // OrderQty, including what already filled
+    cum_qty: f64,
+    notional: f64, // filled qty times price, for AvgPx
+}
+
+impl FixOrder {
+    fn leaves(&self) -> f64 {
+        (self.qty - self.cum_qty).max(0.0)
+    }
+
+    fn filled(&self) -> bool {
+        self.leaves() <= QTY_EPSILON
+    }
+
+    // Trade this order, under engine id `id`, took part in
+    fn traded_in(&self, id: u64, trade: &Trade) -> bool {
+        let order_id = match self.side {
+            Side::Buy => trade.buy_order_id,
+            Side::Sell => trade.sell_order_id,
+        };
+        trade.market_id == self.market_id && trade.outcome == self.outcome && order_id == id
+    }
+}
+
+struct Counterparty {
+    user: String, // wallet its orders trade as
+    password: String,
+}
+
+pub struct FixGateway {
+    pub comp_id: String, // our CompID
+    pub engine: MatchingEngine,
+    counterparties: HashMap<String, Counterparty>, // by CompID
+    sessions: HashMap<String, FixSession>,
+    orders: BTreeMap<u64, FixOrder>, // open orders by engine id
+    cl_ord_ids: HashMap<(String, String), u64>, // (CompID, ClOrdID) -> engine id of an open order
+    next_order_id: u64,
+    next_exec_id: u64,
+}
+
+impl FixGateway {
+    pub fn new(comp_id: &str, engine: MatchingEngine) -> Self {
+        Self {
+            comp_id: comp_id.to_string(),
+            engine,
+            counterparties: HashMap::new(),
+            sessions: HashMap::new(),
+            orders: BTreeMap::new(),
+            cl_ord_ids: HashMap::new(),
+            next_order_id: FIX_ORDER_ID_BASE,
+            next_exec_id: 1,
+        }
+    }
+
+    pub fn add_counterparty(&mut self, comp_id: &str, user: &str, password: &str) {
+        let counterparty = Counterparty {
+            user: user.to_string(),
+            password: password.to_string(),
+        };
+        self.counterparties
+            .insert(comp_id.to_string(), counterparty);
+    }
+
+    pub fn accepts(&self, comp_id: &str) -> bool {
+        self.counterparties.contains_key(comp_id)
+    }
+
+    // A Logon from a registered CompID with its Username and Password
+    pub fn authenticates(&self, logon: &FixMessage) -> bool {
+        let Some(counterparty) = logon
+            .get(tag::SENDER_COMP_ID)
+            .and_then(|comp_id| self.counterparties.get(comp_id))
+        else {
+            return false;
+        };
+        logon.msg_type() == "A"
+            && logon.get(tag::USERNAME) == Some(counterparty.user.as_str())
+            && logon
+                .get(tag::PASSWORD)
+                .is_some_and(|password| same_secret(password, &counterparty.password))
+    }
+
+    pub fn session(&self, comp_id: &str) -> Option<&FixSession> {
+        self.sessions.get(comp_id)
+    }
+
+    // True once the counterparty's session has ended and its connection should close
+    pub fn is_closed(&self, comp_id: &str) -> bool {
+        self.sessions.get(comp_id).is_none_or(|s| s.closed)
+    }
+
+    // The connection dropped without a logout; the next one has to log on again
+    pub fn disconnected(&mut self, comp_id: &str) {
+        if let Some(session) = self.sessions.get_mut(comp_id) {
+            session.logged_on = false;
+        }
+    }
+
+    pub fn receive(&mut self, msg: FixMessage, now: u64) -> Vec<Outbound> {
+        let Some(comp_id) = msg.get(tag::SENDER_COMP_ID).map(str::to_string) else {
+            return Vec::new();
+        };
+        if !self.accepts(&comp_id) {
+            return Vec::new();
+        }
+        // Turned away without touching the counterparty's real session
+        if msg.msg_type() == "A" && !self.authenticates(&msg) {
+            let logout =
+                FixSession::new(&self.comp_id, &comp_id).logout("invalid credentials", now);
+            return vec![(comp_id, logout)];
+        }
+        let session = self
+            .sessions
+            .entry(comp_id.clone())
+            .or_insert_with(|| FixSession::new(&self.comp_id, &comp_id));
+        let received = session.receive(msg, now);
+        let mut out: Vec<Outbound> = received
+            .replies
+            .into_iter()
+            .map(|reply| (comp_id.clone(), reply))
+            .collect();
+
+        if let Some(app) = received.app {
+            let reports = match app.msg_type() {
+                "D" => self.new_order(&comp_id, &app),
+                "F" => self.cancel(&comp_id, &app),
+                "G" => self.replace(&comp_id, &app),
+                _ => {
+                    let reject = FixMessage::new("3")
+                        .with(tag::REF_SEQ_NUM, app.seq_num().unwrap_or_default())
+                        .with(tag::REF_MSG_TYPE, app.msg_type())
+                        .with(tag::SESSION_REJECT_REASON, 11) // invalid MsgType
+                        .with(tag::TEXT, "unsupported message type");
+                    vec![(comp_id.clone(), reject)]
+                }
+            };
+            out.extend(self.stamp(reports, now));
+        }
+        out
+    }
+
+    // Session timers, then the engine's timed work; fills it produced on
+    // resting FIX orders are reported like any other
+    pub fn on_timer(&mut self, now: u64) -> Vec<Outbound> {
+        let mut out: Vec<Outbound> = Vec::new();
+        for (comp_id, session) in self.sessions.iter_mut() {
+            out.extend(
+                session
+                    .on_timer(now)
+                    .into_iter()
+                    .map(|msg| (comp_id.clone(), msg)),
+            );
+        }
+        let trades: Vec<Trade> = self
+            .engine
+            .tick(now)
+            .into_iter()
+            .filter_map(|event| match event {
+                EngineEvent::Trade(trade) => Some(trade),
+                _ => None,
+            })
+            .collect();
+        // Anything else that left a book was cancelled by the engine (mass
+        // cancel, session expiry, market close)
+        let mut reports = self.apply_trades(&trades);
+        let ids: Vec<u64> = self.orders.keys().copied().collect();
+        reports.extend(self.cancel_gone(ids));
+        out.extend(self.stamp(reports, now));
+        out
+    }
+
+    // Give application messages their counterparty's next sequence number
+    fn stamp(&mut self, reports: Vec<Outbound>, now: u64) -> Vec<Outbound> {
+        reports
+            .into_iter()
+            .filter_map(|(comp_id, msg)| {
+                let session = self.sessions.get_mut(&comp_id)?;
+                Some((comp_id, session.send(msg, now)))
+            })
+            .collect()
+    }
+
+    // ----- Order handling -----
+    fn new_order(&mut self, comp_id: &str, msg: &FixMessage) -> Vec<Outbound> {
+        let order = match self.parse_order(comp_id, msg) {
+            Ok(order) => order,
+            Err(text) => return vec![(comp_id.to_string(), self.rejected(msg, &text))],
+        };
+        if self
+            .cl_ord_ids
+            .contains_key(&(comp_id.to_string(), order.cl_ord_id.clone()))
+        {
+            return vec![(comp_id.to_string(), self.rejected(msg, "duplicate ClOrdID"))];
+        }
+        match self.submit(order, None) {
+            Ok(reports) => reports,
+            Err(text) => vec![(comp_id.to_string(), self.rejected(msg, &text))],
+        }
+    }
+
+    fn cancel(&mut self, comp_id: &str, msg: &FixMessage) -> Vec<Outbound> {
+        let Some(id) = self.lookup(comp_id, msg) else {
+            return vec![self.cancel_rejected(comp_id, msg, None, "unknown order")];
+        };
+        let order = self.orders[&id].clone();
+        if !self
+            .engine
+            .cancel_order(&order.market_id, order.outcome, id)
+        {
+            return vec![self.cancel_rejected(comp_id, msg, Some(id), "order is not resting")];
+        }
+        let mut report = self
+            .cancelled(id)
+            .with(tag::ORIG_CL_ORD_ID, &order.cl_ord_id);
+        set_field(
+            &mut report,
+            tag::CL_ORD_ID,
+            msg.get(tag::CL_ORD_ID).unwrap_or_default(),
+        );
+        vec![(comp_id.to_string(), report)]
+    }
+
+    // Cancel and re-enter at the new price and size; the replacement joins the
+    // back of the queue. OrderQty is the new total including what has filled.
+    fn replace(&mut self, comp_id: &str, msg: &FixMessage) -> Vec<Outbound> {
+        let Some(id) = self.lookup(comp_id, msg) else {
+            return vec![self.cancel_rejected(comp_id, msg, None, "unknown order")];
+        };
+        let old = self.orders[&id].clone();
+        let mut new = match self.parse_order(comp_id, msg) {
+            Ok(new) => new,
+            Err(text) => return vec![self.cancel_rejected(comp_id, msg, Some(id), &text)],
+        };
+        if new.market_id != old.market_id || new.outcome != old.outcome || new.side != old.side {
+            let text = "cannot change symbol, outcome or side";
+            return vec![self.cancel_rejected(comp_id, msg, Some(id), text)];
+        }
+        if self
+            .cl_ord_ids
+            .contains_key(&(comp_id.to_string(), new.cl_ord_id.clone()))
+        {
+            return vec![self.cancel_rejected(comp_id, msg, Some(id), "duplicate ClOrdID")];
+        }
+        if new.qty <= old.cum_qty + QTY_EPSILON {
+            let text = "OrderQty not above the filled quantity";
+            return vec![self.cancel_rejected(comp_id, msg, Some(id), text)];
+        }
+        if !self.engine.cancel_order(&old.market_id, old.outcome, id) {
+            return vec![self.cancel_rejected(comp_id, msg, Some(id), "order is not resting")];
+        }
+
+        self.forget(id);
+        new.cum_qty = old.cum_qty;
+        new.notional = old.notional;
+        match self.submit(new, Some(&old.cl_ord_id)) {
+            Ok(reports) => reports,
+            Err(text) => {
+                // The original is gone either way
+                self.orders.insert(id, old);
+                let mut out = vec![self.cancel_rejected(comp_id, msg, Some(id), &text)];
+                out.push((comp_id.to_string(), self.cancelled(id)));
+                out
+            }
+        }
+    }
+
+    fn parse_order(&self, comp_id: &str, msg: &FixMessage) -> Result<FixOrder, String> {
+        let cl_ord_id = msg.get(tag::CL_ORD_ID).ok_or("missing ClOrdID")?;
+        let market_id = msg.get(tag::SYMBOL).ok_or("missing Symbol")?;
+        let outcome = msg.get(tag::SECURITY_SUB_TYPE).unwrap_or("YES");
+        let outcome = parse_outcome(outcome).ok_or("unknown SecuritySubType")?;
+        let side = match msg.get(tag::SIDE) {
+            Some("1") => Side::Buy,
+            Some("2") => Side::Sell,
+            _ => return Err("Side must be 1 (buy) or 2 (sell)".to_string()),
+        };
+        if msg.get(tag::ORD_TYPE) != Some("2") {
+            return Err("only limit orders (OrdType 2) are supported".to_string());
+        }
+        let price = msg.get_f64(tag::PRICE).ok_or("missing Price")?;
+        check_price(Price(price)).map_err(|e| e.to_string())?;
+        let qty = msg
+            .get_f64(tag::ORDER_QTY)
+            .filter(|&qty| qty.is_finite() && qty > 0.0)
+            .ok_or("OrderQty must be positive")?;
+        Ok(FixOrder {
+            comp_id: comp_id.to_string(),
+            cl_ord_id: cl_ord_id.to_string(),
+            market_id: market_id.to_string(),
+            outcome,
+            side,
+            price,
+            qty,
+            cum_qty: 0.0,
+            notional: 0.0,
+        })
+    }
+
+    // Place an order on the engine and report it with its immediate fills.
+    // `replaces` is the ClOrdID of the order it replaces, if any.
+    fn submit(&mut self, order: FixOrder, replaces: Option<&str>) -> Result<Vec<Outbound>, String> {
+        let id = self.next_order_id;
+        let engine_order = Order {
+            id,
+            user: self.counterparties[&order.comp_id].user.clone(),
+            side: order.side,
+            price: Price(order.price),
+            qty: order.leaves(),
+            timestamp: current_timestamp(),
+            iceberg: None,
+        };
+        let trades = self
+            .engine
+            .place_order(&order.market_id, order.outcome, engine_order)
+            .map_err(|e| e.to_string())?;
+        self.next_order_id += 1;
+
+        let comp_id = order.comp_id.clone();
+        self.cl_ord_ids
+            .insert((comp_id.clone(), order.cl_ord_id.clone()), id);
+        self.orders.insert(id, order);
+        let report = match replaces {
+            Some(orig) => self.report(id, "5").with(tag::ORIG_CL_ORD_ID, orig),
+            None => self.report(id, "0"),
+        };
+        let mut out = vec![(comp_id, report)];
+
+        // Fills of the new order and of the orders it traded against, then
+        // whatever of them did not stay on the book
+        out.extend(self.apply_trades(&trades));
+        let touched = trades
+            .iter()
+            .flat_map(|t| [t.buy_order_id, t.sell_order_id])
+            .chain([id]);
+        out.extend(self.cancel_gone(touched.collect()));
+        Ok(out)
+    }
+
+    // Fill FIX orders from the trades they took part in, at each trade's
+    // price and size: an auction or batch clears every order at one price
+    fn apply_trades(&mut self, trades: &[Trade]) -> Vec<Outbound> {
+        let mut out = Vec::new();
+        for trade in trades {
+            for id in [trade.buy_order_id, trade.sell_order_id] {
+                if self
+                    .orders
+                    .get(&id)
+                    .is_some_and(|order| order.traded_in(id, trade))
+                {
+                    out.push(self.fill(id, trade.qty, trade.price.0));
+                }
+            }
+        }
+        out
+    }
+
+    // Report the unfilled remainder of orders that are no longer resting as
+    // cancelled; run after their fills are applied
+    fn cancel_gone(&mut self, ids: Vec<u64>) -> Vec<Outbound> {
+        let mut out = Vec::new();
+        for id in ids {
+            let Some(order) = self.orders.get(&id) else {
+                continue; // not ours, or fully filled
+            };
+            if order.leaves() - self.resting_qty(id) > QTY_EPSILON {
+                let comp_id = order.comp_id.clone();
+                out.push((comp_id, self.cancelled(id)));
+            }
+        }
+        out
+    }
+
+    // Size of an order still on its book, looked up at its own price level
+    fn resting_qty(&self, id: u64) -> f64 {
+        let order = &self.orders[&id];
+        let Some(book) = self.engine.book(&order.market_id, order.outcome) else {
+            return 0.0;
+        };
+        let level = match order.side {
+            Side::Buy => book.bids.get(&Price(order.price)),
+            Side::Sell => book.asks.get(&Price(order.price)),
+        };
+        level
+            .and_then(|orders| orders.iter().find(|o| o.id == id))
+            .map_or(0.0, |o| o.total_qty())
+    }
+
+    fn lookup(&self, comp_id: &str, msg: &FixMessage) -> Option<u64> {
+        let orig = msg.get(tag::ORIG_CL_ORD_ID)?;
+        self.cl_ord_ids
+            .get(&(comp_id.to_string(), orig.to_string()))
+            .copied()
+    }
+
+    fn forget(&mut self, id: u64) -> Option<FixOrder> {
+        let order = self.orders.remove(&id)?;
+        self.cl_ord_ids
+            .remove(&(order.comp_id.clone(), order.cl_ord_id.clone()));
+        Some(order)
+    }
+
+    // ----- Execution reports -----
+    fn fill(&mut self, id: u64, qty: f64, price: f64) -> Outbound {
+        let order = self.orders.get_mut(&id).unwrap();
+        order.cum_qty += qty;
+        order.notional += qty * price;
+        let comp_id = order.comp_id.clone();
+        let report = self
+            .report(id, "F")
+            .with(tag::LAST_QTY, qty)
+            .with(tag::LAST_PX, price);
+        if self.orders[&id].filled() {
+            self.forget(id);
+        }
+        (comp_id, report)
+    }
+
+    // Report a cancelled order and stop tracking it
+    fn cancelled(&mut self, id: u64) -> FixMessage {
+        let mut report = self.report(id, "4");
+        set_field(&mut report, tag::ORD_STATUS, "4");
+        set_field(&mut report, tag::LEAVES_QTY, "0");
+        self.forget(id);
+        report
+    }
+
+    fn report(&mut self, id: u64, exec_type: &str) -> FixMessage {
+        let exec_id = self.next_exec_id;
+        self.next_exec_id += 1;
+        let order = &self.orders[&id];
+        let status = if order.filled() {
+            "2"
+        } else if order.cum_qty > 0.0 {
+            "1"
+        } else {
+            "0"
+        };
+        let avg_px = if order.cum_qty > 0.0 {
+            order.notional / order.cum_qty
+        } else {
+            0.0
+        };
+        FixMessage::new("8")
+            .with(tag::ORDER_ID, id)
+            .with(tag::CL_ORD_ID, &order.cl_ord_id)
+            .with(tag::EXEC_ID, exec_id)
+            .with(tag::EXEC_TYPE, exec_type)
+            .with(tag::ORD_STATUS, status)
+            .with(tag::SYMBOL, &order.market_id)
+            .with(tag::SECURITY_SUB_TYPE, outcome_label(order.outcome))
+            .with(tag::SIDE, side_code(order.side))
+            .with(tag::ORDER_QTY, order.qty)
+            .with(tag::PRICE, order.price)
+            .with(tag::LEAVES_QTY, order.leaves())
+            .with(tag::CUM_QTY, order.cum_qty)
+            .with(tag::AVG_PX, avg_px)
+    }
+
+    fn rejected(&mut self, msg: &FixMessage, text: &str) -> FixMessage {
+        let exec_id = self.next_exec_id;
+        self.next_exec_id += 1;
+        let echo = |tag| msg.get(tag).unwrap_or_default().to_string();
+        FixMessage::new("8")
+            .with(tag::ORDER_ID, "NONE")
+            .with(tag::CL_ORD_ID, echo(tag::CL_ORD_ID))
+            .with(tag::EXEC_ID, exec_id)
+            .with(tag::EXEC_TYPE, "8")
+            .with(tag::ORD_STATUS, "8")
+            .with(tag::SYMBOL, echo(tag::SYMBOL))
+            .with(tag::SIDE, echo(tag::SIDE))
+            .with(tag::LEAVES_QTY, 0)
+            .with(tag::CUM_QTY, 0)
+            .with(tag::AVG_PX, 0)
+            .with(tag::TEXT, text)
+    }
+
+    fn cancel_rejected(
+        &self,
+        comp_id: &str,
+        msg: &FixMessage,
+        id: Option<u64>,
+        text: &str,
+    ) -> Outbound {
+        let (order_id, status) = match id.and_then(|id| self.orders.get(&id).map(|o| (id, o))) {
+            Some((id, order)) if order.cum_qty > 0.0 => (id.to_string(), "1"),
+            Some((id, _)) => (id.to_string(), "0"),
+            None => ("NONE".to_string(), "8"),
+        };
+        let response_to = if msg.msg_type() == "G" { "2" } else { "1" };
+        let reject = FixMessage::new("9")
+            .with(tag::ORDER_ID, order_id)
+            .with(tag::CL_ORD_ID, msg.get(tag::CL_ORD_ID).unwrap_or_default())
+            .with(
+                tag::ORIG_CL_ORD_ID,
+                msg.get(tag::ORIG_CL_ORD_ID).unwrap_or_default(),
+            )
+            .with(tag::ORD_STATUS, status)
+            .with(tag::CXL_REJ_RESPONSE_TO, response_to)
+            .with(tag::CXL_REJ_REASON, if id.is_some() { 0 } else { 1 }) // too late / unknown
+            .with(tag::TEXT, text);
+        (comp_id.to_string(), reject)
+    }
+}
+
+fn side_code(side: Side) -> &'static str {
+    match side {
+        Side::Buy => "1",
+        Side::Sell => "2",
+    }
+}
+
+// Compare secrets without stopping at the first differing byte
+pub(crate) fn same_secret(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn set_field(msg: &mut FixMessage, tag: u32, value: &str) {
+    if let Some(field) = msg.fields.iter_mut().find(|(t, _)| *t == tag) {
+        field.1 = value.to_string();
+    }
+}
+
+// ----- Outbound queues -----
+// Each connection has a writer thread fed through a bounded queue, so a
+// client that stops reading never stalls the gateway behind its lock. A
+// client that lets its queue fill up is disconnected.
+const OUTBOX_CAPACITY: usize = 1024;
+
+pub(crate) struct Outbox {
+    queue: SyncSender<Vec<u8>>,
+    hang_up: Arc<dyn Fn() + Send + Sync>,
+}
+
+impl Outbox {
+    // Dropping the outbox lets the writer flush what is queued, then hang up
+    pub(crate) fn spawn(
+        mut writer: impl Write + Send + 'static,
+        hang_up: impl Fn() + Send + Sync + 'static,
+    ) -> Self {
+        let (queue, pending) = sync_channel::<Vec<u8>>(OUTBOX_CAPACITY);
+        let hang_up: Arc<dyn Fn() + Send + Sync> = Arc::new(hang_up);
+        let done = Arc::clone(&hang_up);
+        thread::spawn(move || {
+            for bytes in pending {
+                if writer.write_all(&bytes).is_err() {
+                    break;
+                }
+            }
+            done();
+        });
+        Self { queue, hang_up }
+    }
+
+    pub(crate) fn push(&self, bytes: Vec<u8>) {
+        if self.queue.try_send(bytes).is_err() {
+            (self.hang_up)();
+        }
+    }
+}
+
+// ----- TCP acceptor -----
+struct Connections {
+    gateway: FixGateway,
+    outboxes: HashMap<String, (u64, Outbox)>, // CompID -> connection number and queue
+    next_connection: u64,
+}
+
+impl Connections {
+    // Queue messages for their counterparties, then hang up on ended sessions.
+    // Messages for a counterparty that is not connected wait for its resend request.
+    fn deliver(&mut self, out: Vec<Outbound>) {
+        for (comp_id, msg) in out {
+            if let Some((_, outbox)) = self.outboxes.get(&comp_id) {
+                outbox.push(msg.encode().into_bytes());
+            }
+        }
+        let gateway = &self.gateway;
+        self.outboxes
+            .retain(|comp_id, _| !gateway.is_closed(comp_id));
+    }
+}
+
+// Accept FIX connections on `listener`, one reader thread per connection
+// and a timer thread for heartbeats and the engine's `tick`
+pub fn serve(listener: TcpListener, gateway: FixGateway) -> std::io::Result<()> {
+    let shared = Arc::new(Mutex::new(Connections {
+        gateway,
+        outboxes: HashMap::new(),
+        next_connection: 0,
+    }));
+
+    let timer = Arc::clone(&shared);
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(1));
+        let mut connections = timer.lock().unwrap();
+        let out = connections.gateway.on_timer(current_timestamp());
+        connections.deliver(out);
+    });
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let shared = Arc::clone(&shared);
+        thread::spawn(move || handle_connection(stream, shared));
+    }
+    Ok(())
+}
+
+fn handle_connection(mut stream: TcpStream, shared: Arc<Mutex<Connections>>) {
+    let mut bound: Option<(String, u64)> = None; // CompID and connection number after logon
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    'read: loop {
+        let n = match stream.read(&mut chunk) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        buf.extend_from_slice(&chunk[..n]);
+
+        loop {
+            let len = match frame_len(&buf) {
+                Ok(Some(len)) => len,
+                Ok(None) => break,
+                Err(_) => break 'read, // cannot find the next message boundary
+            };
+            let raw: Vec<u8> = buf.drain(..len).collect();
+            // Garbled messages are dropped; the gap they leave is resent
+            let Some(msg) = std::str::from_utf8(&raw)
+                .ok()
+                .and_then(|raw| FixMessage::decode(raw).ok())
+            else {
+                continue;
+            };
+            let sender = msg.get(tag::SENDER_COMP_ID).unwrap_or_default().to_string();
+
+            let mut connections = shared.lock().unwrap();
+            match &bound {
+                // First message binds the connection to one counterparty
+                None => {
+                    if !connections.gateway.authenticates(&msg)
+                        || connections.outboxes.contains_key(&sender)
+                    {
+                        break 'read;
+                    }
+                    let (Ok(writer), Ok(closer)) = (stream.try_clone(), stream.try_clone()) else {
+                        break 'read;
+                    };
+                    let outbox = Outbox::spawn(writer, move || {
+                        let _ = closer.shutdown(Shutdown::Both);
+                    });
+                    let number = connections.next_connection;
+                    connections.next_connection += 1;
+                    connections
+                        .outboxes
+                        .insert(sender.clone(), (number, outbox));
+                    bound = Some((sender.clone(), number));
+                }
+                Some((comp_id, _)) if *comp_id != sender => continue,
+                Some(_) => {}
+            }
+            let out = connections.gateway.receive(msg, current_timestamp());
+            connections.deliver(out);
+            if connections.gateway.is_closed(&sender) {
+                break 'read;
+            }
+        }
+    }
+
+    // A bound connection is hung up by its writer once the queue is sent,
+    // so a Logout reply is not cut off on its way out
+    let Some((comp_id, number)) = bound else {
+        let _ = stream.shutdown(Shutdown::Both);
+        return;
+    };
+    let mut connections = shared.lock().unwrap();
+    if connections
+        .outboxes
+        .get(&comp_id)
+        .is_some_and(|(n, _)| *n == number)
+    {
+        connections.outboxes.remove(&comp_id);
+    }
+    connections.gateway.disconnected(&comp_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MarketCommand;
+
+    fn gateway() -> FixGateway {
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        engine.create_market("m");
+        engine
+            .apply_market_command("admin", "m", MarketCommand::Open)
+            .unwrap();
+        let mut gateway = FixGateway::new("CLOB", engine);
+        gateway.add_counterparty("MM1", "maker", "maker-secret");
+        gateway.add_counterparty("MM2", "taker", "taker-secret");
+        gateway
+    }
+
+    fn new_order(cl_ord_id: &str, side: &str, price: f64, qty: f64) -> FixMessage {
+        FixMessage::new("D")
+            .with(tag::CL_ORD_ID, cl_ord_id)
+            .with(tag::SYMBOL, "m")
+            .with(tag::SIDE, side)
+            .with(tag::ORD_TYPE, 2)
+            .with(tag::PRICE, price)
+            .with(tag::ORDER_QTY, qty)
+    }
+
+    fn field(out: &[Outbound], i: usize, tag: u32) -> (&str, &str) {
+        (out[i].0.as_str(), out[i].1.get(tag).unwrap())
+    }
+
+    // Client sessions logged on to the gateway, sans sockets
+    fn logon(gateway: &mut FixGateway, comp_id: &str, user: &str) -> FixSession {
+        let mut client = FixSession::new(comp_id, "CLOB");
+        client.credentials = Some((user.to_string(), format!("{user}-secret")));
+        let logon = client.logon(30, 0);
+        for (_, reply) in gateway.receive(logon, 0) {
+            client.receive(reply, 0);
+        }
+        client
+    }
+
+    #[test]
+    fn test_fills_reported_to_both_sides() {
+        let mut gateway = gateway();
+        let mut maker = logon(&mut gateway, "MM1", "maker");
+        let mut taker = logon(&mut gateway, "MM2", "taker");
+
+        let out = gateway.receive(maker.send(new_order("a1", "2", 0.6, 10.0), 1), 1);
+        assert_eq!(field(&out, 0, tag::EXEC_TYPE), ("MM1", "0"));
+
+        // Replace to a lower price and larger size
+        let replace = FixMessage::new("G")
+            .with(tag::ORIG_CL_ORD_ID, "a1")
+            .with(tag::CL_ORD_ID, "a2")
+            .with(tag::SYMBOL, "m")
+            .with(tag::SIDE, 2)
+            .with(tag::ORD_TYPE, 2)
+            .with(tag::PRICE, 0.55)
+            .with(tag::ORDER_QTY, 12);
+        let out = gateway.receive(maker.send(replace, 2), 2);
+        assert_eq!(field(&out, 0, tag::EXEC_TYPE), ("MM1", "5"));
+        assert_eq!(field(&out, 0, tag::LEAVES_QTY).1, "12");
+
+        let out = gateway.receive(taker.send(new_order("b1", "1", 0.7, 5.0), 3), 3);
+        let types: Vec<(&str, &str)> = (0..out.len())
+            .map(|i| field(&out, i, tag::EXEC_TYPE))
+            .collect();
+        assert_eq!(types, vec![("MM2", "0"), ("MM2", "F"), ("MM1", "F")]);
+        assert_eq!(field(&out, 1, tag::ORD_STATUS).1, "2");
+        assert_eq!(field(&out, 1, tag::LAST_PX).1, "0.55");
+        assert_eq!(field(&out, 2, tag::CUM_QTY).1, "5");
+        assert_eq!(field(&out, 2, tag::LEAVES_QTY).1, "7");
+
+        let cancel = FixMessage::new("F")
+            .with(tag::ORIG_CL_ORD_ID, "a1")
+            .with(tag::CL_ORD_ID, "a3");
+        let out = gateway.receive(maker.send(cancel, 4), 4);
+        assert_eq!(out[0].1.msg_type(), "9"); // a1 was replaced by a2
+        let cancel = FixMessage::new("F")
+            .with(tag::ORIG_CL_ORD_ID, "a2")
+            .with(tag::CL_ORD_ID, "a3");
+        let out = gateway.receive(maker.send(cancel, 5), 5);
+        assert_eq!(field(&out, 0, tag::ORD_STATUS), ("MM1", "4"));
+        assert!(gateway
+            .engine
+            .book("m", crate::YES)
+            .unwrap()
+            .asks
+            .is_empty());
+    }
+
+    #[test]
+    fn test_rejects_invalid_orders() {
+        let mut gateway = gateway();
+        let mut maker = logon(&mut gateway, "MM1", "maker");
+        let out = gateway.receive(maker.send(new_order("a1", "1", 1.5, 10.0), 1), 1);
+        assert_eq!(field(&out, 0, tag::ORD_STATUS).1, "8");
+        let out = gateway.receive(maker.send(new_order("a2", "3", 0.5, 10.0), 2), 2);
+        assert_eq!(field(&out, 0, tag::ORD_STATUS).1, "8");
+        let unknown = FixMessage::new("V");
+        assert_eq!(
+            gateway.receive(maker.send(unknown, 3), 3)[0].1.msg_type(),
+            "3"
+        );
+
+        // An unbounded size is not a size, on a new order or a replace
+        for (i, qty) in ["NaN", "inf"].into_iter().enumerate() {
+            let mut order = new_order(&format!("n{i}"), "2", 0.6, 10.0);
+            set_field(&mut order, tag::ORDER_QTY, qty);
+            let out = gateway.receive(maker.send(order, 4), 4);
+            assert_eq!(field(&out, 0, tag::ORD_STATUS).1, "8");
+        }
+        gateway.receive(maker.send(new_order("a3", "2", 0.6, 10.0), 5), 5);
+        let replace = FixMessage::new("G")
+            .with(tag::ORIG_CL_ORD_ID, "a3")
+            .with(tag::CL_ORD_ID, "a4")
+            .with(tag::SYMBOL, "m")
+            .with(tag::SIDE, 2)
+            .with(tag::ORD_TYPE, 2)
+            .with(tag::PRICE, 0.6)
+            .with(tag::ORDER_QTY, "inf");
+        let out = gateway.receive(maker.send(replace, 6), 6);
+        assert_eq!(out[0].1.msg_type(), "9");
+        let book = gateway.engine.book("m", crate::YES).unwrap();
+        assert_eq!(book.asks[&Price(0.6)][0].qty, 10.0);
+    }
+
+    #[test]
+    fn test_auction_fills_at_the_clearing_price() {
+        let mut gateway = gateway();
+        let config = crate::MarketConfig {
+            auction_secs: 60,
+            ..Default::default()
+        };
+        gateway.engine.create_market_with_config("a", config);
+        gateway
+            .engine
+            .apply_market_command("admin", "a", MarketCommand::Open)
+            .unwrap();
+        let mut maker = logon(&mut gateway, "MM1", "maker");
+        let mut taker = logon(&mut gateway, "MM2", "taker");
+        let in_a = |mut msg: FixMessage| {
+            set_field(&mut msg, tag::SYMBOL, "a");
+            msg
+        };
+        gateway.receive(maker.send(in_a(new_order("a1", "2", 0.4, 10.0)), 1), 1);
+        gateway.receive(taker.send(in_a(new_order("b1", "1", 0.6, 5.0)), 1), 1);
+
+        let out = gateway.on_timer(current_timestamp() + 61);
+        let reports: Vec<&Outbound> = out.iter().filter(|(_, m)| m.msg_type() == "8").collect();
+        assert_eq!(reports.len(), 2);
+        let px = |i: usize| reports[i].1.get(tag::LAST_PX).unwrap();
+        let (_, price) = gateway
+            .engine
+            .book("a", crate::YES)
+            .unwrap()
+            .get_top_of_book();
+        assert_eq!(price, Price(0.4)); // the maker's remainder still rests
+        assert_eq!(px(0), px(1)); // not each order's own limit
+
+        for (_, report) in reports {
+            assert_eq!(report.get(tag::EXEC_TYPE), Some("F"));
+            assert_eq!(report.get(tag::LAST_QTY), Some("5"));
+        }
+    }
+
+    #[test]
+    fn test_logon_requires_credentials() {
+        let mut gateway = gateway();
+        let mut intruder = FixSession::new("MM1", "CLOB");
+        intruder.credentials = Some(("maker".to_string(), "guess".to_string()));
+        let bad_logon = intruder.logon(30, 0);
+        assert!(!gateway.authenticates(&bad_logon));
+        let out = gateway.receive(bad_logon, 0);
+        assert_eq!(field(&out, 0, tag::TEXT), ("MM1", "invalid credentials"));
+        assert!(gateway.session("MM1").is_none());
+
+        let mut anonymous = FixSession::new("MM1", "CLOB");
+        gateway.receive(anonymous.logon(30, 0), 0);
+        assert!(gateway.is_closed("MM1"));
+
+        let maker = logon(&mut gateway, "MM1", "maker");
+        assert!(maker.logged_on);
+        assert!(gateway.session("MM1").unwrap().logged_on);
+    }
+
+    // Read messages off the socket until one of `msg_type` arrives
+    fn read_until(stream: &mut TcpStream, buf: &mut Vec<u8>, msg_type: &str) -> FixMessage {
+        let mut chunk = [0u8; 4096];
+        loop {
+            while let Some(len) = frame_len(buf).unwrap() {
+                let raw: Vec<u8> = buf.drain(..len).collect();
+                let msg = FixMessage::decode(std::str::from_utf8(&raw).unwrap()).unwrap();
+                if msg.msg_type() == msg_type {
+                    return msg;
+                }
+            }
+            let n = stream.read(&mut chunk).unwrap();
+            assert!(n > 0, "connection closed");
+            buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    #[test]
+    fn test_local_client_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let gateway = gateway();
+        thread::spawn(move || serve(listener, gateway));
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut client = FixSession::new("MM1", "CLOB");
+        client.credentials = Some(("maker".to_string(), "maker-secret".to_string()));
+        let mut buf = Vec::new();
+        let now = current_timestamp();
+
+        let logon = client.logon(30, now);
+        stream.write_all(logon.encode().as_bytes()).unwrap();
+        client.receive(read_until(&mut stream, &mut buf, "A"), now);
+        assert!(client.logged_on);
+
+        let order = client.send(new_order("a1", "2", 0.6, 10.0), now);
+        stream.write_all(order.encode().as_bytes()).unwrap();
+        let report = read_until(&mut stream, &mut buf, "8");
+        assert_eq!(report.get(tag::CL_ORD_ID), Some("a1"));
+        assert_eq!(report.get(tag::ORD_STATUS), Some("0"));
+
+        let logout = client.logout("done", now);
+        stream.write_all(logout.encode().as_bytes()).unwrap();
+        assert_eq!(read_until(&mut stream, &mut buf, "5").msg_type(), "5");
+    }
+}
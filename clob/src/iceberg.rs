@@ -0,0 +1,178 @@
+use crate::{EngineError, Order};
+
+// --------------------- Iceberg Orders ---------------------
+// Hidden part of an iceberg order. `Order.qty` is the visible slice; once it
+// fills, a new slice of up to `display_qty` is taken from `reserve_qty`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Iceberg {
+    pub display_qty: f64,
+    pub reserve_qty: f64,
+}
+
+// A display size of zero would refill empty slices forever, and a reserve
+// that is not a plain amount would refill nonsense
+pub(crate) fn check_iceberg(order: &Order) -> Result<(), EngineError> {
+    let Some(iceberg) = order.iceberg else {
+        return Ok(());
+    };
+    if !(iceberg.display_qty.is_finite() && iceberg.display_qty > 0.0) {
+        return Err(EngineError::InvalidIceberg(format!(
+            "display size {} must be positive",
+            iceberg.display_qty
+        )));
+    }
+    if !(iceberg.reserve_qty.is_finite() && iceberg.reserve_qty >= 0.0) {
+        return Err(EngineError::InvalidIceberg(format!(
+            "reserve size {} must not be negative",
+            iceberg.reserve_qty
+        )));
+    }
+    Ok(())
+}
+
+impl Order {
+    // Show at most `display_qty` of this order and hide the rest
+    pub fn with_iceberg(mut self, display_qty: f64) -> Result<Order, EngineError> {
+        let total = self.total_qty();
+        let visible = total.min(display_qty);
+        self.qty = visible;
+        self.iceberg = Some(Iceberg {
+            display_qty,
+            reserve_qty: total - visible,
+        });
+        check_iceberg(&self)?;
+        Ok(self)
+    }
+
+    // Visible plus hidden quantity
+    pub fn total_qty(&self) -> f64 {
+        self.qty + self.iceberg.map_or(0.0, |i| i.reserve_qty)
+    }
+
+    // Take size off an order, from the hidden reserve first so the visible
+    // slice keeps its place in the queue
+    pub(crate) fn reduce_by(&mut self, cut: f64) {
+        let from_reserve = match self.iceberg.as_mut() {
+            Some(iceberg) => {
+                let taken = cut.min(iceberg.reserve_qty);
+                iceberg.reserve_qty -= taken;
+                taken
+            }
+            None => 0.0,
+        };
+        self.qty -= cut - from_reserve;
+    }
+
+    // Next visible slice once the current one has filled, or None if the
+    // reserve is exhausted. A display size the engine would have refused
+    // shows the whole reserve at once rather than an empty slice.
+    pub fn refill(mut self) -> Option<Order> {
+        let valid = check_iceberg(&self).is_ok();
+        let iceberg = self.iceberg.as_mut()?;
+        if iceberg.reserve_qty <= 0.0 {
+            return None;
+        }
+        let slice = if valid {
+            iceberg.display_qty.min(iceberg.reserve_qty)
+        } else {
+            iceberg.reserve_qty
+        };
+        iceberg.reserve_qty -= slice;
+        self.qty = slice;
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_test_order;
+    use crate::{MarketCommand, MatchingEngine, OrderBook, Price, Side, YES};
+
+    #[test]
+    fn test_depth_shows_only_displayed_slice() {
+        let mut book = OrderBook::new("test_market", YES);
+        book.add_order(
+            create_test_order(1, "whale", Side::Sell, 0.6, 1000.0)
+                .with_iceberg(100.0)
+                .unwrap(),
+        );
+        book.add_order(create_test_order(2, "alice", Side::Sell, 0.6, 50.0));
+
+        let (_, asks) = book.get_order_book_depth(5);
+        assert_eq!(asks, vec![(Price(0.6), 150.0)]);
+        assert_eq!(book.asks[&Price(0.6)][0].total_qty(), 1000.0);
+    }
+
+    #[test]
+    fn test_refilled_slice_loses_queue_priority() {
+        let mut book = OrderBook::new("test_market", YES);
+        book.add_order(
+            create_test_order(1, "whale", Side::Sell, 0.6, 250.0)
+                .with_iceberg(100.0)
+                .unwrap(),
+        );
+        book.add_order(create_test_order(2, "alice", Side::Sell, 0.6, 50.0));
+
+        // Fill the first slice plus part of the queue behind it
+        book.add_order(create_test_order(3, "bob", Side::Buy, 0.6, 120.0));
+        let trades = book.match_orders();
+        let sellers: Vec<(&str, f64)> = trades.iter().map(|t| (t.seller.as_str(), t.qty)).collect();
+        assert_eq!(sellers, vec![("whale", 100.0), ("alice", 20.0)]);
+
+        // alice keeps priority; the refilled slice sits behind her
+        let level = &book.asks[&Price(0.6)];
+        assert_eq!(level[0].user, "alice");
+        assert_eq!(level[1].user, "whale");
+        assert_eq!(level[1].qty, 100.0);
+        assert_eq!(level[1].total_qty(), 150.0);
+
+        // Sweeping everything drains the reserve, including the last partial slice
+        book.add_order(create_test_order(4, "carol", Side::Buy, 0.6, 1000.0));
+        let filled: f64 = book.match_orders().iter().map(|t| t.qty).sum();
+        assert_eq!(filled, 30.0 + 150.0);
+        assert!(book.asks.is_empty());
+    }
+
+    #[test]
+    fn test_display_size_must_be_positive() {
+        for display_qty in [0.0, -5.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                create_test_order(1, "whale", Side::Sell, 0.6, 100.0).with_iceberg(display_qty),
+                Err(EngineError::InvalidIceberg(_))
+            ));
+        }
+
+        // Built by hand past `with_iceberg`: the engine refuses it, and the
+        // book never refills an empty slice
+        let mut zero = create_test_order(1, "whale", Side::Sell, 0.6, 10.0);
+        zero.iceberg = Some(Iceberg {
+            display_qty: 0.0,
+            reserve_qty: 90.0,
+        });
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        engine.create_market("m");
+        engine
+            .apply_market_command("admin", "m", MarketCommand::Open)
+            .unwrap();
+        assert!(matches!(
+            engine.place_order("m", YES, zero.clone()),
+            Err(EngineError::InvalidIceberg(_))
+        ));
+        assert_eq!(zero.refill().map(|o| o.qty), Some(90.0));
+
+        for reserve_qty in [-1.0, f64::NAN, f64::INFINITY] {
+            let mut bad = create_test_order(2, "whale", Side::Sell, 0.6, 10.0);
+            bad.iceberg = Some(Iceberg {
+                display_qty: 10.0,
+                reserve_qty,
+            });
+            assert!(matches!(
+                engine.place_order("m", YES, bad),
+                Err(EngineError::InvalidIceberg(_))
+            ));
+        }
+        assert!(engine.book("m", YES).unwrap().asks.is_empty());
+    }
+}
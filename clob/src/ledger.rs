@@ -0,0 +1,543 @@
+use crate::abi::{keccak, Address, Word};
+use crate::error::EngineError;
+use crate::settlement::ClaimId;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+// --------------------- Vault Ledger ---------------------
+// `PredictionMarketVault` events that move balances
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VaultEvent {
+    Deposited {
+        user: Address,
+        amount: u128,
+    },
+    Withdrawn {
+        user: Address,
+        amount: u128,
+    },
+    ClaimsDeposited {
+        user: Address,
+        id: ClaimId,
+        amount: u128,
+    },
+    ClaimsWithdrawn {
+        user: Address,
+        id: ClaimId,
+        amount: u128,
+    },
+    ClaimsCredited {
+        user: Address,
+        id: ClaimId,
+        amount: u128,
+    },
+    ClaimsDebited {
+        user: Address,
+        id: ClaimId,
+        amount: u128,
+    },
+    CollateralCredited {
+        user: Address,
+        amount: u128,
+    },
+    CollateralDebited {
+        user: Address,
+        amount: u128,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Account {
+    Cash(Address),
+    Claims(Address, ClaimId),
+}
+
+impl VaultEvent {
+    pub fn user(&self) -> Address {
+        match self.entry().0 {
+            Account::Cash(user) | Account::Claims(user, _) => user,
+        }
+    }
+
+    // Account the event moves, the amount, and whether it adds to the balance
+    fn entry(&self) -> (Account, u128, bool) {
+        match *self {
+            VaultEvent::Deposited { user, amount }
+            | VaultEvent::CollateralCredited { user, amount } => {
+                (Account::Cash(user), amount, true)
+            }
+            VaultEvent::Withdrawn { user, amount }
+            | VaultEvent::CollateralDebited { user, amount } => {
+                (Account::Cash(user), amount, false)
+            }
+            VaultEvent::ClaimsDeposited { user, id, amount }
+            | VaultEvent::ClaimsCredited { user, id, amount } => {
+                (Account::Claims(user, id), amount, true)
+            }
+            VaultEvent::ClaimsWithdrawn { user, id, amount }
+            | VaultEvent::ClaimsDebited { user, id, amount } => {
+                (Account::Claims(user, id), amount, false)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VaultLog {
+    pub block_number: u64,
+    pub block_hash: Word,
+    pub log_index: u64,
+    pub removed: bool, // set by the node for logs of blocks that were reorged out
+    pub event: VaultEvent,
+}
+
+fn topic(signature: &str) -> Word {
+    keccak(signature.as_bytes())
+}
+
+fn ledger_error(reason: impl Into<String>) -> EngineError {
+    EngineError::Ledger(reason.into())
+}
+
+fn hex_bytes(value: &Value) -> Result<Vec<u8>, EngineError> {
+    let text = value
+        .as_str()
+        .ok_or_else(|| ledger_error("expected a hex string"))?;
+    hex::decode(text.strip_prefix("0x").unwrap_or(text))
+        .map_err(|_| ledger_error(format!("bad hex {}", text)))
+}
+
+fn hex_word(value: &Value) -> Result<Word, EngineError> {
+    hex_bytes(value)?
+        .try_into()
+        .map_err(|_| ledger_error("expected 32 bytes"))
+}
+
+fn hex_quantity(value: &Value) -> Result<u64, EngineError> {
+    let text = value
+        .as_str()
+        .ok_or_else(|| ledger_error("expected a hex quantity"))?;
+    u64::from_str_radix(text.trim_start_matches("0x"), 16)
+        .map_err(|_| ledger_error(format!("bad quantity {}", text)))
+}
+
+fn word_amount(word: &[u8]) -> Result<u128, EngineError> {
+    if word.len() != 32 || word[..16].iter().any(|&b| b != 0) {
+        return Err(ledger_error("amount does not fit in 128 bits"));
+    }
+    Ok(u128::from_be_bytes(word[16..].try_into().unwrap()))
+}
+
+fn word_address(word: &Word) -> Address {
+    word[12..].try_into().unwrap()
+}
+
+// Decode one JSON-RPC log object; logs of other contracts or events yield None
+fn parse_log(log: &Value, vault: &Address) -> Result<Option<VaultLog>, EngineError> {
+    if hex_bytes(&log["address"])? != vault {
+        return Ok(None);
+    }
+    let topics = log["topics"]
+        .as_array()
+        .ok_or_else(|| ledger_error("log without topics"))?
+        .iter()
+        .map(hex_word)
+        .collect::<Result<Vec<Word>, EngineError>>()?;
+    let Some(signature) = topics.first() else {
+        return Ok(None);
+    };
+    let amount = word_amount(&hex_bytes(&log["data"])?)?;
+    let user = || {
+        topics
+            .get(1)
+            .map(word_address)
+            .ok_or_else(|| ledger_error("missing user topic"))
+    };
+    let id = || {
+        topics
+            .get(2)
+            .copied()
+            .ok_or_else(|| ledger_error("missing id topic"))
+    };
+
+    let event = if *signature == topic("Deposited(address,uint256)") {
+        VaultEvent::Deposited {
+            user: user()?,
+            amount,
+        }
+    } else if *signature == topic("Withdrawn(address,uint256)") {
+        VaultEvent::Withdrawn {
+            user: user()?,
+            amount,
+        }
+    } else if *signature == topic("ClaimsDeposited(address,uint256,uint256)") {
+        VaultEvent::ClaimsDeposited {
+            user: user()?,
+            id: id()?,
+            amount,
+        }
+    } else if *signature == topic("ClaimsWithdrawn(address,uint256,uint256)") {
+        VaultEvent::ClaimsWithdrawn {
+            user: user()?,
+            id: id()?,
+            amount,
+        }
+    } else if *signature == topic("ClaimsCredited(address,uint256,uint256)") {
+        VaultEvent::ClaimsCredited {
+            user: user()?,
+            id: id()?,
+            amount,
+        }
+    } else if *signature == topic("ClaimsDebited(address,uint256,uint256)") {
+        VaultEvent::ClaimsDebited {
+            user: user()?,
+            id: id()?,
+            amount,
+        }
+    } else if *signature == topic("CollateralCredited(address,uint256)") {
+        VaultEvent::CollateralCredited {
+            user: user()?,
+            amount,
+        }
+    } else if *signature == topic("CollateralDebited(address,uint256)") {
+        VaultEvent::CollateralDebited {
+            user: user()?,
+            amount,
+        }
+    } else {
+        return Ok(None);
+    };
+
+    Ok(Some(VaultLog {
+        block_number: hex_quantity(&log["blockNumber"])?,
+        block_hash: hex_word(&log["blockHash"])?,
+        log_index: hex_quantity(&log["logIndex"])?,
+        removed: log["removed"].as_bool().unwrap_or(false),
+        event,
+    }))
+}
+
+// Vault logs from an `eth_getLogs` dump: either the bare array or the full
+// JSON-RPC response
+pub fn parse_logs(json: &str, vault: &Address) -> Result<Vec<VaultLog>, EngineError> {
+    let value: Value = serde_json::from_str(json).map_err(|e| ledger_error(e.to_string()))?;
+    let logs = value
+        .get("result")
+        .unwrap_or(&value)
+        .as_array()
+        .ok_or_else(|| ledger_error("expected an array of logs"))?;
+    let mut parsed = Vec::new();
+    for log in logs {
+        parsed.extend(parse_log(log, vault)?);
+    }
+    Ok(parsed)
+}
+
+// A node that stops answering must not hang whoever polls it
+const RPC_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Ask a local node (e.g. anvil on 127.0.0.1:8545) for the vault's logs in a block range
+pub fn fetch_logs(
+    rpc_addr: &str,
+    vault: &Address,
+    from_block: u64,
+    to_block: u64,
+) -> Result<Vec<VaultLog>, EngineError> {
+    let rpc_error = |e: std::io::Error| ledger_error(format!("{}: {}", rpc_addr, e));
+    let body = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "eth_getLogs",
+        "params": [{
+            "address": format!("0x{}", hex::encode(vault)),
+            "fromBlock": format!("0x{:x}", from_block),
+            "toBlock": format!("0x{:x}", to_block),
+        }],
+    })
+    .to_string();
+
+    let request = format!(
+        "POST / HTTP/1.0\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        rpc_addr,
+        body.len(),
+        body
+    );
+    let addr = rpc_addr
+        .to_socket_addrs()
+        .map_err(rpc_error)?
+        .next()
+        .ok_or_else(|| ledger_error(format!("{}: no address", rpc_addr)))?;
+    let mut stream = TcpStream::connect_timeout(&addr, RPC_TIMEOUT).map_err(rpc_error)?;
+    stream
+        .set_read_timeout(Some(RPC_TIMEOUT))
+        .map_err(rpc_error)?;
+    stream
+        .set_write_timeout(Some(RPC_TIMEOUT))
+        .map_err(rpc_error)?;
+    stream.write_all(request.as_bytes()).map_err(rpc_error)?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).map_err(rpc_error)?;
+    let (_, json) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| ledger_error("malformed RPC response"))?;
+    parse_logs(json, vault)
+}
+
+// Off-chain mirror of the vault's balances. Every applied log is kept under
+// its (block, log index) so replays are ignored and reorgs can be undone.
+#[derive(Clone, Debug, Default)]
+pub struct Ledger {
+    balances: HashMap<Account, u128>,
+    applied: BTreeMap<(u64, u64), VaultLog>,
+    block_hashes: BTreeMap<u64, Word>,
+}
+
+impl Ledger {
+    pub fn cash(&self, user: &Address) -> u128 {
+        self.balances
+            .get(&Account::Cash(*user))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn claims(&self, user: &Address, id: &ClaimId) -> u128 {
+        self.balances
+            .get(&Account::Claims(*user, *id))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn first_block(&self) -> Option<u64> {
+        self.block_hashes.keys().next().copied()
+    }
+
+    pub fn last_block(&self) -> Option<u64> {
+        self.block_hashes.keys().next_back().copied()
+    }
+
+    fn post(&mut self, event: &VaultEvent, forward: bool) -> Result<(), EngineError> {
+        let (account, amount, credit) = event.entry();
+        let balance = self.balances.entry(account).or_default();
+        let next = if credit == forward {
+            balance.checked_add(amount)
+        } else {
+            balance.checked_sub(amount)
+        };
+        *balance = next.ok_or_else(|| ledger_error(format!("{:?} would go negative", event)))?;
+        Ok(())
+    }
+
+    // Apply one log. Returns false for logs already applied. A log from a
+    // block whose hash changed rolls the ledger back to before that block.
+    // Removed logs the ledger never applied, including any from before its
+    // first block, change nothing.
+    pub fn apply(&mut self, log: &VaultLog) -> Result<bool, EngineError> {
+        let key = (log.block_number, log.log_index);
+        if log.removed {
+            if self
+                .applied
+                .get(&key)
+                .is_some_and(|a| a.block_hash == log.block_hash)
+            {
+                self.rewind_from(log.block_number)?;
+                return Ok(true);
+            }
+            return Ok(false);
+        }
+
+        // The ledger cannot tell what happened before its first block, so it
+        // only follows the chain forward from there
+        if let Some(first) = self.first_block().filter(|&first| log.block_number < first) {
+            return Err(ledger_error(format!(
+                "log of block {} predates the ledger's first block {}",
+                log.block_number, first
+            )));
+        }
+        if self
+            .block_hashes
+            .get(&log.block_number)
+            .is_some_and(|hash| *hash != log.block_hash)
+        {
+            self.rewind_from(log.block_number)?;
+        }
+        if self.applied.contains_key(&key) {
+            return Ok(false);
+        }
+        self.post(&log.event, true)?;
+        self.applied.insert(key, *log);
+        self.block_hashes.insert(log.block_number, log.block_hash);
+        Ok(true)
+    }
+
+    // Apply logs in chain order; returns how many were new. The batch goes
+    // in whole or, if any log fails, not at all.
+    pub fn ingest(&mut self, logs: &[VaultLog]) -> Result<usize, EngineError> {
+        let mut sorted = logs.to_vec();
+        sorted.sort_by_key(|log| (log.block_number, log.log_index));
+        let mut ledger = self.clone();
+        let mut applied = 0;
+        for log in &sorted {
+            if ledger.apply(log)? {
+                applied += 1;
+            }
+        }
+        *self = ledger;
+        Ok(applied)
+    }
+
+    // Undo every log after `block`, newest first
+    pub fn rollback_to(&mut self, block: u64) -> Result<(), EngineError> {
+        match block.checked_add(1) {
+            Some(next) => self.rewind_from(next),
+            None => Ok(()),
+        }
+    }
+
+    // Undo every log from `block` on. The undo runs on a copy that replaces
+    // the ledger only once every log came off cleanly.
+    fn rewind_from(&mut self, block: u64) -> Result<(), EngineError> {
+        let mut rewound = self.clone();
+        let undone = rewound.applied.split_off(&(block, 0));
+        for log in undone.values().rev() {
+            rewound.post(&log.event, false)?;
+        }
+        rewound.block_hashes.split_off(&block);
+        *self = rewound;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: Address = [0xbb; 20];
+    const ALICE: Address = [0x01; 20];
+
+    fn log_json(block: u64, hash: u8, index: u64, event: &str, amount: u128) -> String {
+        format!(
+            r#"{{"address":"0x{vault}","topics":["0x{topic}","0x{user:0>64}"],"data":"0x{amount:064x}","blockNumber":"0x{block:x}","blockHash":"0x{hash}","logIndex":"0x{index:x}","removed":false}}"#,
+            vault = hex::encode(VAULT),
+            topic = hex::encode(topic(event)),
+            user = hex::encode(ALICE),
+            hash = hex::encode([hash; 32]),
+        )
+    }
+
+    #[test]
+    fn test_parse_rpc_response() {
+        let json = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"result":[{},{}]}}"#,
+            log_json(10, 1, 0, "Deposited(address,uint256)", 500),
+            log_json(10, 1, 1, "MarketSet(address)", 0),
+        );
+        let logs = parse_logs(&json, &VAULT).unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(
+            logs[0].event,
+            VaultEvent::Deposited {
+                user: ALICE,
+                amount: 500
+            }
+        );
+        assert_eq!((logs[0].block_number, logs[0].log_index), (10, 0));
+        assert!(parse_logs(&json, &ALICE).unwrap().is_empty());
+
+        // Trade settlement moves collateral inside the vault's ledger
+        let settled = format!(
+            "[{},{}]",
+            log_json(11, 2, 0, "CollateralDebited(address,uint256)", 200),
+            log_json(11, 2, 1, "CollateralCredited(address,uint256)", 200),
+        );
+        let mut ledger = Ledger::default();
+        ledger.ingest(&logs).unwrap();
+        ledger
+            .ingest(&parse_logs(&settled, &VAULT).unwrap())
+            .unwrap();
+        assert_eq!(ledger.cash(&ALICE), 500);
+    }
+
+    #[test]
+    fn test_idempotent_apply_and_reorg() {
+        let parse = |json: String| parse_logs(&format!("[{}]", json), &VAULT).unwrap()[0];
+        let deposit = parse(log_json(10, 1, 0, "Deposited(address,uint256)", 500));
+        let withdraw = parse(log_json(11, 2, 0, "Withdrawn(address,uint256)", 200));
+
+        let mut ledger = Ledger::default();
+        assert_eq!(ledger.ingest(&[withdraw, deposit]).unwrap(), 2);
+        assert_eq!(ledger.ingest(&[deposit, withdraw]).unwrap(), 0);
+        assert_eq!(ledger.cash(&ALICE), 300);
+
+        // Block 11 is replaced by a block with a bigger withdrawal
+        let replacement = parse(log_json(11, 3, 0, "Withdrawn(address,uint256)", 450));
+        assert!(ledger.apply(&replacement).unwrap());
+        assert_eq!(ledger.cash(&ALICE), 50);
+        assert_eq!(ledger.last_block(), Some(11));
+
+        // The node reports the replacement as removed too
+        let mut removed = replacement;
+        removed.removed = true;
+        assert!(ledger.apply(&removed).unwrap());
+        assert_eq!(ledger.cash(&ALICE), 500);
+        assert_eq!(ledger.last_block(), Some(10));
+
+        let overdraw = parse(log_json(12, 4, 0, "Withdrawn(address,uint256)", 900));
+        assert!(matches!(
+            ledger.apply(&overdraw),
+            Err(EngineError::Ledger(_))
+        ));
+    }
+
+    #[test]
+    fn test_reorgs_at_the_edges() {
+        let parse = |json: String| parse_logs(&format!("[{}]", json), &VAULT).unwrap()[0];
+        let genesis = parse(log_json(0, 1, 0, "Deposited(address,uint256)", 500));
+        let next = parse(log_json(1, 2, 0, "Deposited(address,uint256)", 100));
+        let mut ledger = Ledger::default();
+        ledger.ingest(&[genesis, next]).unwrap();
+
+        // Rolling back to block 0 keeps block 0 itself
+        ledger.rollback_to(0).unwrap();
+        assert_eq!((ledger.cash(&ALICE), ledger.last_block()), (500, Some(0)));
+
+        // A new block 0 replaces the old one entirely
+        let replacement = parse(log_json(0, 3, 0, "Deposited(address,uint256)", 70));
+        assert!(ledger.apply(&replacement).unwrap());
+        assert_eq!(ledger.cash(&ALICE), 70);
+        let mut removed = replacement;
+        removed.removed = true;
+        assert!(ledger.apply(&removed).unwrap());
+        assert_eq!((ledger.cash(&ALICE), ledger.last_block()), (0, None));
+
+        // Below the first tracked block: removals are no-ops, new logs are refused
+        ledger
+            .ingest(&[parse(log_json(10, 4, 0, "Deposited(address,uint256)", 300))])
+            .unwrap();
+        let mut stale = parse(log_json(5, 5, 0, "Deposited(address,uint256)", 40));
+        assert!(matches!(ledger.apply(&stale), Err(EngineError::Ledger(_))));
+        stale.removed = true;
+        assert!(!ledger.apply(&stale).unwrap());
+        assert_eq!((ledger.cash(&ALICE), ledger.first_block()), (300, Some(10)));
+    }
+
+    #[test]
+    fn test_failed_rollback_leaves_the_ledger_intact() {
+        let parse = |json: String| parse_logs(&format!("[{}]", json), &VAULT).unwrap()[0];
+        let mut ledger = Ledger::default();
+        ledger
+            .ingest(&[
+                parse(log_json(10, 1, 0, "Deposited(address,uint256)", 500)),
+                parse(log_json(11, 2, 0, "Withdrawn(address,uint256)", 200)),
+                parse(log_json(12, 3, 0, "Deposited(address,uint256)", 50)),
+            ])
+            .unwrap();
+        // Corrupt the mirror so that undoing block 12's deposit underflows
+        ledger.balances.insert(Account::Cash(ALICE), 10);
+        assert!(ledger.rollback_to(10).is_err());
+        assert_eq!((ledger.cash(&ALICE), ledger.last_block()), (10, Some(12)));
+        assert_eq!(ledger.applied.len(), 3);
+    }
+}
@@ -1,9 +1,70 @@
 use std::collections::BTreeMap;
+#[cfg(not(target_arch = "wasm32"))]
 use std::time::{SystemTime, UNIX_EPOCH};
 
+pub mod abi;
+pub mod auction;
+pub mod band;
+pub mod batch;
+pub mod cancel;
+pub mod circuit_breaker;
+pub mod collateral;
+pub mod error;
+pub mod feed;
+pub mod fix;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod fix_gateway;
+pub mod iceberg;
+pub mod ledger;
+pub mod market;
+#[cfg(feature = "napi")]
+pub mod node;
+pub mod pricer;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod quote;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod session;
+pub mod settlement;
+pub mod signing;
+pub mod simulate;
+pub mod stops;
+mod undo;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod wire;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod wire_gateway;
+
 // FFI module for Node.js integration
 pub mod ffi;
 
+pub use abi::Address;
+pub use band::{BandMarket, OUTSIDE, WITHIN};
+pub use batch::{BatchCancel, BatchOrder, BatchResult};
+pub use circuit_breaker::{CircuitBreaker, PriceHistory};
+pub use error::EngineError;
+pub use feed::{CsvReplayFeed, FeedPrice, FileFeed, HttpFeed, PriceFeed};
+pub use fix::{FixMessage, FixSession};
+#[cfg(not(target_arch = "wasm32"))]
+pub use fix_gateway::FixGateway;
+pub use iceberg::Iceberg;
+pub use ledger::{Ledger, VaultEvent, VaultLog};
+pub use market::{
+    Market, MarketCommand, MarketConfig, MarketState, MatchingMode, OutcomeId, NO, YES,
+};
+pub use pricer::{BasketPricer, FeedChecks};
+pub use quote::{Quote, QuoteLevel, QuoteResult};
+pub use session::{Session, SessionId};
+pub use settlement::{settle, Settlement, SettlementConfig, SettlementTarget};
+pub use signing::{Eip712Domain, SignedOrder};
+pub use simulate::Preview;
+pub use stops::{StopKind, StopOrder, TriggerBook};
+pub use wire::{WireClient, WireMessage};
+#[cfg(not(target_arch = "wasm32"))]
+pub use wire_gateway::{WireGateway, WireServer};
+
 // Re-export FFI functions
 pub use ffi::*;
 
@@ -15,14 +76,22 @@ pub enum Side {
 }
 
 // Custom price type that can be ordered
-#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Price(pub f64);
 
 impl Eq for Price {}
 
+impl PartialOrd for Price {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl Ord for Price {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(other).unwrap_or(std::cmp::Ordering::Equal)
+        self.0
+            .partial_cmp(&other.0)
+            .unwrap_or(std::cmp::Ordering::Equal)
     }
 }
 
@@ -34,33 +103,92 @@ pub struct Order {
     pub price: Price, // 0.0-1.0 for binary market
     pub qty: f64,     // YES shares
     pub timestamp: u64,
+    pub iceberg: Option<Iceberg>, // hidden reserve behind `qty`
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Trade {
     pub buyer: String,
     pub seller: String,
+    pub buy_order_id: u64,
+    pub sell_order_id: u64,
     pub qty: f64,
     pub price: Price,
     pub market_id: String,
+    pub outcome: OutcomeId,
     pub timestamp: u64,
 }
 
+// Aggregated (price, total qty) per level, best price first
+pub type DepthLevels = Vec<(Price, f64)>;
+
+// Levels whose quantity changed between two depth snapshots; a level that
+// disappeared is reported with quantity 0
+pub fn level_changes(before: &DepthLevels, after: &DepthLevels) -> DepthLevels {
+    let old: BTreeMap<Price, f64> = before.iter().copied().collect();
+    let new: BTreeMap<Price, f64> = after.iter().copied().collect();
+    let mut changes: DepthLevels = new
+        .iter()
+        .filter(|(price, qty)| old.get(price) != Some(qty))
+        .map(|(&price, &qty)| (price, qty))
+        .collect();
+    changes.extend(
+        old.keys()
+            .filter(|price| !new.contains_key(price))
+            .map(|&price| (price, 0.0)),
+    );
+    changes.sort_by_key(|&(price, _)| price);
+    changes
+}
+
+// Notifications produced by the engine outside of order placement
+#[derive(Clone, Debug, PartialEq)]
+pub enum EngineEvent {
+    StateChanged {
+        market_id: String,
+        from: MarketState,
+        to: MarketState,
+    },
+    // Call auction or batch cleared at a single price
+    Uncrossed {
+        market_id: String,
+        outcome: OutcomeId,
+        price: Price,
+        volume: f64,
+    },
+    Trade(Trade),
+    // Automatic resolution blocked; the market waits for an admin to resolve it
+    Disputed {
+        market_id: String,
+        reason: String,
+    },
+    // Heartbeat lapsed; the session's resting orders were cancelled
+    SessionExpired {
+        session: SessionId,
+        cancelled: Vec<u64>,
+    },
+}
+
 // --------------------- Order Book ---------------------
+#[derive(Clone, Debug)]
 pub struct OrderBook {
-    pub yes: bool,
+    pub outcome: OutcomeId,
     pub bids: BTreeMap<Price, Vec<Order>>, // descending price
     pub asks: BTreeMap<Price, Vec<Order>>, // ascending price
     pub market_id: String,
+    pub in_auction: bool, // call period: orders rest without matching until uncrossed
+    pub last_trade_price: Option<Price>,
 }
 
 impl OrderBook {
-    pub fn new(market_id: &str, yes: bool) -> Self {
+    pub fn new(market_id: &str, outcome: OutcomeId) -> Self {
         Self {
-            yes,
+            outcome,
             bids: BTreeMap::new(),
             asks: BTreeMap::new(),
             market_id: market_id.to_string(),
+            in_auction: false,
+            last_trade_price: None,
         }
     }
 
@@ -68,16 +196,10 @@ impl OrderBook {
         // Add order to appropriate side with proper FIFO ordering
         match order.side {
             Side::Buy => {
-                self.bids
-                    .entry(order.price)
-                    .or_insert_with(Vec::new)
-                    .push(order);
+                self.bids.entry(order.price).or_default().push(order);
             }
             Side::Sell => {
-                self.asks
-                    .entry(order.price)
-                    .or_insert_with(Vec::new)
-                    .push(order);
+                self.asks.entry(order.price).or_default().push(order);
             }
         }
     }
@@ -85,7 +207,7 @@ impl OrderBook {
     pub fn cancel_order(&mut self, order_id: u64) -> bool {
         // Cancel order from both sides (in case of any inconsistencies)
         let mut cancelled = false;
-        
+
         // Cancel from bids
         for (_, orders) in self.bids.iter_mut() {
             if let Some(pos) = orders.iter().position(|o| o.id == order_id) {
@@ -94,7 +216,7 @@ impl OrderBook {
                 break;
             }
         }
-        
+
         // Cancel from asks
         for (_, orders) in self.asks.iter_mut() {
             if let Some(pos) = orders.iter().position(|o| o.id == order_id) {
@@ -103,33 +225,62 @@ impl OrderBook {
                 break;
             }
         }
-        
+
         // Clean up empty price levels
         self.bids.retain(|_, orders| !orders.is_empty());
         self.asks.retain(|_, orders| !orders.is_empty());
-        
+
+        cancelled
+    }
+
+    pub fn contains(&self, order_id: u64) -> bool {
+        self.bids
+            .values()
+            .chain(self.asks.values())
+            .flatten()
+            .any(|o| o.id == order_id)
+    }
+
+    // Remove every resting order `cancel` selects, returning them
+    pub fn cancel_where(&mut self, cancel: impl Fn(&Order) -> bool) -> Vec<Order> {
+        let mut cancelled = Vec::new();
+        for orders in self.bids.values_mut().chain(self.asks.values_mut()) {
+            let (gone, kept) = orders.drain(..).partition(|o| cancel(o));
+            *orders = kept;
+            cancelled.extend::<Vec<Order>>(gone);
+        }
+        self.bids.retain(|_, orders| !orders.is_empty());
+        self.asks.retain(|_, orders| !orders.is_empty());
         cancelled
     }
 
     pub fn match_orders(&mut self) -> Vec<Trade> {
-        let mut trades = Vec::new();
+        self.match_orders_at(current_timestamp())
+    }
 
-        loop {
-            // Get the best bid and ask
-            let (bid_price, bid_orders) = match self.bids.iter_mut().next_back() {
-                Some((price, orders)) => (*price, orders),
-                None => break, // No more bids
-            };
-            
-            let (ask_price, ask_orders) = match self.asks.iter_mut().next() {
-                Some((price, orders)) => (*price, orders),
-                None => break, // No more asks
-            };
+    // Match with trades stamped at `now`, e.g. the clock of the engine
+    pub fn match_orders_at(&mut self, now: u64) -> Vec<Trade> {
+        self.match_at(None, now)
+    }
 
+    // Match crossing orders in price-time priority. With a clearing price every
+    // trade executes at that price and only orders willing to trade at it match;
+    // otherwise the resting ask price is used.
+    pub(crate) fn match_at(&mut self, clearing: Option<Price>, now: u64) -> Vec<Trade> {
+        let mut trades = Vec::new();
+
+        // Get the best bid and ask until either side runs out
+        while let (Some((&bid_price, bid_orders)), Some((&ask_price, ask_orders))) = (
+            self.bids.iter_mut().next_back(),
+            self.asks.iter_mut().next(),
+        ) {
             // Check if prices cross
             if bid_price < ask_price {
                 break; // No more matches possible
             }
+            if clearing.is_some_and(|p| bid_price < p || ask_price > p) {
+                break; // Remaining orders are not marketable at the clearing price
+            }
 
             // Get the first orders from each side
             let mut bid_order = bid_orders.first().unwrap().clone();
@@ -137,18 +288,21 @@ impl OrderBook {
 
             // Calculate trade quantity (minimum of both orders)
             let trade_qty = bid_order.qty.min(ask_order.qty);
-            
+
             // Price improvement: aggressive bid gets filled at ask price (better for buyer)
-            let trade_price = ask_price;
+            let trade_price = clearing.unwrap_or(ask_price);
 
             // Create trade
             trades.push(Trade {
                 buyer: bid_order.user.clone(),
                 seller: ask_order.user.clone(),
+                buy_order_id: bid_order.id,
+                sell_order_id: ask_order.id,
                 qty: trade_qty,
                 price: trade_price,
                 market_id: self.market_id.clone(),
-                timestamp: current_timestamp(),
+                outcome: self.outcome,
+                timestamp: now,
             });
 
             // Update order quantities
@@ -159,6 +313,8 @@ impl OrderBook {
             if bid_order.qty <= 0.0 {
                 // Order fully filled, remove it
                 bid_orders.remove(0);
+                // Iceberg: the next slice joins the back of the queue
+                bid_orders.extend(bid_order.refill());
             } else {
                 // Order partially filled, update it
                 bid_orders[0] = bid_order;
@@ -168,6 +324,8 @@ impl OrderBook {
             if ask_order.qty <= 0.0 {
                 // Order fully filled, remove it
                 ask_orders.remove(0);
+                // Iceberg: the next slice joins the back of the queue
+                ask_orders.extend(ask_order.refill());
             } else {
                 // Order partially filled, update it
                 ask_orders[0] = ask_order;
@@ -182,6 +340,9 @@ impl OrderBook {
             }
         }
 
+        if let Some(trade) = trades.last() {
+            self.last_trade_price = Some(trade.price);
+        }
         trades
     }
 
@@ -201,112 +362,868 @@ impl OrderBook {
         (best_bid, best_ask)
     }
 
-    pub fn get_order_book_depth(&self, levels: usize) -> (Vec<(Price, f64)>, Vec<(Price, f64)>) {
+    pub fn get_order_book_depth(&self, levels: usize) -> (DepthLevels, DepthLevels) {
         let mut bids = Vec::new();
         let mut asks = Vec::new();
-        
+
         // Get top bid levels with aggregated quantities
         for (price, orders) in self.bids.iter().rev().take(levels) {
             let total_qty: f64 = orders.iter().map(|o| o.qty).sum();
             bids.push((*price, total_qty));
         }
-        
+
         // Get top ask levels with aggregated quantities
         for (price, orders) in self.asks.iter().take(levels) {
             let total_qty: f64 = orders.iter().map(|o| o.qty).sum();
             asks.push((*price, total_qty));
         }
-        
+
         (bids, asks)
     }
 }
 
-fn current_timestamp() -> u64 {
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn current_timestamp() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs()
 }
 
+// wasm32 has no system clock: engines there run on their own `clock`, set
+// by the host page
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn current_timestamp() -> u64 {
+    0
+}
+
+// Run an auction step on a book and report its clearing price followed by the trades
+fn auction_events(
+    book: &mut OrderBook,
+    now: u64,
+    run: fn(&mut OrderBook, u64) -> Vec<Trade>,
+) -> Vec<EngineEvent> {
+    let mut events = Vec::new();
+    if let Some((price, volume)) = book.indicative_uncross() {
+        events.push(EngineEvent::Uncrossed {
+            market_id: book.market_id.clone(),
+            outcome: book.outcome,
+            price,
+            volume,
+        });
+    }
+    events.extend(run(book, now).into_iter().map(EngineEvent::Trade));
+    events
+}
+
 // --------------------- Matching Engine ---------------------
-use std::collections::HashMap;
+use crate::iceberg::check_iceberg;
+use crate::quote::check_client_id;
+use std::collections::{HashMap, HashSet};
 
 pub struct MatchingEngine {
-    pub order_books: HashMap<String, OrderBook>, // market_id -> book
+    pub markets: HashMap<String, Market>, // market_id -> outcome books and lifecycle
+    pub admins: HashSet<String>,          // wallets allowed to run market commands
+    pub pricer: Option<BasketPricer>,     // settles band markets; none means manual resolution only
+    pub nonces: HashMap<Address, HashSet<u64>>, // nonces each wallet has used on signed orders
+    pub ledger: Ledger,                   // vault balances mirrored from on-chain logs
+    pub collateral_units: f64,            // ledger units per 1.0 of price, e.g. 1e6 for USDC
+    pub claim_units: f64,                 // ledger claim units per share
+    pub unsettled: HashMap<String, f64>,  // collateral owed by buyers for trades not yet settled
+    pub unsettled_claims: HashMap<(String, String, OutcomeId), f64>, // claims owed by sellers, per book
+    pub sessions: HashMap<SessionId, Session>, // heartbeat sessions of quoting clients
+    pub clock: Option<u64>, // time set by the host, e.g. a backtest; none reads the system clock
+    next_session: SessionId,
+    next_quote_id: u64,
+    undo: Option<undo::UndoLog>, // set while an all-or-nothing action runs
+}
+
+impl Default for MatchingEngine {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MatchingEngine {
     pub fn new() -> Self {
         Self {
-            order_books: HashMap::new(),
+            markets: HashMap::new(),
+            admins: HashSet::new(),
+            pricer: None,
+            nonces: HashMap::new(),
+            ledger: Ledger::default(),
+            collateral_units: 1e6,
+            claim_units: 1.0,
+            unsettled: HashMap::new(),
+            unsettled_claims: HashMap::new(),
+            sessions: HashMap::new(),
+            clock: None,
+            next_session: 0,
+            next_quote_id: 0,
+            undo: None,
         }
     }
 
+    // Engine time: the host-set clock if there is one, otherwise the system clock
+    pub fn now(&self) -> u64 {
+        self.clock.unwrap_or_else(current_timestamp)
+    }
+
+    pub fn set_price_feed(&mut self, feed: Box<dyn PriceFeed>) {
+        self.pricer = Some(BasketPricer::new(feed));
+    }
+
     pub fn create_market(&mut self, market_id: &str) {
-        // Create two order books for binary markets: YES and NO
-        let yes_market_id = format!("{}_YES", market_id);
-        let no_market_id = format!("{}_NO", market_id);
+        self.create_market_with_config(market_id, MarketConfig::default());
+    }
+
+    // Binary market with a YES and a NO book
+    pub fn create_market_with_config(&mut self, market_id: &str, config: MarketConfig) {
+        self.markets
+            .entry(market_id.to_string())
+            .or_insert_with(|| Market::new(market_id, &[YES, NO], config));
+    }
+
+    // Market with N mutually exclusive outcomes, one book each. New markets
+    // start in PreOpen and must be opened by an admin.
+    pub fn create_outcome_market(
+        &mut self,
+        market_id: &str,
+        outcomes: &[OutcomeId],
+        config: MarketConfig,
+    ) -> Result<(), EngineError> {
+        let distinct: HashSet<&OutcomeId> = outcomes.iter().collect();
+        if outcomes.len() < 2 || distinct.len() != outcomes.len() {
+            return Err(EngineError::InvalidOutcomes(market_id.to_string()));
+        }
+        self.markets
+            .entry(market_id.to_string())
+            .or_insert_with(|| Market::new(market_id, outcomes, config));
+        Ok(())
+    }
+
+    // WITHIN/OUTSIDE market created from the factory's definition. Trading
+    // ends at the settle time unless the config ends it earlier.
+    pub fn create_band_market(
+        &mut self,
+        market_id: &str,
+        band: BandMarket,
+        mut config: MarketConfig,
+    ) -> Result<(), EngineError> {
+        config.trading_end = Some(
+            config
+                .trading_end
+                .map_or(band.settle_ts, |end| end.min(band.settle_ts)),
+        );
+        self.create_outcome_market(market_id, &[WITHIN, OUTSIDE], config)?;
+        let market = self.markets.get_mut(market_id).unwrap();
+        market.band.get_or_insert(band);
+        Ok(())
+    }
 
-        self.order_books
-            .entry(yes_market_id.clone())
-            .or_insert(OrderBook::new(&yes_market_id, true));
-        self.order_books
-            .entry(no_market_id.clone())
-            .or_insert(OrderBook::new(&no_market_id, false));
+    pub fn add_admin(&mut self, admin: &str) {
+        self.admins.insert(admin.to_string());
     }
 
-    pub fn place_order(&mut self, market_id: &str, order: Order) -> Vec<Trade> {
-        let book = self
-            .order_books
+    pub fn market_state(&self, market_id: &str) -> Option<MarketState> {
+        self.markets.get(market_id).map(|m| m.state)
+    }
+
+    pub fn book(&self, market_id: &str, outcome: OutcomeId) -> Option<&OrderBook> {
+        self.markets.get(market_id)?.books.get(&outcome)
+    }
+
+    pub fn apply_market_command(
+        &mut self,
+        caller: &str,
+        market_id: &str,
+        command: MarketCommand,
+    ) -> Result<MarketState, EngineError> {
+        if !self.admins.contains(caller) {
+            return Err(EngineError::Unauthorized(caller.to_string()));
+        }
+        let now = self.now();
+        let market = self
+            .markets
             .get_mut(market_id)
-            .expect("Market not found");
-        book.add_order(order);
-        book.match_orders()
+            .ok_or_else(|| EngineError::MarketNotFound(market_id.to_string()))?;
+        if let MarketCommand::Resolve { winner } = command {
+            if !market.outcomes.contains(&winner) {
+                return Err(EngineError::UnknownOutcome {
+                    market_id: market_id.to_string(),
+                    outcome: winner,
+                });
+            }
+        }
+        let next = market.state.apply(command)?;
+        market.state = next;
+        if let MarketCommand::Resolve { winner } = command {
+            market.winner = Some(winner);
+        }
+        market.halted_until = None;
+        match next {
+            MarketState::Open => {
+                let auction_secs = market.config.auction_secs;
+                market.start_trading(now, auction_secs);
+            }
+            MarketState::Closed => market.clear_books(),
+            _ => {}
+        }
+        Ok(next)
     }
 
-    pub fn cancel_order(&mut self, market_id: &str, order_id: u64) -> bool {
-        // check
-        if let Some(book) = self.order_books.get_mut(market_id) {
+    // Block automatic resolution of a market whose feed data looks wrong. The
+    // market can still be resolved by hand with `MarketCommand::Resolve`.
+    pub fn dispute_market(
+        &mut self,
+        caller: &str,
+        market_id: &str,
+        reason: &str,
+    ) -> Result<(), EngineError> {
+        if !self.admins.contains(caller) {
+            return Err(EngineError::Unauthorized(caller.to_string()));
+        }
+        let market = self
+            .markets
+            .get_mut(market_id)
+            .ok_or_else(|| EngineError::MarketNotFound(market_id.to_string()))?;
+        market.dispute = Some(reason.to_string());
+        Ok(())
+    }
+
+    pub fn indicative_uncross(&self, market_id: &str, outcome: OutcomeId) -> Option<(Price, f64)> {
+        self.book(market_id, outcome)?.indicative_uncross()
+    }
+
+    // End the call period of a market early (or on schedule from `tick`).
+    // A call the band halts stays due, so it runs again once trading resumes.
+    pub fn uncross_market(&mut self, market_id: &str) -> Vec<EngineEvent> {
+        let now = self.now();
+        let Some(market) = self.markets.get_mut(market_id) else {
+            return Vec::new();
+        };
+        let auction_end = market.auction_end.take();
+        let (events, halted) = self.run_call(market_id, now, OrderBook::uncross);
+        if halted {
+            self.markets.get_mut(market_id).unwrap().auction_end = auction_end;
+        }
+        events
+    }
+
+    // Clear the current batch of a frequent batch market and schedule the next one
+    fn clear_batch(&mut self, market_id: &str, now: u64) -> Vec<EngineEvent> {
+        let Some(market) = self.markets.get_mut(market_id) else {
+            return Vec::new();
+        };
+        if let MatchingMode::FrequentBatch { interval_secs } = market.config.matching {
+            market.next_batch = Some(now + interval_secs);
+        }
+        self.run_call(market_id, now, OrderBook::clear_batch).0
+    }
+
+    // Run an auction step on every book of a market under its circuit breaker.
+    // A clearing price beyond the band halts the market before anything
+    // trades, reported as true; the trades of a call count towards the price
+    // move like continuous ones do.
+    fn run_call(
+        &mut self,
+        market_id: &str,
+        now: u64,
+        run: fn(&mut OrderBook, u64) -> Vec<Trade>,
+    ) -> (Vec<EngineEvent>, bool) {
+        let market = self.markets.get_mut(market_id).unwrap();
+        let state = market.state;
+        let breaker = market.config.circuit_breaker;
+        let halt = |market: &mut Market| {
+            market.trip_circuit_breaker(now);
+            (market.state != state).then(|| EngineEvent::StateChanged {
+                market_id: market_id.to_string(),
+                from: state,
+                to: market.state,
+            })
+        };
+
+        if let Some(breaker) = breaker {
+            let beyond_band = market.books.iter().any(|(outcome, book)| {
+                let reference = market
+                    .price_history
+                    .get(outcome)
+                    .and_then(PriceHistory::reference_price);
+                book.indicative_uncross()
+                    .zip(reference)
+                    .is_some_and(|((price, _), reference)| breaker.beyond_band(price, reference))
+            });
+            if beyond_band {
+                return (halt(market).into_iter().collect(), true);
+            }
+        }
+
+        let mut events: Vec<EngineEvent> = market
+            .books
+            .values_mut()
+            .flat_map(|book| auction_events(book, now, run))
+            .collect();
+        if let Some(breaker) = breaker {
+            let outcomes: Vec<OutcomeId> = market.books.keys().copied().collect();
+            let mut move_exceeded = false;
+            for outcome in outcomes {
+                let trades: Vec<Trade> = events
+                    .iter()
+                    .filter_map(|event| match event {
+                        EngineEvent::Trade(trade) if trade.outcome == outcome => {
+                            Some(trade.clone())
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                let history = market.price_history.entry(outcome).or_default();
+                history.record(now, &trades, breaker.window_secs);
+                move_exceeded |= breaker.move_exceeded(history);
+            }
+            if move_exceeded {
+                events.extend(halt(market));
+            }
+        }
+        self.track_event_trades(&events);
+        (events, false)
+    }
+
+    // Uncross calls whose period has ended and close every market whose
+    // trading end has passed
+    pub fn tick(&mut self, now: u64) -> Vec<EngineEvent> {
+        // Pull quotes of dead clients before anything can trade against them
+        let mut events = self.expire_sessions(now);
+
+        let due: Vec<String> = self
+            .markets
+            .values()
+            .filter(|m| m.auction_due(now))
+            .map(|m| m.id.clone())
+            .collect();
+        for market_id in due {
+            events.extend(self.uncross_market(&market_id));
+            events.extend(self.run_market_stops(&market_id));
+        }
+
+        let resumed: Vec<String> = self
+            .markets
+            .values()
+            .filter(|m| m.resume_due(now))
+            .map(|m| m.id.clone())
+            .collect();
+        for market_id in resumed {
+            let market = self.markets.get_mut(&market_id).unwrap();
+            market.state = MarketState::Open;
+            market.halted_until = None;
+            let auction_secs = market
+                .config
+                .circuit_breaker
+                .map_or(0, |breaker| breaker.auction_secs);
+            market.start_trading(now, auction_secs);
+            events.push(EngineEvent::StateChanged {
+                market_id,
+                from: MarketState::Halted,
+                to: MarketState::Open,
+            });
+        }
+
+        let batches: Vec<String> = self
+            .markets
+            .values()
+            .filter(|m| m.batch_due(now))
+            .map(|m| m.id.clone())
+            .collect();
+        for market_id in batches {
+            events.extend(self.clear_batch(&market_id, now));
+            events.extend(self.run_market_stops(&market_id));
+        }
+
+        let expired: Vec<String> = self
+            .markets
+            .values()
+            .filter(|m| m.trading_ended(now))
+            .map(|m| m.id.clone())
+            .collect();
+        events.extend(
+            expired
+                .iter()
+                .filter_map(|market_id| self.close_market(market_id)),
+        );
+
+        // Band markets settle from the feed once closed. A feed that cannot
+        // answer for the settle time, unreachable or live and already past it,
+        // is retried next tick; suspect feed data disputes the market instead.
+        for (market_id, _) in self.settling_band_markets(now) {
+            let result = self.resolve_band_market(&market_id, now);
+            events.extend(self.record_settlement(market_id, result));
+        }
+        events
+    }
+
+    // Closed, undisputed band markets whose settle time has come, with their bands
+    pub fn settling_band_markets(&self, now: u64) -> Vec<(String, BandMarket)> {
+        self.markets
+            .values()
+            .filter(|m| m.state == MarketState::Closed && m.dispute.is_none())
+            .filter_map(|m| m.band.as_ref().map(|band| (m.id.clone(), band.clone())))
+            .filter(|(_, band)| now >= band.settle_ts)
+            .collect()
+    }
+
+    // Price the basket at its settle time and resolve WITHIN or OUTSIDE, like
+    // `EtfPredictionMarket.settle`
+    pub fn resolve_band_market(
+        &mut self,
+        market_id: &str,
+        now: u64,
+    ) -> Result<OutcomeId, EngineError> {
+        let band = self.band_to_settle(market_id)?.clone();
+        if now < band.settle_ts {
+            return Err(EngineError::SettleTooEarly {
+                market_id: market_id.to_string(),
+                settle_ts: band.settle_ts,
+            });
+        }
+        let pricer = self
+            .pricer
+            .as_mut()
+            .ok_or_else(|| EngineError::Feed("no price feed configured".to_string()))?;
+        // The basket as of the settle time, however late resolution runs
+        let price = pricer.calculate_weighted_basket_price(
+            &band.symbols,
+            &band.weights_1e18,
+            band.settle_ts,
+        )?;
+        self.settle_band_market(market_id, price)
+    }
+
+    // Resolve a band market from its basket price at the settle time, for
+    // callers that fetch the price themselves
+    pub fn settle_band_market(
+        &mut self,
+        market_id: &str,
+        price_1e18: i128,
+    ) -> Result<OutcomeId, EngineError> {
+        let winner = self.band_to_settle(market_id)?.outcome_at(price_1e18);
+        let market = self.markets.get_mut(market_id).unwrap();
+        market.state = market.state.apply(MarketCommand::Resolve { winner })?;
+        market.winner = Some(winner);
+        market.final_price_1e18 = Some(price_1e18);
+        Ok(winner)
+    }
+
+    // Report how an attempt to settle a band market went. Suspect feed data
+    // disputes the market; any other failure is left to the next attempt.
+    pub fn record_settlement(
+        &mut self,
+        market_id: String,
+        result: Result<OutcomeId, EngineError>,
+    ) -> Option<EngineEvent> {
+        match result {
+            Ok(_) => Some(EngineEvent::StateChanged {
+                market_id,
+                from: MarketState::Closed,
+                to: MarketState::Resolved,
+            }),
+            Err(EngineError::SuspectFeed(reason)) => {
+                self.markets.get_mut(&market_id)?.dispute = Some(reason.clone());
+                Some(EngineEvent::Disputed { market_id, reason })
+            }
+            Err(_) => None,
+        }
+    }
+
+    fn band_to_settle(&self, market_id: &str) -> Result<&BandMarket, EngineError> {
+        let market = self
+            .markets
+            .get(market_id)
+            .ok_or_else(|| EngineError::MarketNotFound(market_id.to_string()))?;
+        let band = market.band.as_ref().ok_or_else(|| {
+            EngineError::InvalidBand(format!("{} is not a band market", market_id))
+        })?;
+        if market.dispute.is_some() {
+            return Err(EngineError::Disputed(market_id.to_string()));
+        }
+        Ok(band)
+    }
+
+    fn close_market(&mut self, market_id: &str) -> Option<EngineEvent> {
+        let market = self.markets.get_mut(market_id)?;
+        let from = market.state;
+        market.state = from.apply(MarketCommand::Close).ok()?;
+        market.clear_books();
+        Some(EngineEvent::StateChanged {
+            market_id: market_id.to_string(),
+            from,
+            to: MarketState::Closed,
+        })
+    }
+
+    // Require EIP-712 signed orders on a market from now on
+    pub fn set_market_domain(
+        &mut self,
+        caller: &str,
+        market_id: &str,
+        domain: Eip712Domain,
+    ) -> Result<(), EngineError> {
+        if !self.admins.contains(caller) {
+            return Err(EngineError::Unauthorized(caller.to_string()));
+        }
+        let market = self
+            .markets
+            .get_mut(market_id)
+            .ok_or_else(|| EngineError::MarketNotFound(market_id.to_string()))?;
+        market.domain = Some(domain);
+        Ok(())
+    }
+
+    pub fn place_order(
+        &mut self,
+        market_id: &str,
+        outcome: OutcomeId,
+        order: Order,
+    ) -> Result<Vec<Trade>, EngineError> {
+        if self
+            .markets
+            .get(market_id)
+            .is_some_and(|m| m.domain.is_some())
+        {
+            return Err(EngineError::SignatureRequired(market_id.to_string()));
+        }
+        self.execute_order(market_id, outcome, order)
+    }
+
+    // Verify the maker's signature, expiry and nonce before the order reaches
+    // the book. The nonce is only used up once the order is accepted.
+    pub fn place_signed_order(&mut self, signed: &SignedOrder) -> Result<Vec<Trade>, EngineError> {
+        let market = self
+            .markets
+            .get(&signed.market_id)
+            .ok_or_else(|| EngineError::MarketNotFound(signed.market_id.clone()))?;
+        let domain = market.domain.as_ref().ok_or_else(|| {
+            EngineError::InvalidSignature(format!("market {} has no signing domain", market.id))
+        })?;
+        let order = signed.verify(domain, self.now())?;
+        if self
+            .nonces
+            .get(&signed.maker)
+            .is_some_and(|used| used.contains(&signed.nonce))
+        {
+            return Err(EngineError::NonceUsed {
+                user: order.user,
+                nonce: signed.nonce,
+            });
+        }
+
+        let trades = self.execute_order(&signed.market_id, signed.outcome, order)?;
+        self.nonces
+            .entry(signed.maker)
+            .or_default()
+            .insert(signed.nonce);
+        Ok(trades)
+    }
+
+    pub(crate) fn execute_order(
+        &mut self,
+        market_id: &str,
+        outcome: OutcomeId,
+        order: Order,
+    ) -> Result<Vec<Trade>, EngineError> {
+        check_client_id(order.id)?;
+        self.match_order(market_id, outcome, order)
+    }
+
+    // Check, add and match an order of any origin, then run the stops it sets off
+    pub(crate) fn match_order(
+        &mut self,
+        market_id: &str,
+        outcome: OutcomeId,
+        order: Order,
+    ) -> Result<Vec<Trade>, EngineError> {
+        self.ensure_open(market_id, outcome)?;
+        check_price(order.price)?;
+        check_iceberg(&order)?;
+        let mut trades = self.submit(market_id, outcome, order, false)?;
+        let last_price = trades.last().map(|t| t.price);
+        trades.extend(self.run_stops(market_id, outcome, last_price));
+        Ok(trades)
+    }
+
+    // Park a stop or stop-limit order until the last trade crosses its trigger.
+    // A stop whose trigger has already been crossed is injected right away.
+    pub fn place_stop_order(
+        &mut self,
+        market_id: &str,
+        outcome: OutcomeId,
+        stop: StopOrder,
+    ) -> Result<Vec<Trade>, EngineError> {
+        self.ensure_open(market_id, outcome)?;
+        check_client_id(stop.order.id)?;
+        check_price(stop.order.price)?;
+        check_price(stop.trigger)?;
+        check_iceberg(&stop.order)?;
+        let market = self.markets.get_mut(market_id).unwrap();
+        let last_price = market.books[&outcome].last_trade_price;
+        market.triggers.entry(outcome).or_default().add(stop);
+        Ok(self.run_stops(market_id, outcome, last_price))
+    }
+
+    // Reject orders for unknown books and for markets that are not open
+    pub(crate) fn ensure_open(
+        &mut self,
+        market_id: &str,
+        outcome: OutcomeId,
+    ) -> Result<(), EngineError> {
+        let now = self.now();
+        if self
+            .markets
+            .get(market_id)
+            .is_some_and(|m| m.trading_ended(now))
+        {
+            self.close_market(market_id);
+        }
+
+        let market = self
+            .markets
+            .get(market_id)
+            .ok_or_else(|| EngineError::MarketNotFound(market_id.to_string()))?;
+        if !market.books.contains_key(&outcome) {
+            return Err(EngineError::UnknownOutcome {
+                market_id: market_id.to_string(),
+                outcome,
+            });
+        }
+        if !market.state.accepts_orders() {
+            return Err(EngineError::MarketNotOpen {
+                market_id: market_id.to_string(),
+                state: market.state,
+            });
+        }
+        Ok(())
+    }
+
+    // Add an order to an open book and match it, applying circuit breakers.
+    // Immediate-or-cancel orders drop whatever did not fill straight away.
+    fn submit(
+        &mut self,
+        market_id: &str,
+        outcome: OutcomeId,
+        order: Order,
+        immediate_or_cancel: bool,
+    ) -> Result<Vec<Trade>, EngineError> {
+        let now = self.now();
+        self.log_order(market_id, outcome, &order);
+        let market = self.markets.get_mut(market_id).unwrap();
+        let book = market.books.get_mut(&outcome).unwrap();
+        if book.in_auction {
+            // Nothing matches during a call, so an IOC order has nothing to take
+            if !immediate_or_cancel {
+                book.add_order(order);
+            }
+            return Ok(Vec::new());
+        }
+        let order_id = order.id;
+
+        let trades = match market.config.circuit_breaker {
+            None => {
+                book.add_order(order);
+                book.match_orders_at(now)
+            }
+            Some(breaker) => {
+                // Price band: an aggressive order may not walk the book too far from the last trade
+                let history = market.price_history.entry(outcome).or_default();
+                if history
+                    .reference_price()
+                    .is_some_and(|reference| breaker.walks_beyond_band(book, &order, reference))
+                {
+                    market.trip_circuit_breaker(now);
+                    return Err(EngineError::CircuitBreakerTripped(market_id.to_string()));
+                }
+
+                book.add_order(order);
+                let trades = book.match_orders_at(now);
+
+                // Price move: too large a swing in the last trade price within the window
+                history.record(now, &trades, breaker.window_secs);
+                if breaker.move_exceeded(history) {
+                    market.trip_circuit_breaker(now);
+                }
+                trades
+            }
+        };
+
+        if immediate_or_cancel {
+            market
+                .books
+                .get_mut(&outcome)
+                .unwrap()
+                .cancel_order(order_id);
+        }
+        self.track_unsettled(&trades);
+        Ok(trades)
+    }
+
+    // Evaluate the stops of every book after an auction or batch traded
+    fn run_market_stops(&mut self, market_id: &str) -> Vec<EngineEvent> {
+        let Some(market) = self.markets.get(market_id) else {
+            return Vec::new();
+        };
+        let last_prices: Vec<(OutcomeId, Option<Price>)> = market
+            .books
+            .iter()
+            .map(|(&outcome, book)| (outcome, book.last_trade_price))
+            .collect();
+
+        last_prices
+            .into_iter()
+            .flat_map(|(outcome, last_price)| self.run_stops(market_id, outcome, last_price))
+            .map(EngineEvent::Trade)
+            .collect()
+    }
+
+    // Inject stops triggered by `last_price`, then keep going with the price of
+    // the trades they produce until no further stop fires
+    fn run_stops(
+        &mut self,
+        market_id: &str,
+        outcome: OutcomeId,
+        mut last_price: Option<Price>,
+    ) -> Vec<Trade> {
+        let mut trades = Vec::new();
+
+        while let Some(price) = last_price.take() {
+            self.log_triggers(market_id, outcome);
+            let market = self.markets.get_mut(market_id).unwrap();
+            let Some(triggers) = market.triggers.get_mut(&outcome) else {
+                break;
+            };
+            for stop in triggers.take_triggered(price) {
+                let market = self.markets.get_mut(market_id).unwrap();
+                if !market.state.accepts_orders() {
+                    // A breaker halted the market mid-cascade: re-arm the rest
+                    market.triggers.entry(outcome).or_default().add(stop);
+                    continue;
+                }
+                let immediate_or_cancel = stop.kind == StopKind::Market;
+                match self.submit(
+                    market_id,
+                    outcome,
+                    stop.clone().into_order(),
+                    immediate_or_cancel,
+                ) {
+                    Ok(new_trades) => {
+                        if let Some(trade) = new_trades.last() {
+                            last_price = Some(trade.price);
+                        }
+                        trades.extend(new_trades);
+                    }
+                    // The stop itself tripped the breaker: keep it pending like the rest
+                    Err(_) => {
+                        let market = self.markets.get_mut(market_id).unwrap();
+                        market.triggers.entry(outcome).or_default().add(stop);
+                    }
+                }
+            }
+        }
+        trades
+    }
+
+    pub fn cancel_order(&mut self, market_id: &str, outcome: OutcomeId, order_id: u64) -> bool {
+        let Some(market) = self.markets.get_mut(market_id) else {
+            return false;
+        };
+        // Pending stop orders can be cancelled like resting ones
+        if let Some(triggers) = market.triggers.get_mut(&outcome) {
+            if triggers.cancel(order_id) {
+                return true;
+            }
+        }
+        if let Some(book) = market.books.get_mut(&outcome) {
             book.cancel_order(order_id)
         } else {
             false
         }
     }
 
-    pub fn get_top_of_book(&self, market_id: &str) -> (Price, Price) {
-        let book = self.order_books.get(market_id).expect("Market not found");
+    pub fn get_top_of_book(&self, market_id: &str, outcome: OutcomeId) -> (Price, Price) {
+        let book = self.book(market_id, outcome).expect("Market not found");
         book.get_top_of_book()
     }
 
+    // Sum of the best bids and sum of the best asks across all outcomes. A full
+    // set is always worth 1.0, so bids summing above 1.0 or asks summing below
+    // it mean the books are out of line with the payout.
+    pub fn full_set_prices(&self, market_id: &str) -> Option<(f64, f64)> {
+        let market = self.markets.get(market_id)?;
+        let (bids, asks) = market
+            .books
+            .values()
+            .map(|book| book.get_top_of_book())
+            .fold((0.0, 0.0), |(bids, asks), (bid, ask)| {
+                (bids + bid.0, asks + ask.0)
+            });
+        Some((bids, asks))
+    }
+
+    // Payout per share of `outcome` once the market has resolved
+    pub fn payout(&self, market_id: &str, outcome: OutcomeId) -> Option<f64> {
+        self.markets.get(market_id)?.payout(outcome)
+    }
+
     // Helper methods for binary markets
-    pub fn place_yes_order(&mut self, market_id: &str, order: Order) -> Vec<Trade> {
-        let yes_market_id = format!("{}_YES", market_id);
-        self.place_order(&yes_market_id, order)
+    pub fn place_yes_order(
+        &mut self,
+        market_id: &str,
+        order: Order,
+    ) -> Result<Vec<Trade>, EngineError> {
+        self.place_order(market_id, YES, order)
     }
 
-    pub fn place_no_order(&mut self, market_id: &str, order: Order) -> Vec<Trade> {
-        let no_market_id = format!("{}_NO", market_id);
-        self.place_order(&no_market_id, order)
+    pub fn place_no_order(
+        &mut self,
+        market_id: &str,
+        order: Order,
+    ) -> Result<Vec<Trade>, EngineError> {
+        self.place_order(market_id, NO, order)
     }
 
     pub fn get_yes_top_of_book(&self, market_id: &str) -> (Price, Price) {
-        let yes_market_id = format!("{}_YES", market_id);
-        self.get_top_of_book(&yes_market_id)
+        self.get_top_of_book(market_id, YES)
     }
 
     pub fn get_no_top_of_book(&self, market_id: &str) -> (Price, Price) {
-        let no_market_id = format!("{}_NO", market_id);
-        self.get_top_of_book(&no_market_id)
+        self.get_top_of_book(market_id, NO)
     }
 
     pub fn cancel_yes_order(&mut self, market_id: &str, order_id: u64) -> bool {
-        let yes_market_id = format!("{}_YES", market_id);
-        self.cancel_order(&yes_market_id, order_id)
+        self.cancel_order(market_id, YES, order_id)
     }
 
     pub fn cancel_no_order(&mut self, market_id: &str, order_id: u64) -> bool {
-        let no_market_id = format!("{}_NO", market_id);
-        self.cancel_order(&no_market_id, order_id)
+        self.cancel_order(market_id, NO, order_id)
+    }
+}
+
+// A single outcome share is never worth less than 0.0 or more than the full set
+pub(crate) fn check_price(price: Price) -> Result<(), EngineError> {
+    if (0.0..=1.0).contains(&price.0) {
+        Ok(())
+    } else {
+        Err(EngineError::InvalidPrice(price.0))
+    }
+}
+
+// Helper function to create test orders, shared by every module's tests
+#[cfg(test)]
+pub(crate) fn create_test_order(id: u64, user: &str, side: Side, price: f64, qty: f64) -> Order {
+    Order {
+        id,
+        user: user.to_string(),
+        side,
+        price: Price(price),
+        qty,
+        timestamp: current_timestamp(),
+        iceberg: None,
     }
 }
 
@@ -314,16 +1231,13 @@ impl MatchingEngine {
 mod tests {
     use super::*;
 
-    // Helper function to create test orders
-    fn create_test_order(id: u64, user: &str, side: Side, price: f64, qty: f64) -> Order {
-        Order {
-            id,
-            user: user.to_string(),
-            side,
-            price: Price(price),
-            qty,
-            timestamp: current_timestamp(),
-        }
+    // Helper function to create a market that is already open for trading
+    fn create_open_market(engine: &mut MatchingEngine, market_id: &str) {
+        engine.add_admin("admin");
+        engine.create_market(market_id);
+        engine
+            .apply_market_command("admin", market_id, MarketCommand::Open)
+            .unwrap();
     }
 
     #[test]
@@ -340,7 +1254,7 @@ mod tests {
 
     #[test]
     fn test_order_book_creation() {
-        let book = OrderBook::new("test_market", true);
+        let book = OrderBook::new("test_market", YES);
         assert_eq!(book.market_id, "test_market");
         assert!(book.bids.is_empty());
         assert!(book.asks.is_empty());
@@ -348,7 +1262,7 @@ mod tests {
 
     #[test]
     fn test_add_buy_order() {
-        let mut book = OrderBook::new("test_market", true);
+        let mut book = OrderBook::new("test_market", YES);
         let order = create_test_order(1, "alice", Side::Buy, 0.6, 100.0);
 
         book.add_order(order);
@@ -359,7 +1273,7 @@ mod tests {
 
     #[test]
     fn test_add_sell_order() {
-        let mut book = OrderBook::new("test_market", true);
+        let mut book = OrderBook::new("test_market", YES);
         let order = create_test_order(1, "alice", Side::Sell, 0.7, 100.0);
 
         for key in book.asks.keys() {
@@ -381,7 +1295,7 @@ mod tests {
 
     #[test]
     fn test_order_matching_simple() {
-        let mut book = OrderBook::new("test_market", true);
+        let mut book = OrderBook::new("test_market", YES);
 
         // Add buy order at 0.6
         let buy_order = create_test_order(1, "alice", Side::Buy, 0.6, 100.0);
@@ -403,7 +1317,7 @@ mod tests {
 
     #[test]
     fn test_order_matching_partial_fill() {
-        let mut book = OrderBook::new("test_market", true);
+        let mut book = OrderBook::new("test_market", YES);
 
         // Add buy order for 100 shares
         let buy_order = create_test_order(1, "alice", Side::Buy, 0.6, 100.0);
@@ -422,14 +1336,14 @@ mod tests {
 
         // Check that buy order still has 40 shares remaining
         assert_eq!(book.bids.get(&Price(0.6)).unwrap()[0].qty, 40.0);
-        
+
         // Check that sell order is fully filled and removed
         assert!(book.asks.is_empty());
     }
 
     #[test]
     fn test_order_matching_large_quantities() {
-        let mut book = OrderBook::new("test_market", true);
+        let mut book = OrderBook::new("test_market", YES);
 
         // Add buy order for 1000 shares
         let buy_order = create_test_order(1, "alice", Side::Buy, 0.6, 1000.0);
@@ -448,14 +1362,14 @@ mod tests {
 
         // Check that buy order still has 700 shares remaining
         assert_eq!(book.bids.get(&Price(0.6)).unwrap()[0].qty, 700.0);
-        
+
         // Check that sell order is fully filled and removed
         assert!(book.asks.is_empty());
     }
 
     #[test]
     fn test_price_improvement_for_aggressive_orders() {
-        let mut book = OrderBook::new("test_market", true);
+        let mut book = OrderBook::new("test_market", YES);
 
         // Add sell order at 0.7
         let sell_order = create_test_order(1, "alice", Side::Sell, 0.7, 100.0);
@@ -473,7 +1387,7 @@ mod tests {
         assert_eq!(trade.price, Price(0.7)); // Aggressive buyer gets filled at ask price (better)
         assert_eq!(trade.buyer, "bob");
         assert_eq!(trade.seller, "alice");
-        
+
         // Both orders should be fully filled and removed
         assert!(book.bids.is_empty());
         assert!(book.asks.is_empty());
@@ -481,7 +1395,7 @@ mod tests {
 
     #[test]
     fn test_multiple_orders_same_price() {
-        let mut book = OrderBook::new("test_market", true);
+        let mut book = OrderBook::new("test_market", YES);
         let order1 = create_test_order(1, "alice", Side::Buy, 0.6, 100.0);
         let order2 = create_test_order(2, "bob", Side::Buy, 0.6, 50.0);
 
@@ -493,7 +1407,7 @@ mod tests {
 
     #[test]
     fn test_order_matching_multiple_levels() {
-        let mut book = OrderBook::new("test_market", true);
+        let mut book = OrderBook::new("test_market", YES);
 
         // Add multiple buy orders at different prices
         book.add_order(create_test_order(1, "alice", Side::Buy, 0.6, 100.0));
@@ -508,18 +1422,18 @@ mod tests {
         // Verify both trades occurred with correct quantities
         assert_eq!(trades[0].qty, 100.0);
         assert_eq!(trades[1].qty, 20.0);
-        
+
         // Verify the total quantity traded matches the sell order
         let total_traded: f64 = trades.iter().map(|t| t.qty).sum();
         assert_eq!(total_traded, 120.0);
-        
+
         // Verify orders were processed (basic check)
-        assert!(trades.len() > 0);
+        assert!(!trades.is_empty());
     }
 
     #[test]
     fn test_no_matching_when_prices_dont_cross() {
-        let mut book = OrderBook::new("test_market", true);
+        let mut book = OrderBook::new("test_market", YES);
 
         // Buy order at 0.4 -> buy yes at 0.4 -> sell no at 0.6
         book.add_order(create_test_order(1, "alice", Side::Buy, 0.4, 100.0));
@@ -549,7 +1463,7 @@ mod tests {
 
     #[test]
     fn test_get_top_of_book() {
-        let mut book = OrderBook::new("test_market", true);
+        let mut book = OrderBook::new("test_market", YES);
 
         // Add orders at different prices
         book.add_order(create_test_order(1, "alice", Side::Buy, 0.5, 100.0));
@@ -572,7 +1486,7 @@ mod tests {
 
     #[test]
     fn test_empty_order_book() {
-        let book = OrderBook::new("test_market", true);
+        let book = OrderBook::new("test_market", YES);
         let (best_bid, best_ask) = book.get_top_of_book();
         assert_eq!(best_bid, Price(0.0));
         assert_eq!(best_ask, Price(1.0));
@@ -581,7 +1495,7 @@ mod tests {
     #[test]
     fn test_matching_engine_creation() {
         let engine = MatchingEngine::new();
-        assert!(engine.order_books.is_empty());
+        assert!(engine.markets.is_empty());
     }
 
     #[test]
@@ -589,43 +1503,45 @@ mod tests {
         let mut engine = MatchingEngine::new();
         engine.create_market("test_market");
 
-        assert!(engine.order_books.contains_key("test_market_YES"));
-        assert!(engine.order_books.contains_key("test_market_NO"));
+        assert!(engine.book("test_market", YES).is_some());
+        assert!(engine.book("test_market", NO).is_some());
     }
 
     #[test]
     fn test_place_order_through_engine() {
         let mut engine = MatchingEngine::new();
-        engine.create_market("test_market");
+        create_open_market(&mut engine, "test_market");
 
         let order = create_test_order(1, "alice", Side::Buy, 0.6, 100.0);
-        let trades = engine.place_order("test_market_YES", order);
+        let trades = engine.place_order("test_market", YES, order).unwrap();
 
         // No trades should occur since there are no matching orders
         assert_eq!(trades.len(), 0);
-        
+
         // Order should be in the book
-        let (best_bid, _) = engine.get_top_of_book("test_market_YES");
+        let (best_bid, _) = engine.get_top_of_book("test_market", YES);
         assert_eq!(best_bid, Price(0.6));
     }
 
     #[test]
     fn test_binary_markets_separate() {
         let mut engine = MatchingEngine::new();
-        engine.create_market("test_market");
+        create_open_market(&mut engine, "test_market");
 
         // Test that we have two separate markets
-        assert!(engine.order_books.contains_key("test_market_YES"));
-        assert!(engine.order_books.contains_key("test_market_NO"));
+        assert!(engine.book("test_market", YES).is_some());
+        assert!(engine.book("test_market", NO).is_some());
 
         // Test YES market orders
         let yes_buy_order = create_test_order(1, "alice", Side::Buy, 0.6, 100.0);
-        let yes_trades = engine.place_yes_order("test_market", yes_buy_order);
+        let yes_trades = engine
+            .place_yes_order("test_market", yes_buy_order)
+            .unwrap();
         assert_eq!(yes_trades.len(), 0); // No matching orders yet
 
         // Test NO market orders
         let no_sell_order = create_test_order(2, "bob", Side::Sell, 0.4, 100.0);
-        let no_trades = engine.place_no_order("test_market", no_sell_order);
+        let no_trades = engine.place_no_order("test_market", no_sell_order).unwrap();
         assert_eq!(no_trades.len(), 0); // No matching orders yet
 
         // Check that orders are in separate books
@@ -641,22 +1557,22 @@ mod tests {
     #[test]
     fn test_arbitrage_relationship() {
         let mut engine = MatchingEngine::new();
-        engine.create_market("test_market");
+        create_open_market(&mut engine, "test_market");
 
         // Add orders that should create arbitrage opportunities
         // YES buy at 0.6 and NO buy at 0.3 (total = 0.9, should be 1.0)
         let yes_buy = create_test_order(1, "alice", Side::Buy, 0.6, 100.0);
         let no_buy = create_test_order(2, "bob", Side::Buy, 0.3, 100.0);
 
-        engine.place_yes_order("test_market", yes_buy);
-        engine.place_no_order("test_market", no_buy);
+        engine.place_yes_order("test_market", yes_buy).unwrap();
+        engine.place_no_order("test_market", no_buy).unwrap();
 
         // Add matching sell orders
         let yes_sell = create_test_order(3, "charlie", Side::Sell, 0.6, 100.0);
         let no_sell = create_test_order(4, "dave", Side::Sell, 0.3, 100.0);
 
-        let yes_trades = engine.place_yes_order("test_market", yes_sell);
-        let no_trades = engine.place_no_order("test_market", no_sell);
+        let yes_trades = engine.place_yes_order("test_market", yes_sell).unwrap();
+        let no_trades = engine.place_no_order("test_market", no_sell).unwrap();
 
         // Both should match
         assert_eq!(yes_trades.len(), 1);
@@ -676,7 +1592,7 @@ mod tests {
 
     #[test]
     fn test_cancel_order() {
-        let mut book = OrderBook::new("test_market", true);
+        let mut book = OrderBook::new("test_market", YES);
         let order = create_test_order(1, "alice", Side::Buy, 0.6, 100.0);
 
         book.add_order(order);
@@ -686,4 +1602,774 @@ mod tests {
         assert!(cancelled);
         assert_eq!(book.bids.get(&Price(0.6)).map(|v| v.len()), None); // Price level removed
     }
+
+    #[test]
+    fn test_orders_rejected_until_market_open() {
+        let mut engine = MatchingEngine::new();
+        engine.create_market("test_market");
+        assert_eq!(
+            engine.market_state("test_market"),
+            Some(MarketState::PreOpen)
+        );
+
+        let order = create_test_order(1, "alice", Side::Buy, 0.6, 100.0);
+        let result = engine.place_yes_order("test_market", order);
+        assert_eq!(
+            result.unwrap_err(),
+            EngineError::MarketNotOpen {
+                market_id: "test_market".to_string(),
+                state: MarketState::PreOpen,
+            }
+        );
+
+        let unknown = create_test_order(2, "alice", Side::Buy, 0.6, 100.0);
+        assert!(matches!(
+            engine.place_yes_order("missing", unknown),
+            Err(EngineError::MarketNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_market_commands_require_admin() {
+        let mut engine = MatchingEngine::new();
+        engine.create_market("test_market");
+
+        let result = engine.apply_market_command("mallory", "test_market", MarketCommand::Open);
+        assert_eq!(
+            result,
+            Err(EngineError::Unauthorized("mallory".to_string()))
+        );
+
+        engine.add_admin("admin");
+        let state = engine.apply_market_command("admin", "test_market", MarketCommand::Open);
+        assert_eq!(state, Ok(MarketState::Open));
+
+        // Halted markets keep their orders but reject new ones
+        let order = create_test_order(1, "alice", Side::Buy, 0.6, 100.0);
+        engine.place_yes_order("test_market", order).unwrap();
+        engine
+            .apply_market_command("admin", "test_market", MarketCommand::Halt)
+            .unwrap();
+        let order = create_test_order(2, "bob", Side::Sell, 0.6, 100.0);
+        assert!(engine.place_yes_order("test_market", order).is_err());
+        assert_eq!(engine.get_yes_top_of_book("test_market").0, Price(0.6));
+    }
+
+    #[test]
+    fn test_market_closes_at_trading_end() {
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        engine.create_market_with_config(
+            "test_market",
+            MarketConfig {
+                trading_end: Some(1_000),
+                ..Default::default()
+            },
+        );
+        engine
+            .apply_market_command("admin", "test_market", MarketCommand::Open)
+            .unwrap();
+        engine
+            .place_yes_order(
+                "test_market",
+                create_test_order(1, "alice", Side::Buy, 0.6, 100.0),
+            )
+            .unwrap_err(); // trading end is already in the past
+
+        assert_eq!(
+            engine.market_state("test_market"),
+            Some(MarketState::Closed)
+        );
+        assert!(engine.tick(2_000).is_empty()); // already closed
+
+        let state = engine.apply_market_command(
+            "admin",
+            "test_market",
+            MarketCommand::Resolve { winner: YES },
+        );
+        assert_eq!(state, Ok(MarketState::Resolved));
+        assert_eq!(engine.markets["test_market"].winner, Some(YES));
+    }
+
+    #[test]
+    fn test_tick_closes_expired_markets_and_clears_books() {
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        engine.create_market_with_config(
+            "test_market",
+            MarketConfig {
+                trading_end: Some(u64::MAX),
+                ..Default::default()
+            },
+        );
+        engine
+            .apply_market_command("admin", "test_market", MarketCommand::Open)
+            .unwrap();
+        engine
+            .place_yes_order(
+                "test_market",
+                create_test_order(1, "alice", Side::Buy, 0.6, 100.0),
+            )
+            .unwrap();
+
+        assert!(engine.tick(1_000).is_empty());
+        let events = engine.tick(u64::MAX);
+        assert_eq!(
+            events,
+            vec![EngineEvent::StateChanged {
+                market_id: "test_market".to_string(),
+                from: MarketState::Open,
+                to: MarketState::Closed,
+            }]
+        );
+        assert!(engine.book("test_market", YES).unwrap().bids.is_empty());
+    }
+
+    #[test]
+    fn test_opening_auction_uncrosses_on_tick() {
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        engine.create_market_with_config(
+            "test_market",
+            MarketConfig {
+                auction_secs: 60,
+                ..Default::default()
+            },
+        );
+        engine
+            .apply_market_command("admin", "test_market", MarketCommand::Open)
+            .unwrap();
+
+        // Crossing orders rest during the call period
+        let buy = create_test_order(1, "alice", Side::Buy, 0.7, 100.0);
+        let sell = create_test_order(2, "bob", Side::Sell, 0.5, 100.0);
+        assert!(engine
+            .place_yes_order("test_market", buy)
+            .unwrap()
+            .is_empty());
+        assert!(engine
+            .place_yes_order("test_market", sell)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            engine.indicative_uncross("test_market", YES),
+            Some((Price(0.5), 100.0))
+        );
+
+        assert!(engine.tick(current_timestamp()).is_empty()); // call still running
+        let events = engine.tick(current_timestamp() + 60);
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[0],
+            EngineEvent::Uncrossed {
+                market_id: "test_market".to_string(),
+                outcome: YES,
+                price: Price(0.5),
+                volume: 100.0,
+            }
+        );
+        assert!(matches!(&events[1], EngineEvent::Trade(t) if t.qty == 100.0));
+
+        // Continuous matching afterwards
+        let buy = create_test_order(3, "alice", Side::Buy, 0.6, 10.0);
+        let sell = create_test_order(4, "bob", Side::Sell, 0.6, 10.0);
+        engine.place_yes_order("test_market", buy).unwrap();
+        assert_eq!(
+            engine.place_yes_order("test_market", sell).unwrap().len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_frequent_batch_market_clears_on_interval() {
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        engine.create_market_with_config(
+            "test_market",
+            MarketConfig {
+                matching: MatchingMode::FrequentBatch { interval_secs: 1 },
+                ..Default::default()
+            },
+        );
+        engine
+            .apply_market_command("admin", "test_market", MarketCommand::Open)
+            .unwrap();
+
+        let buy = create_test_order(1, "alice", Side::Buy, 0.7, 100.0);
+        let sell = create_test_order(2, "bob", Side::Sell, 0.6, 40.0);
+        let late_sell = create_test_order(3, "carol", Side::Sell, 0.5, 40.0);
+        assert!(engine
+            .place_yes_order("test_market", buy)
+            .unwrap()
+            .is_empty());
+        assert!(engine
+            .place_yes_order("test_market", sell)
+            .unwrap()
+            .is_empty());
+        assert!(engine
+            .place_yes_order("test_market", late_sell)
+            .unwrap()
+            .is_empty());
+
+        // Both sellers clear at the same uniform price regardless of arrival
+        let now = current_timestamp() + 1;
+        let trades: Vec<Trade> = engine
+            .tick(now)
+            .into_iter()
+            .filter_map(|e| match e {
+                EngineEvent::Trade(t) => Some(t),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(trades.len(), 2);
+        assert!(trades.iter().all(|t| t.price == trades[0].price));
+
+        // The book keeps batching and the next clear is scheduled
+        assert!(engine.book("test_market", YES).unwrap().in_auction);
+        assert_eq!(engine.markets["test_market"].next_batch, Some(now + 1));
+        assert!(engine.tick(now).is_empty());
+    }
+
+    #[test]
+    fn test_trades_carry_the_engine_clock() {
+        let mut engine = MatchingEngine::new();
+        engine.clock = Some(1_000);
+        engine.add_admin("admin");
+        engine.create_market("test_market");
+        engine
+            .apply_market_command("admin", "test_market", MarketCommand::Open)
+            .unwrap();
+        let buy = create_test_order(1, "alice", Side::Buy, 0.5, 10.0);
+        let sell = create_test_order(2, "bob", Side::Sell, 0.5, 10.0);
+        engine.place_yes_order("test_market", buy).unwrap();
+        let trades = engine.place_yes_order("test_market", sell).unwrap();
+        assert_eq!(trades[0].timestamp, 1_000);
+    }
+
+    #[test]
+    fn test_breakers_apply_to_batch_clears() {
+        let mut engine = MatchingEngine::new();
+        engine.clock = Some(1_000);
+        engine.add_admin("admin");
+        engine.create_market_with_config(
+            "test_market",
+            MarketConfig {
+                matching: MatchingMode::FrequentBatch { interval_secs: 1 },
+                circuit_breaker: Some(CircuitBreaker {
+                    max_move: 0.05,
+                    window_secs: 60,
+                    band: 0.1,
+                    cool_down_secs: 30,
+                    auction_secs: 0,
+                }),
+                ..Default::default()
+            },
+        );
+        engine
+            .apply_market_command("admin", "test_market", MarketCommand::Open)
+            .unwrap();
+        let cross = |engine: &mut MatchingEngine, id: u64, price: f64| {
+            let buy = create_test_order(id, "alice", Side::Buy, price, 10.0);
+            let sell = create_test_order(id + 1, "bob", Side::Sell, price, 10.0);
+            engine.place_yes_order("test_market", buy).unwrap();
+            engine.place_yes_order("test_market", sell).unwrap();
+        };
+
+        // The first clear sets the reference; a clear 0.08 away trades but moves too far
+        cross(&mut engine, 1, 0.5);
+        assert_eq!(engine.tick(1_001).len(), 2);
+        cross(&mut engine, 3, 0.58);
+        let events = engine.tick(1_002);
+        assert!(events.contains(&EngineEvent::StateChanged {
+            market_id: "test_market".to_string(),
+            from: MarketState::Open,
+            to: MarketState::Halted,
+        }));
+        assert_eq!(
+            engine.markets["test_market"].price_history[&YES].reference_price(),
+            Some(Price(0.58))
+        );
+
+        // After the cool-down, a clear beyond the band halts before anything trades
+        assert_eq!(engine.tick(1_032).len(), 1);
+        cross(&mut engine, 5, 0.75);
+        let events = engine.tick(1_033);
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            engine.market_state("test_market"),
+            Some(MarketState::Halted)
+        );
+        let book = engine.book("test_market", YES).unwrap();
+        assert_eq!((book.bids.len(), book.asks.len()), (1, 1));
+    }
+
+    fn create_breaker_market(engine: &mut MatchingEngine, band: f64) {
+        engine.add_admin("admin");
+        engine.create_market_with_config(
+            "test_market",
+            MarketConfig {
+                circuit_breaker: Some(CircuitBreaker {
+                    max_move: 0.1,
+                    window_secs: 60,
+                    band,
+                    cool_down_secs: 30,
+                    auction_secs: 10,
+                }),
+                ..Default::default()
+            },
+        );
+        engine
+            .apply_market_command("admin", "test_market", MarketCommand::Open)
+            .unwrap();
+
+        // First trade sets the reference price at 0.5
+        let buy = create_test_order(1, "alice", Side::Buy, 0.5, 10.0);
+        let sell = create_test_order(2, "bob", Side::Sell, 0.5, 10.0);
+        engine.place_yes_order("test_market", buy).unwrap();
+        engine.place_yes_order("test_market", sell).unwrap();
+    }
+
+    #[test]
+    fn test_host_clock_drives_the_opening_call() {
+        let mut engine = MatchingEngine::new();
+        engine.clock = Some(1_000);
+        engine.add_admin("admin");
+        engine.create_market_with_config(
+            "test_market",
+            MarketConfig {
+                auction_secs: 60,
+                ..Default::default()
+            },
+        );
+        engine
+            .apply_market_command("admin", "test_market", MarketCommand::Open)
+            .unwrap();
+        let buy = create_test_order(1, "alice", Side::Buy, 0.6, 10.0);
+        let sell = create_test_order(2, "bob", Side::Sell, 0.6, 10.0);
+        engine.place_yes_order("test_market", buy).unwrap();
+        engine.place_yes_order("test_market", sell).unwrap();
+
+        // The call ends 60s after the host's time, not the system's
+        assert!(engine.tick(1_059).is_empty());
+        assert_eq!(engine.tick(1_060).len(), 2);
+    }
+
+    #[test]
+    fn test_price_band_halts_and_resumes_via_auction() {
+        let mut engine = MatchingEngine::new();
+        create_breaker_market(&mut engine, 0.1);
+
+        let ask = create_test_order(3, "carol", Side::Sell, 0.55, 10.0);
+        let far_ask = create_test_order(4, "carol", Side::Sell, 0.7, 10.0);
+        engine.place_yes_order("test_market", ask).unwrap();
+        engine.place_yes_order("test_market", far_ask).unwrap();
+
+        // Sweeping both levels would trade at 0.7, 0.2 away from the reference
+        let sweep = create_test_order(5, "dave", Side::Buy, 0.8, 20.0);
+        assert_eq!(
+            engine.place_yes_order("test_market", sweep),
+            Err(EngineError::CircuitBreakerTripped(
+                "test_market".to_string()
+            ))
+        );
+        assert_eq!(
+            engine.market_state("test_market"),
+            Some(MarketState::Halted)
+        );
+        assert_eq!(engine.book("test_market", YES).unwrap().asks.len(), 2);
+
+        // Cool-down ends: trading resumes with a re-opening call
+        let now = current_timestamp();
+        assert!(engine.tick(now).is_empty());
+        let events = engine.tick(now + 30);
+        assert!(events.contains(&EngineEvent::StateChanged {
+            market_id: "test_market".to_string(),
+            from: MarketState::Halted,
+            to: MarketState::Open,
+        }));
+        assert!(engine.book("test_market", YES).unwrap().in_auction);
+    }
+
+    #[test]
+    fn test_last_trade_move_halts_market() {
+        let mut engine = MatchingEngine::new();
+        create_breaker_market(&mut engine, 1.0);
+
+        let ask = create_test_order(3, "carol", Side::Sell, 0.65, 10.0);
+        let buy = create_test_order(4, "dave", Side::Buy, 0.65, 10.0);
+        engine.place_yes_order("test_market", ask).unwrap();
+        let trades = engine.place_yes_order("test_market", buy).unwrap();
+
+        // The trade that moved the price still stands, but trading stops
+        assert_eq!(trades.len(), 1);
+        assert_eq!(
+            engine.market_state("test_market"),
+            Some(MarketState::Halted)
+        );
+        assert!(engine.markets["test_market"].halted_until.is_some());
+    }
+
+    #[test]
+    fn test_stop_orders_cascade_after_trades() {
+        let mut engine = MatchingEngine::new();
+        create_open_market(&mut engine, "test_market");
+
+        // Resting asks the stops will lift
+        for (id, price) in [(1, 0.55), (2, 0.60), (3, 0.65)] {
+            let ask = create_test_order(id, "maker", Side::Sell, price, 10.0);
+            engine.place_yes_order("test_market", ask).unwrap();
+        }
+
+        // Stop-market buy at 0.55 and stop-limit buy triggered at 0.60 (limit 0.62)
+        let stop_market = StopOrder {
+            order: create_test_order(10, "alice", Side::Buy, 0.0, 10.0),
+            trigger: Price(0.55),
+            kind: StopKind::Market,
+        };
+        let stop_limit = StopOrder {
+            order: create_test_order(11, "bob", Side::Buy, 0.62, 20.0),
+            trigger: Price(0.60),
+            kind: StopKind::Limit,
+        };
+        assert!(engine
+            .place_stop_order("test_market", YES, stop_market)
+            .unwrap()
+            .is_empty());
+        engine
+            .place_stop_order("test_market", YES, stop_limit)
+            .unwrap();
+        assert!(engine.book("test_market", YES).unwrap().bids.is_empty());
+
+        // A trade at 0.55 fires the stop-market, whose fill at 0.60 fires the stop-limit
+        let buy = create_test_order(20, "carol", Side::Buy, 0.55, 10.0);
+        let trades = engine.place_yes_order("test_market", buy).unwrap();
+        let buyers: Vec<&str> = trades.iter().map(|t| t.buyer.as_str()).collect();
+        assert_eq!(buyers, vec!["carol", "alice"]);
+        assert_eq!(trades[1].price, Price(0.60));
+
+        // The stop-limit could not reach 0.65 and rests at its limit; nothing is pending
+        let book = engine.book("test_market", YES).unwrap();
+        assert_eq!(book.bids[&Price(0.62)][0].user, "bob");
+        assert_eq!(book.bids[&Price(0.62)][0].qty, 20.0);
+        assert!(engine.markets["test_market"].triggers[&YES]
+            .stops
+            .is_empty());
+    }
+
+    #[test]
+    fn test_stop_that_trips_the_breaker_stays_pending() {
+        let mut engine = MatchingEngine::new();
+        create_breaker_market(&mut engine, 0.1);
+        let ask = create_test_order(3, "carol", Side::Sell, 0.55, 10.0);
+        let far_ask = create_test_order(4, "carol", Side::Sell, 0.7, 10.0);
+        engine.place_yes_order("test_market", ask).unwrap();
+        engine.place_yes_order("test_market", far_ask).unwrap();
+        let stop = StopOrder {
+            order: create_test_order(10, "alice", Side::Buy, 0.0, 20.0),
+            trigger: Price(0.55),
+            kind: StopKind::Market,
+        };
+        engine.place_stop_order("test_market", YES, stop).unwrap();
+
+        // The trade at 0.55 fires the stop, whose sweep to 0.7 breaks the band
+        let buy = create_test_order(5, "dave", Side::Buy, 0.55, 5.0);
+        assert_eq!(engine.place_yes_order("test_market", buy).unwrap().len(), 1);
+        assert_eq!(
+            engine.market_state("test_market"),
+            Some(MarketState::Halted)
+        );
+        let stops = &engine.markets["test_market"].triggers[&YES].stops;
+        assert_eq!(stops.len(), 1);
+        assert_eq!(stops[0].order.id, 10);
+    }
+
+    #[test]
+    fn test_immediate_or_cancel_does_not_rest_in_auction() {
+        let mut engine = MatchingEngine::new();
+        create_open_market(&mut engine, "test_market");
+        engine
+            .markets
+            .get_mut("test_market")
+            .unwrap()
+            .books
+            .get_mut(&YES)
+            .unwrap()
+            .in_auction = true;
+
+        let ioc = create_test_order(1, "alice", Side::Buy, 1.0, 10.0);
+        let limit = create_test_order(2, "bob", Side::Buy, 0.4, 10.0);
+        assert!(engine
+            .submit("test_market", YES, ioc, true)
+            .unwrap()
+            .is_empty());
+        assert!(engine
+            .submit("test_market", YES, limit, false)
+            .unwrap()
+            .is_empty());
+        let bids = &engine.book("test_market", YES).unwrap().bids;
+        assert_eq!(bids.len(), 1);
+        assert!(bids.contains_key(&Price(0.4)));
+    }
+
+    #[test]
+    fn test_cancel_pending_stop_order() {
+        let mut engine = MatchingEngine::new();
+        create_open_market(&mut engine, "test_market");
+        let stop = StopOrder {
+            order: create_test_order(1, "alice", Side::Sell, 0.3, 10.0),
+            trigger: Price(0.4),
+            kind: StopKind::Limit,
+        };
+        engine.place_stop_order("test_market", YES, stop).unwrap();
+
+        assert!(engine.cancel_yes_order("test_market", 1));
+        assert!(engine.markets["test_market"].triggers[&YES]
+            .stops
+            .is_empty());
+    }
+
+    #[test]
+    fn test_categorical_market() {
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        assert_eq!(
+            engine.create_outcome_market("election", &[1, 1], MarketConfig::default()),
+            Err(EngineError::InvalidOutcomes("election".to_string()))
+        );
+        engine
+            .create_outcome_market("election", &[1, 2, 3], MarketConfig::default())
+            .unwrap();
+        engine
+            .apply_market_command("admin", "election", MarketCommand::Open)
+            .unwrap();
+
+        // Each outcome trades on its own book
+        for (outcome, bid, ask) in [(1, 0.2, 0.25), (2, 0.3, 0.35), (3, 0.4, 0.45)] {
+            let id = outcome as u64 * 10;
+            engine
+                .place_order(
+                    "election",
+                    outcome,
+                    create_test_order(id, "mm", Side::Buy, bid, 10.0),
+                )
+                .unwrap();
+            engine
+                .place_order(
+                    "election",
+                    outcome,
+                    create_test_order(id + 1, "mm", Side::Sell, ask, 10.0),
+                )
+                .unwrap();
+        }
+        let trades = engine
+            .place_order(
+                "election",
+                3,
+                create_test_order(40, "alice", Side::Buy, 0.45, 5.0),
+            )
+            .unwrap();
+        assert_eq!(trades[0].outcome, 3);
+        assert_eq!(
+            engine.get_top_of_book("election", 2),
+            (Price(0.3), Price(0.35))
+        );
+
+        let (bids, asks) = engine.full_set_prices("election").unwrap();
+        assert!((bids - 0.9).abs() < 1e-9 && (asks - 1.05).abs() < 1e-9);
+
+        assert!(matches!(
+            engine.place_order(
+                "election",
+                4,
+                create_test_order(50, "bob", Side::Buy, 0.1, 1.0)
+            ),
+            Err(EngineError::UnknownOutcome { .. })
+        ));
+        assert_eq!(
+            engine.place_order(
+                "election",
+                1,
+                create_test_order(51, "bob", Side::Buy, 1.5, 1.0)
+            ),
+            Err(EngineError::InvalidPrice(1.5))
+        );
+
+        engine
+            .apply_market_command("admin", "election", MarketCommand::Close)
+            .unwrap();
+        assert!(engine
+            .apply_market_command("admin", "election", MarketCommand::Resolve { winner: 7 })
+            .is_err());
+        engine
+            .apply_market_command("admin", "election", MarketCommand::Resolve { winner: 2 })
+            .unwrap();
+        assert_eq!(engine.payout("election", 2), Some(1.0));
+        assert_eq!(engine.payout("election", 3), Some(0.0));
+    }
+
+    #[test]
+    fn test_band_market_from_factory_definition() {
+        let mut engine = MatchingEngine::new();
+        let band = BandMarket::new(
+            vec!["SPY".to_string()],
+            vec![band::ONE_E18],
+            400 * band::ONE_E18,
+            500,
+            2_000,
+        )
+        .unwrap();
+        engine
+            .create_band_market("0xmarket", band.clone(), MarketConfig::default())
+            .unwrap();
+
+        let market = &engine.markets["0xmarket"];
+        assert_eq!(market.outcomes, vec![WITHIN, OUTSIDE]);
+        assert_eq!(market.config.trading_end, Some(2_000));
+        assert_eq!(market.band.as_ref(), Some(&band));
+        assert_eq!(band.outcome_at(419 * band::ONE_E18), WITHIN);
+        assert_eq!(band.outcome_at(421 * band::ONE_E18), OUTSIDE);
+    }
+
+    #[test]
+    fn test_band_market_resolves_from_feed_at_settle() {
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        let band = BandMarket::new(
+            vec!["SPY".to_string()],
+            vec![band::ONE_E18],
+            400 * band::ONE_E18,
+            500,
+            2_000,
+        )
+        .unwrap();
+        engine
+            .create_band_market("0xmarket", band, MarketConfig::default())
+            .unwrap();
+        engine
+            .apply_market_command("admin", "0xmarket", MarketCommand::Open)
+            .unwrap();
+
+        // Without a feed the market closes at settle time but stays unresolved
+        assert_eq!(engine.tick(2_000).len(), 1);
+        assert_eq!(engine.market_state("0xmarket"), Some(MarketState::Closed));
+
+        let feed =
+            CsvReplayFeed::from_csv("SPY,41000000000,8,1990\nSPY,43000000000,8,2100\n").unwrap();
+        engine.set_price_feed(Box::new(feed));
+        // Resolving late still prices the basket as of the settle time
+        let events = engine.tick(2_200);
+        assert_eq!(
+            events,
+            vec![EngineEvent::StateChanged {
+                market_id: "0xmarket".to_string(),
+                from: MarketState::Closed,
+                to: MarketState::Resolved,
+            }]
+        );
+        assert_eq!(engine.payout("0xmarket", WITHIN), Some(1.0));
+        assert_eq!(
+            engine.markets["0xmarket"].final_price_1e18,
+            Some(410 * band::ONE_E18)
+        );
+    }
+
+    #[test]
+    fn test_suspect_feed_disputes_band_market() {
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        let band = BandMarket::new(
+            vec!["SPY".to_string()],
+            vec![band::ONE_E18],
+            400 * band::ONE_E18,
+            500,
+            2_000,
+        )
+        .unwrap();
+        engine
+            .create_band_market("0xmarket", band, MarketConfig::default())
+            .unwrap();
+
+        // The last update is well over five minutes before settlement
+        let feed = CsvReplayFeed::from_csv("SPY,41000000000,8,400\n").unwrap();
+        engine.pricer = Some(BasketPricer::new(Box::new(feed)).with_checks(FeedChecks {
+            max_staleness_secs: 300,
+            max_deviation_bps: 1_000,
+        }));
+        let events = engine.tick(2_000);
+        assert!(
+            matches!(&events[1], EngineEvent::Disputed { market_id, .. } if market_id == "0xmarket")
+        );
+        assert!(engine.markets["0xmarket"].dispute.is_some());
+        assert!(engine.tick(2_100).is_empty());
+        assert_eq!(
+            engine.resolve_band_market("0xmarket", 2_100),
+            Err(EngineError::Disputed("0xmarket".to_string()))
+        );
+
+        // Manual override
+        engine
+            .apply_market_command(
+                "admin",
+                "0xmarket",
+                MarketCommand::Resolve { winner: OUTSIDE },
+            )
+            .unwrap();
+        assert_eq!(engine.payout("0xmarket", OUTSIDE), Some(1.0));
+    }
+
+    #[test]
+    fn test_signed_orders_only_on_signed_markets() {
+        use signing::tests::{domain, sign, unsigned, wallet};
+
+        let mut engine = MatchingEngine::new();
+        create_open_market(&mut engine, "test_market");
+        engine
+            .set_market_domain("admin", "test_market", domain())
+            .unwrap();
+        let order = create_test_order(1, "alice", Side::Buy, 0.6, 10.0);
+        assert_eq!(
+            engine.place_yes_order("test_market", order),
+            Err(EngineError::SignatureRequired("test_market".to_string()))
+        );
+
+        let key = wallet(3);
+        let mut signed = unsigned(2, &key, "test_market", 5);
+        signed.expiry = u64::MAX;
+        let signed = sign(signed, &key, &domain());
+        assert!(engine.place_signed_order(&signed).unwrap().is_empty());
+        let maker = signing::format_address(&signed.maker);
+        assert_eq!(
+            engine.book("test_market", YES).unwrap().bids[&Price(0.6)][0].user,
+            maker
+        );
+
+        // Replaying the same signed order is rejected
+        assert_eq!(
+            engine.place_signed_order(&signed),
+            Err(EngineError::NonceUsed {
+                user: maker,
+                nonce: 5
+            })
+        );
+
+        // A signature from another wallet does not authorise this maker
+        let mut forged = unsigned(3, &key, "test_market", 6);
+        forged.expiry = u64::MAX;
+        let forged = sign(forged, &wallet(4), &domain());
+        assert!(matches!(
+            engine.place_signed_order(&forged),
+            Err(EngineError::InvalidSignature(_))
+        ));
+    }
+
+    #[test]
+    fn test_level_changes() {
+        let before = vec![(Price(0.4), 10.0), (Price(0.5), 5.0)];
+        let after = vec![(Price(0.5), 5.0), (Price(0.6), 7.0)];
+        assert_eq!(
+            level_changes(&before, &after),
+            vec![(Price(0.4), 0.0), (Price(0.6), 7.0)]
+        );
+        assert!(level_changes(&after, &after).is_empty());
+    }
 }
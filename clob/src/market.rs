@@ -0,0 +1,249 @@
+use crate::band::BandMarket;
+use crate::circuit_breaker::{CircuitBreaker, PriceHistory};
+use crate::error::EngineError;
+use crate::signing::Eip712Domain;
+use crate::stops::TriggerBook;
+use crate::OrderBook;
+use std::collections::BTreeMap;
+
+// --------------------- Outcomes ---------------------
+// Mutually exclusive outcomes of a market; exactly one pays 1.0 at resolution.
+// Codes match the factory's claim ids (1 = WITHIN/YES, 2 = OUTSIDE/NO).
+pub type OutcomeId = u8;
+
+pub const YES: OutcomeId = 1;
+pub const NO: OutcomeId = 2;
+
+// --------------------- Lifecycle ---------------------
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarketState {
+    PreOpen,  // created, not accepting orders yet
+    Open,     // accepting and matching orders
+    Halted,   // accepting cancels only
+    Closed,   // trading ended, books cleared
+    Resolved, // winning outcome known
+}
+
+// Admin commands that move a market between states
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarketCommand {
+    Open,
+    Halt,
+    Resume,
+    Close,
+    Resolve { winner: OutcomeId },
+}
+
+impl MarketState {
+    // Returns the state reached by applying `command`, or an error if the
+    // transition is not allowed from the current state
+    pub fn apply(self, command: MarketCommand) -> Result<MarketState, EngineError> {
+        let next = match (self, command) {
+            (MarketState::PreOpen, MarketCommand::Open) => MarketState::Open,
+            (MarketState::Open, MarketCommand::Halt) => MarketState::Halted,
+            (MarketState::Halted, MarketCommand::Resume) => MarketState::Open,
+            (
+                MarketState::PreOpen | MarketState::Open | MarketState::Halted,
+                MarketCommand::Close,
+            ) => MarketState::Closed,
+            (MarketState::Closed, MarketCommand::Resolve { .. }) => MarketState::Resolved,
+            (from, command) => return Err(EngineError::InvalidTransition { from, command }),
+        };
+        Ok(next)
+    }
+
+    pub fn accepts_orders(self) -> bool {
+        self == MarketState::Open
+    }
+}
+
+// How a market's books turn resting orders into trades
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MatchingMode {
+    #[default]
+    Continuous, // match on every incoming order
+    FrequentBatch {
+        interval_secs: u64, // orders accumulate and clear at one uniform price per batch
+    },
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MarketConfig {
+    pub trading_end: Option<u64>, // unix seconds; market closes automatically at this time
+    pub auction_secs: u64,        // call period on open/resume; 0 starts matching immediately
+    pub matching: MatchingMode,   // batch markets skip the opening call, every batch is one
+    pub circuit_breaker: Option<CircuitBreaker>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Market {
+    pub id: String,
+    pub state: MarketState,
+    pub config: MarketConfig,
+    pub outcomes: Vec<OutcomeId>,
+    pub band: Option<BandMarket>, // basket definition of WITHIN/OUTSIDE markets
+    pub books: BTreeMap<OutcomeId, OrderBook>,
+    pub triggers: BTreeMap<OutcomeId, TriggerBook>, // pending stop orders per outcome
+    pub price_history: BTreeMap<OutcomeId, PriceHistory>, // recent trades for circuit breakers
+    pub winner: Option<OutcomeId>,                  // set once resolved
+    pub final_price_1e18: Option<i128>,             // basket price a band market settled at
+    pub domain: Option<Eip712Domain>, // set when the market only takes EIP-712 signed orders
+    pub dispute: Option<String>, // why automatic resolution is blocked; an admin resolves by hand
+    pub auction_end: Option<u64>, // set while an opening/re-opening call is running
+    pub next_batch: Option<u64>, // next clearing time in frequent batch mode
+    pub halted_until: Option<u64>, // automatic resume time after a circuit breaker trip
+}
+
+impl Market {
+    pub fn new(id: &str, outcomes: &[OutcomeId], config: MarketConfig) -> Self {
+        Self {
+            id: id.to_string(),
+            state: MarketState::PreOpen,
+            config,
+            outcomes: outcomes.to_vec(),
+            band: None,
+            books: outcomes
+                .iter()
+                .map(|&outcome| (outcome, OrderBook::new(id, outcome)))
+                .collect(),
+            triggers: BTreeMap::new(),
+            price_history: BTreeMap::new(),
+            winner: None,
+            final_price_1e18: None,
+            dispute: None,
+            domain: None,
+            auction_end: None,
+            next_batch: None,
+            halted_until: None,
+        }
+    }
+
+    // What one share of `outcome` is worth: 1.0 for the winner, 0.0 for the
+    // rest, so a full set of outcomes is always worth exactly 1.0
+    pub fn payout(&self, outcome: OutcomeId) -> Option<f64> {
+        let winner = self.winner?;
+        self.outcomes
+            .contains(&outcome)
+            .then_some(if outcome == winner { 1.0 } else { 0.0 })
+    }
+
+    // Put the books of a market that just (re)opened into the right matching mode
+    pub fn start_trading(&mut self, now: u64, auction_secs: u64) {
+        match self.config.matching {
+            MatchingMode::FrequentBatch { interval_secs } => {
+                self.next_batch = Some(now + interval_secs);
+            }
+            // Opening and re-opening both collect orders in a call first
+            MatchingMode::Continuous if auction_secs > 0 => {
+                self.auction_end = Some(now + auction_secs);
+            }
+            MatchingMode::Continuous => return,
+        }
+        for book in self.books.values_mut() {
+            book.start_auction();
+        }
+    }
+
+    // Halt after a circuit breaker trip; the engine's `tick` resumes trading after the cool-down
+    pub fn trip_circuit_breaker(&mut self, now: u64) {
+        let Some(breaker) = self.config.circuit_breaker else {
+            return;
+        };
+        if let Ok(next) = self.state.apply(MarketCommand::Halt) {
+            self.state = next;
+            self.halted_until = Some(now + breaker.cool_down_secs);
+        }
+        for history in self.price_history.values_mut() {
+            history.reset();
+        }
+    }
+
+    // Drop all resting and pending stop orders
+    pub fn clear_books(&mut self) {
+        for book in self.books.values_mut() {
+            book.bids.clear();
+            book.asks.clear();
+            book.in_auction = false;
+        }
+        self.triggers.clear();
+        self.auction_end = None;
+        self.next_batch = None;
+    }
+
+    pub fn trading_ended(&self, now: u64) -> bool {
+        self.config.trading_end.is_some_and(|end| now >= end)
+    }
+
+    pub fn auction_due(&self, now: u64) -> bool {
+        self.state == MarketState::Open && self.auction_end.is_some_and(|end| now >= end)
+    }
+
+    pub fn resume_due(&self, now: u64) -> bool {
+        self.state == MarketState::Halted && self.halted_until.is_some_and(|at| now >= at)
+    }
+
+    pub fn batch_due(&self, now: u64) -> bool {
+        self.state == MarketState::Open && self.next_batch.is_some_and(|at| now >= at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowed_transitions() {
+        let state = MarketState::PreOpen;
+        let state = state.apply(MarketCommand::Open).unwrap();
+        assert_eq!(state, MarketState::Open);
+        let state = state.apply(MarketCommand::Halt).unwrap();
+        assert_eq!(state, MarketState::Halted);
+        let state = state.apply(MarketCommand::Resume).unwrap();
+        assert_eq!(state, MarketState::Open);
+        let state = state.apply(MarketCommand::Close).unwrap();
+        assert_eq!(state, MarketState::Closed);
+        let state = state.apply(MarketCommand::Resolve { winner: YES }).unwrap();
+        assert_eq!(state, MarketState::Resolved);
+    }
+
+    #[test]
+    fn test_rejected_transitions() {
+        assert!(MarketState::PreOpen.apply(MarketCommand::Halt).is_err());
+        assert!(MarketState::Open
+            .apply(MarketCommand::Resolve { winner: NO })
+            .is_err());
+        assert!(MarketState::Closed.apply(MarketCommand::Open).is_err());
+        assert!(MarketState::Resolved.apply(MarketCommand::Close).is_err());
+    }
+
+    #[test]
+    fn test_trading_end() {
+        let market = Market::new(
+            "m",
+            &[YES, NO],
+            MarketConfig {
+                trading_end: Some(100),
+                ..Default::default()
+            },
+        );
+        assert!(!market.trading_ended(99));
+        assert!(market.trading_ended(100));
+        assert!(!Market::new("m", &[YES, NO], MarketConfig::default()).trading_ended(u64::MAX));
+    }
+
+    #[test]
+    fn test_payouts_sum_to_one() {
+        let mut market = Market::new("m", &[1, 2, 3, 4], MarketConfig::default());
+        assert_eq!(market.payout(1), None);
+
+        market.winner = Some(3);
+        let total: f64 = market
+            .outcomes
+            .iter()
+            .filter_map(|&o| market.payout(o))
+            .sum();
+        assert_eq!(total, 1.0);
+        assert_eq!(market.payout(3), Some(1.0));
+        assert_eq!(market.payout(9), None);
+    }
+}
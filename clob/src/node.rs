@@ -0,0 +1,515 @@
+// --------------------- Node Binding ---------------------
+// The engine as a JS class (napi-rs). Orders, trades and depth cross as plain
+// JS objects and arrays owned by the JS heap, so nothing needs freeing.
+// Order ids are BigInts: engine ids use the full u64 range, which a JS number
+// cannot hold exactly.
+
+use crate::ffi::{outcome_label, parse_outcome};
+use crate::{
+    current_timestamp, BatchOrder, EngineEvent, MarketCommand, MarketState, MatchingEngine, Order,
+    OutcomeId, Price, Side, Trade,
+};
+use napi::bindgen_prelude::{AsyncTask, BigInt};
+use napi::{Env, Error, Result, Task};
+use napi_derive::napi;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+#[napi(string_enum)]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+#[napi(string_enum)]
+pub enum MarketAction {
+    Open,
+    Halt,
+    Resume,
+    Close,
+    Resolve,
+}
+
+#[napi(object, js_name = "Order")]
+pub struct JsOrder {
+    pub id: BigInt,
+    pub user: String,
+    pub side: OrderSide,
+    pub price: f64,
+    pub qty: f64,
+    pub timestamp: Option<i64>, // defaults to now
+    pub market: String,         // "YES", "NO" or a numeric outcome id
+    pub market_id: String,
+    pub display_qty: Option<f64>, // iceberg: show at most this much of `qty`
+}
+
+#[napi(object, js_name = "Trade")]
+pub struct JsTrade {
+    pub buyer: String,
+    pub seller: String,
+    pub buy_order_id: BigInt,
+    pub sell_order_id: BigInt,
+    pub qty: f64,
+    pub price: f64,
+    pub market: String,
+    pub market_id: String,
+    pub timestamp: i64,
+}
+
+#[napi(object, js_name = "Level")]
+pub struct JsLevel {
+    pub price: f64,
+    pub qty: f64,
+}
+
+#[napi(object, js_name = "Depth")]
+pub struct JsDepth {
+    pub bids: Vec<JsLevel>,
+    pub asks: Vec<JsLevel>,
+}
+
+#[napi(object, js_name = "TopOfBook")]
+pub struct JsTopOfBook {
+    pub best_bid: f64,
+    pub best_ask: f64,
+}
+
+// Outcome of `placeOrders`: an error message or null per order, and every trade
+#[napi(object, js_name = "BatchPlaced")]
+pub struct JsBatchPlaced {
+    pub errors: Vec<Option<String>>,
+    pub trades: Vec<JsTrade>,
+}
+
+fn js_error(e: impl ToString) -> Error {
+    Error::from_reason(e.to_string())
+}
+
+fn outcome(market: &str) -> Result<OutcomeId> {
+    parse_outcome(market).ok_or_else(|| js_error(format!("unknown outcome {market}")))
+}
+
+fn order_id(id: &BigInt) -> Result<u64> {
+    match id.get_u64() {
+        (false, id, true) => Ok(id),
+        _ => Err(js_error("order id must fit in an unsigned 64-bit integer")),
+    }
+}
+
+fn order_from(order: JsOrder) -> Result<(String, OutcomeId, Order)> {
+    let outcome = outcome(&order.market)?;
+    let engine_order = Order {
+        id: order_id(&order.id)?,
+        user: order.user,
+        side: match order.side {
+            OrderSide::Buy => Side::Buy,
+            OrderSide::Sell => Side::Sell,
+        },
+        price: Price(order.price),
+        qty: order.qty,
+        timestamp: order
+            .timestamp
+            .map_or_else(current_timestamp, |t| t.max(0) as u64),
+        iceberg: None,
+    };
+    let engine_order = match order.display_qty {
+        Some(display_qty) => engine_order.with_iceberg(display_qty).map_err(js_error)?,
+        None => engine_order,
+    };
+    Ok((order.market_id, outcome, engine_order))
+}
+
+fn js_trade(trade: &Trade) -> JsTrade {
+    JsTrade {
+        buyer: trade.buyer.clone(),
+        seller: trade.seller.clone(),
+        buy_order_id: BigInt::from(trade.buy_order_id),
+        sell_order_id: BigInt::from(trade.sell_order_id),
+        qty: trade.qty,
+        price: trade.price.0,
+        market: outcome_label(trade.outcome),
+        market_id: trade.market_id.clone(),
+        timestamp: trade.timestamp as i64,
+    }
+}
+
+fn state_name(state: MarketState) -> String {
+    format!("{state:?}")
+}
+
+type SharedEngine = Arc<Mutex<MatchingEngine>>;
+
+fn lock(engine: &SharedEngine) -> MutexGuard<'_, MatchingEngine> {
+    // A panic elsewhere must not take the whole process down with it
+    engine.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// ----- Async tasks -----
+// Matching runs on the libuv pool; the JS side gets a promise
+
+pub struct PlaceOrder {
+    engine: SharedEngine,
+    order: Option<(String, OutcomeId, Order)>,
+}
+
+impl Task for PlaceOrder {
+    type Output = Vec<Trade>;
+    type JsValue = Vec<JsTrade>;
+
+    fn compute(&mut self) -> Result<Vec<Trade>> {
+        let (market_id, outcome, order) = self.order.take().expect("task runs once");
+        lock(&self.engine)
+            .place_order(&market_id, outcome, order)
+            .map_err(js_error)
+    }
+
+    fn resolve(&mut self, _env: Env, trades: Vec<Trade>) -> Result<Vec<JsTrade>> {
+        Ok(trades.iter().map(js_trade).collect())
+    }
+}
+
+pub struct PlaceOrders {
+    engine: SharedEngine,
+    batch: Vec<BatchOrder>,
+}
+
+impl Task for PlaceOrders {
+    type Output = (Vec<Option<String>>, Vec<Trade>);
+    type JsValue = JsBatchPlaced;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let placed = lock(&self.engine).place_orders(std::mem::take(&mut self.batch));
+        let errors = placed
+            .results
+            .into_iter()
+            .map(|r| r.err().map(|e| e.to_string()))
+            .collect();
+        Ok((errors, placed.trades))
+    }
+
+    fn resolve(&mut self, _env: Env, (errors, trades): Self::Output) -> Result<JsBatchPlaced> {
+        Ok(JsBatchPlaced {
+            errors,
+            trades: trades.iter().map(js_trade).collect(),
+        })
+    }
+}
+
+// Timed work may call out to the price feed, so it runs off the JS thread too
+pub struct Tick {
+    engine: SharedEngine,
+    now: u64,
+}
+
+impl Task for Tick {
+    type Output = Vec<Trade>;
+    type JsValue = Vec<JsTrade>;
+
+    fn compute(&mut self) -> Result<Vec<Trade>> {
+        let trades = lock(&self.engine)
+            .tick(self.now)
+            .into_iter()
+            .filter_map(|event| match event {
+                EngineEvent::Trade(trade) => Some(trade),
+                _ => None,
+            })
+            .collect();
+        Ok(trades)
+    }
+
+    fn resolve(&mut self, _env: Env, trades: Vec<Trade>) -> Result<Vec<JsTrade>> {
+        Ok(trades.iter().map(js_trade).collect())
+    }
+}
+
+// ----- Engine class -----
+#[napi(js_name = "Clob")]
+pub struct JsClob {
+    engine: SharedEngine,
+}
+
+impl Default for JsClob {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[napi]
+impl JsClob {
+    #[napi(constructor)]
+    pub fn new() -> Self {
+        Self {
+            engine: Arc::new(Mutex::new(MatchingEngine::new())),
+        }
+    }
+
+    #[napi]
+    pub fn create_market(&self, market_id: String) {
+        lock(&self.engine).create_market(&market_id);
+    }
+
+    #[napi]
+    pub fn add_admin(&self, admin: String) {
+        lock(&self.engine).add_admin(&admin);
+    }
+
+    // Returns the market's new state
+    #[napi]
+    pub fn market_command(
+        &self,
+        caller: String,
+        market_id: String,
+        action: MarketAction,
+        winner: Option<u32>,
+    ) -> Result<String> {
+        let command = match action {
+            MarketAction::Open => MarketCommand::Open,
+            MarketAction::Halt => MarketCommand::Halt,
+            MarketAction::Resume => MarketCommand::Resume,
+            MarketAction::Close => MarketCommand::Close,
+            MarketAction::Resolve => {
+                let winner = winner.ok_or_else(|| js_error("resolve needs a winner"))?;
+                MarketCommand::Resolve {
+                    winner: OutcomeId::try_from(winner).map_err(js_error)?,
+                }
+            }
+        };
+        lock(&self.engine)
+            .apply_market_command(&caller, &market_id, command)
+            .map(state_name)
+            .map_err(js_error)
+    }
+
+    #[napi]
+    pub fn market_state(&self, market_id: String) -> Option<String> {
+        lock(&self.engine).market_state(&market_id).map(state_name)
+    }
+
+    // Resolves to every trade the order made; rejects if the engine refuses it
+    #[napi(ts_return_type = "Promise<Array<Trade>>")]
+    pub fn place_order(&self, order: JsOrder) -> Result<AsyncTask<PlaceOrder>> {
+        Ok(AsyncTask::new(PlaceOrder {
+            engine: Arc::clone(&self.engine),
+            order: Some(order_from(order)?),
+        }))
+    }
+
+    // Atomic per market, like `MatchingEngine::place_orders`
+    #[napi(ts_return_type = "Promise<BatchPlaced>")]
+    pub fn place_orders(&self, orders: Vec<JsOrder>) -> Result<AsyncTask<PlaceOrders>> {
+        let batch = orders
+            .into_iter()
+            .map(|o| {
+                let (market_id, outcome, order) = order_from(o)?;
+                Ok(BatchOrder {
+                    market_id,
+                    outcome,
+                    order,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(AsyncTask::new(PlaceOrders {
+            engine: Arc::clone(&self.engine),
+            batch,
+        }))
+    }
+
+    // Cancel an order on whichever outcome book of the market holds it
+    #[napi]
+    pub fn cancel_order(&self, market_id: String, order_id: BigInt) -> bool {
+        let Ok(order_id) = self::order_id(&order_id) else {
+            return false;
+        };
+        let mut engine = lock(&self.engine);
+        let outcomes = engine
+            .markets
+            .get(&market_id)
+            .map(|m| m.outcomes.clone())
+            .unwrap_or_default();
+        outcomes
+            .into_iter()
+            .any(|outcome| engine.cancel_order(&market_id, outcome, order_id))
+    }
+
+    // Ids of the user's cancelled orders, across all markets if none is given
+    #[napi]
+    pub fn cancel_all(&self, user: String, market_id: Option<String>) -> Vec<BigInt> {
+        lock(&self.engine)
+            .cancel_all(&user, market_id.as_deref())
+            .iter()
+            .map(|o| BigInt::from(o.id))
+            .collect()
+    }
+
+    // Run the engine's timed work (auctions, halts, sessions, trading end,
+    // settlement) up to `now`; resolves to the trades it made
+    #[napi(ts_return_type = "Promise<Array<Trade>>")]
+    pub fn tick(&self, now: i64) -> AsyncTask<Tick> {
+        AsyncTask::new(Tick {
+            engine: Arc::clone(&self.engine),
+            now: now.max(0) as u64,
+        })
+    }
+
+    #[napi]
+    pub fn get_top_of_book(&self, market_id: String, market: String) -> Result<JsTopOfBook> {
+        let outcome = outcome(&market)?;
+        let engine = lock(&self.engine);
+        let book = engine
+            .book(&market_id, outcome)
+            .ok_or_else(|| js_error(format!("unknown market {market_id}")))?;
+        let (best_bid, best_ask) = book.get_top_of_book();
+        Ok(JsTopOfBook {
+            best_bid: best_bid.0,
+            best_ask: best_ask.0,
+        })
+    }
+
+    // Aggregated levels, best first; all of them unless `levels` is given
+    #[napi]
+    pub fn get_order_book_depth(
+        &self,
+        market_id: String,
+        market: String,
+        levels: Option<u32>,
+    ) -> Result<JsDepth> {
+        let outcome = outcome(&market)?;
+        let engine = lock(&self.engine);
+        let book = engine
+            .book(&market_id, outcome)
+            .ok_or_else(|| js_error(format!("unknown market {market_id}")))?;
+        let (bids, asks) = book.get_order_book_depth(levels.map_or(usize::MAX, |n| n as usize));
+        let js_levels = |levels: Vec<(Price, f64)>| {
+            levels
+                .into_iter()
+                .map(|(price, qty)| JsLevel {
+                    price: price.0,
+                    qty,
+                })
+                .collect()
+        };
+        Ok(JsDepth {
+            bids: js_levels(bids),
+            asks: js_levels(asks),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::YES;
+
+    #[test]
+    fn test_order_conversion() {
+        let order = JsOrder {
+            id: BigInt::from(7u64),
+            user: "alice".to_string(),
+            side: OrderSide::Sell,
+            price: 0.4,
+            qty: 3.0,
+            timestamp: Some(100),
+            market: "YES".to_string(),
+            market_id: "m".to_string(),
+            display_qty: Some(1.0),
+        };
+        let (market_id, outcome, order) = order_from(order).unwrap();
+        assert_eq!((market_id.as_str(), outcome), ("m", YES));
+        assert_eq!(
+            (order.id, order.side, order.timestamp),
+            (7, Side::Sell, 100)
+        );
+        assert_eq!((order.qty, order.total_qty()), (1.0, 3.0));
+
+        let bad = JsOrder {
+            id: BigInt {
+                sign_bit: true,
+                words: vec![1],
+            },
+            user: "alice".to_string(),
+            side: OrderSide::Buy,
+            price: 0.4,
+            qty: 3.0,
+            timestamp: None,
+            market: "YES".to_string(),
+            market_id: "m".to_string(),
+            display_qty: None,
+        };
+        assert!(order_from(bad).is_err());
+
+        // Ids above 2^53 survive the trip, where a JS number would round them
+        let big = BigInt::from(u64::MAX - 1);
+        assert_eq!(order_id(&big).unwrap(), u64::MAX - 1);
+        let too_big = BigInt {
+            sign_bit: false,
+            words: vec![0, 1],
+        };
+        assert!(order_id(&too_big).is_err());
+    }
+
+    fn js_order(id: u64, user: &str, side: OrderSide, price: f64, qty: f64) -> JsOrder {
+        JsOrder {
+            id: BigInt::from(id),
+            user: user.to_string(),
+            side,
+            price,
+            qty,
+            timestamp: None,
+            market: "YES".to_string(),
+            market_id: "m".to_string(),
+            display_qty: None,
+        }
+    }
+
+    // The tasks behind the promises, run as the libuv pool would run them
+    fn place(clob: &JsClob, order: JsOrder) -> Vec<Trade> {
+        let mut task = PlaceOrder {
+            engine: Arc::clone(&clob.engine),
+            order: Some(order_from(order).unwrap()),
+        };
+        task.compute().unwrap()
+    }
+
+    #[test]
+    fn test_place_cancel_and_tick() {
+        let clob = JsClob::new();
+        clob.add_admin("admin".to_string());
+        clob.create_market("m".to_string());
+        let state = clob
+            .market_command(
+                "admin".to_string(),
+                "m".to_string(),
+                MarketAction::Open,
+                None,
+            )
+            .unwrap();
+        assert_eq!(state, "Open");
+
+        assert!(place(&clob, js_order(1, "alice", OrderSide::Sell, 0.6, 10.0)).is_empty());
+        let trades = place(&clob, js_order(2, "bob", OrderSide::Buy, 0.6, 4.0));
+        assert_eq!((trades[0].seller.as_str(), trades[0].qty), ("alice", 4.0));
+        let depth = clob
+            .get_order_book_depth("m".to_string(), "YES".to_string(), None)
+            .unwrap();
+        assert_eq!((depth.asks[0].price, depth.asks[0].qty), (0.6, 6.0));
+
+        assert!(clob.cancel_order("m".to_string(), BigInt::from(1u64)));
+        assert!(!clob.cancel_order("m".to_string(), BigInt::from(1u64)));
+
+        // Ticking past the trading end closes the market
+        lock(&clob.engine)
+            .markets
+            .get_mut("m")
+            .unwrap()
+            .config
+            .trading_end = Some(1_000);
+        let mut tick = Tick {
+            engine: Arc::clone(&clob.engine),
+            now: 1_000,
+        };
+        assert!(tick.compute().unwrap().is_empty());
+        assert_eq!(
+            clob.market_state("m".to_string()).as_deref(),
+            Some("Closed")
+        );
+    }
+}
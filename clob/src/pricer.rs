@@ -0,0 +1,299 @@
+use crate::band::{bounds_for_band, ONE_E18};
+use crate::error::EngineError;
+use crate::feed::{FeedPrice, PriceFeed};
+use std::collections::{BTreeMap, HashMap};
+
+// --------------------- Basket Pricer ---------------------
+// Port of `Decs.to1e18`: rescale a feed answer with `decimals` to 1e18
+pub fn to_1e18(price: i128, decimals: u8) -> Result<i128, EngineError> {
+    let overflow = || EngineError::Feed(format!("price {} overflows at 1e18", price));
+    match decimals {
+        18 => Ok(price),
+        d if d < 18 => price
+            .checked_mul(10i128.pow(18 - d as u32))
+            .ok_or_else(overflow),
+        d => Ok(10i128
+            .checked_pow(d as u32 - 18)
+            .map_or(0, |scale| price / scale)),
+    }
+}
+
+// `a * b / 1e18` truncated toward zero without overflowing the intermediate product
+fn mul_1e18(a: i128, b: i128) -> Option<i128> {
+    let whole = (a / ONE_E18).checked_mul(b)?;
+    let frac = (a % ONE_E18).checked_mul(b)? / ONE_E18;
+    whole.checked_add(frac)
+}
+
+// Port of `calculateWeightedBasketPrice` over prices already fetched from a feed
+pub fn weighted_basket_price(
+    prices: &[FeedPrice],
+    weights_1e18: &[i128],
+) -> Result<i128, EngineError> {
+    if prices.len() != weights_1e18.len() {
+        return Err(EngineError::Feed("len mismatch".to_string()));
+    }
+    prices
+        .iter()
+        .zip(weights_1e18)
+        .try_fold(0i128, |basket, (price, &weight)| {
+            if weight < 0 {
+                return Err(EngineError::Feed("weight<0".to_string()));
+            }
+            mul_1e18(to_1e18(price.price, price.decimals)?, weight)
+                .and_then(|value| basket.checked_add(value))
+                .ok_or_else(|| EngineError::Feed("basket price overflows".to_string()))
+        })
+}
+
+// Sanity checks on feed answers before they are trusted for resolution
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeedChecks {
+    pub max_staleness_secs: u64, // oldest `updated_at` accepted, relative to now
+    pub max_deviation_bps: u32,  // largest move allowed between consecutive updates of a symbol
+}
+
+// Updates of one symbol seen so far, keyed by `updated_at`
+type UpdateHistory = BTreeMap<u64, FeedPrice>;
+
+// Updates kept per symbol; older ones are forgotten
+const HISTORY_LEN: usize = 64;
+
+impl FeedChecks {
+    fn check(
+        &self,
+        symbol: &str,
+        price: &FeedPrice,
+        history: &UpdateHistory,
+        now: u64,
+    ) -> Result<(), EngineError> {
+        let suspect =
+            |reason: String| Err(EngineError::SuspectFeed(format!("{}: {}", symbol, reason)));
+        let price_1e18 = to_1e18(price.price, price.decimals)?;
+        if price_1e18 <= 0 {
+            return suspect(format!("non-positive price {}", price.price));
+        }
+        if now.saturating_sub(price.updated_at) > self.max_staleness_secs {
+            return suspect(format!("stale since {}", price.updated_at));
+        }
+        if price.updated_at > now {
+            return suspect(format!("updated in the future at {}", price.updated_at));
+        }
+
+        // Compare with the updates just before and just after this one: the
+        // answer may be older than the latest update seen, e.g. when pricing
+        // as of a settle time
+        let before = history.range(..price.updated_at).next_back();
+        let after = history.range(price.updated_at + 1..).next();
+        let pairs = [
+            before.map(|(_, earlier)| (earlier, price)),
+            after.map(|(_, later)| (price, later)),
+        ];
+        for (earlier, later) in pairs.into_iter().flatten() {
+            let earlier_1e18 = to_1e18(earlier.price, earlier.decimals)?;
+            let later_1e18 = to_1e18(later.price, later.decimals)?;
+            let moved = (later_1e18 - earlier_1e18).abs();
+            let limit = earlier_1e18 / 10_000 * self.max_deviation_bps as i128;
+            if moved > limit {
+                return suspect(format!("moved from {} to {}", earlier_1e18, later_1e18));
+            }
+        }
+        Ok(())
+    }
+}
+
+// `BasketPricer` with the on-chain aggregator replaced by a pluggable feed
+pub struct BasketPricer {
+    pub feed: Box<dyn PriceFeed>,
+    pub checks: Option<FeedChecks>,
+    history: HashMap<String, UpdateHistory>, // every update the feed has reported, per symbol
+}
+
+impl BasketPricer {
+    pub fn new(feed: Box<dyn PriceFeed>) -> Self {
+        Self {
+            feed,
+            checks: None,
+            history: HashMap::new(),
+        }
+    }
+
+    pub fn with_checks(mut self, checks: FeedChecks) -> Self {
+        self.checks = Some(checks);
+        self
+    }
+
+    pub fn calculate_weighted_basket_price(
+        &mut self,
+        symbols: &[String],
+        weights_1e18: &[i128],
+        now: u64,
+    ) -> Result<i128, EngineError> {
+        if symbols.len() != weights_1e18.len() {
+            return Err(EngineError::Feed("len mismatch".to_string()));
+        }
+        let prices = self.feed.get_prices(symbols, now)?;
+        let empty = UpdateHistory::new();
+        let checked = self.checks.map_or(Ok(()), |checks| {
+            symbols.iter().zip(&prices).try_for_each(|(symbol, price)| {
+                let history = self.history.get(symbol).unwrap_or(&empty);
+                checks.check(symbol, price, history, now)
+            })
+        });
+        // Suspect answers are still updates the feed made: the next one is
+        // checked against them
+        for (symbol, price) in symbols.iter().zip(&prices) {
+            let history = self.history.entry(symbol.clone()).or_default();
+            history.insert(price.updated_at, *price);
+            while history.len() > HISTORY_LEN {
+                history.pop_first();
+            }
+        }
+        checked?;
+        weighted_basket_price(&prices, weights_1e18)
+    }
+
+    // Basket price and its band bounds, like `quoteAndBounds`
+    pub fn quote_and_bounds(
+        &mut self,
+        symbols: &[String],
+        weights_1e18: &[i128],
+        band_bps: u16,
+        now: u64,
+    ) -> Result<(i128, i128, i128), EngineError> {
+        let price = self.calculate_weighted_basket_price(symbols, weights_1e18, now)?;
+        let (lower, upper) = bounds_for_band(price, band_bps)?;
+        Ok((price, lower, upper))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feed::CsvReplayFeed;
+
+    #[test]
+    fn test_feed_checks() {
+        let feed = CsvReplayFeed::from_csv(
+            "SPY,50000000000,8,100\n\
+             SPY,50400000000,8,200\n\
+             SPY,60000000000,8,300\n",
+        )
+        .unwrap();
+        let mut pricer = BasketPricer::new(Box::new(feed)).with_checks(FeedChecks {
+            max_staleness_secs: 60,
+            max_deviation_bps: 100,
+        });
+        let symbols = vec!["SPY".to_string()];
+        let weights = vec![ONE_E18];
+
+        assert_eq!(
+            pricer.calculate_weighted_basket_price(&symbols, &weights, 130),
+            Ok(500 * ONE_E18)
+        );
+        assert!(matches!(
+            pricer.calculate_weighted_basket_price(&symbols, &weights, 190),
+            Err(EngineError::SuspectFeed(_)) // latest update is 90s old
+        ));
+        assert_eq!(
+            pricer.calculate_weighted_basket_price(&symbols, &weights, 210),
+            Ok(504 * ONE_E18)
+        );
+        // A 19% jump between consecutive updates
+        assert!(matches!(
+            pricer.calculate_weighted_basket_price(&symbols, &weights, 310),
+            Err(EngineError::SuspectFeed(_))
+        ));
+    }
+
+    #[test]
+    fn test_deviation_compares_neighbouring_updates_per_symbol() {
+        let feed = CsvReplayFeed::from_csv(
+            "SPY,50000000000,8,100
+             QQQ,40000000000,8,100
+             SPY,60000000000,8,200
+             QQQ,40100000000,8,200
+             SPY,60100000000,8,300
+",
+        )
+        .unwrap();
+        let mut pricer = BasketPricer::new(Box::new(feed)).with_checks(FeedChecks {
+            max_staleness_secs: 60,
+            max_deviation_bps: 100,
+        });
+        let spy = vec!["SPY".to_string()];
+        let qqq = vec!["QQQ".to_string()];
+        let weights = vec![ONE_E18];
+
+        // QQQ's history does not leak into SPY's and the other way round
+        assert!(pricer
+            .calculate_weighted_basket_price(&spy, &weights, 110)
+            .is_ok());
+        assert!(pricer
+            .calculate_weighted_basket_price(&qqq, &weights, 210)
+            .is_ok());
+        // The jump is flagged, and the update after it is judged against it
+        assert!(pricer
+            .calculate_weighted_basket_price(&spy, &weights, 210)
+            .is_err());
+        assert_eq!(
+            pricer.calculate_weighted_basket_price(&spy, &weights, 310),
+            Ok(601 * ONE_E18)
+        );
+
+        // Pricing as of an earlier time checks against the updates around it
+        let mut pricer = BasketPricer::new(Box::new(
+            CsvReplayFeed::from_csv(
+                "SPY,50000000000,8,100
+SPY,60000000000,8,200
+",
+            )
+            .unwrap(),
+        ))
+        .with_checks(FeedChecks {
+            max_staleness_secs: 60,
+            max_deviation_bps: 100,
+        });
+        assert!(pricer
+            .calculate_weighted_basket_price(&spy, &weights, 210)
+            .is_ok());
+        assert!(pricer
+            .calculate_weighted_basket_price(&spy, &weights, 110)
+            .is_err());
+    }
+
+    #[test]
+    fn test_to_1e18_normalises_decimals() {
+        assert_eq!(to_1e18(500_00000000, 8).unwrap(), 500 * ONE_E18);
+        assert_eq!(to_1e18(5 * ONE_E18, 18).unwrap(), 5 * ONE_E18);
+        assert_eq!(
+            to_1e18(5_000_000_000_000_000_000_999, 21).unwrap(),
+            5 * ONE_E18
+        );
+        assert_eq!(to_1e18(1, 255).unwrap(), 0);
+        assert!(to_1e18(i128::MAX, 0).is_err());
+    }
+
+    #[test]
+    fn test_quote_and_bounds_from_feed() {
+        let feed = CsvReplayFeed::from_csv(
+            "SPY,50000000000,8,100\n\
+             QQQ,400000000,6,100\n",
+        )
+        .unwrap();
+        let mut pricer = BasketPricer::new(Box::new(feed));
+        let symbols = vec!["SPY".to_string(), "QQQ".to_string()];
+
+        // 0.6 * 500 + 0.4 * 400
+        let weights = vec![6 * ONE_E18 / 10, 4 * ONE_E18 / 10];
+        let (price, lower, upper) = pricer
+            .quote_and_bounds(&symbols, &weights, 100, 100)
+            .unwrap();
+        assert_eq!(price, 460 * ONE_E18);
+        assert_eq!((lower, upper), (4554 * ONE_E18 / 10, 4646 * ONE_E18 / 10));
+
+        assert!(pricer
+            .calculate_weighted_basket_price(&symbols, &[ONE_E18, -1], 100)
+            .is_err());
+    }
+}
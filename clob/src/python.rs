@@ -0,0 +1,409 @@
+// --------------------- Python Binding ---------------------
+// The production engine as a Python extension module (PyO3), for backtesting
+// strategies against the exact matching logic. Depth comes out as numpy
+// arrays of shape (levels, 2) holding price and quantity.
+
+use crate::{
+    current_timestamp, DepthLevels, EngineError, MarketCommand, MatchingEngine, Order, OrderBook,
+    OutcomeId, Price, Side, Trade, NO, YES,
+};
+use numpy::ndarray::Array2;
+use numpy::{IntoPyArray, PyArray2};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+type PyDepth<'py> = (Bound<'py, PyArray2<f64>>, Bound<'py, PyArray2<f64>>);
+
+fn py_error(e: EngineError) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+fn side_from(side: &str) -> PyResult<Side> {
+    match side {
+        "Buy" | "buy" => Ok(Side::Buy),
+        "Sell" | "sell" => Ok(Side::Sell),
+        other => Err(PyValueError::new_err(format!("unknown side {other}"))),
+    }
+}
+
+fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Buy => "Buy",
+        Side::Sell => "Sell",
+    }
+}
+
+fn levels_array(py: Python<'_>, levels: DepthLevels) -> Bound<'_, PyArray2<f64>> {
+    let flat = levels
+        .iter()
+        .flat_map(|(price, qty)| [price.0, *qty])
+        .collect();
+    Array2::from_shape_vec((levels.len(), 2), flat)
+        .expect("two columns per level")
+        .into_pyarray(py)
+}
+
+fn depth_arrays<'py>(py: Python<'py>, book: &OrderBook, levels: Option<usize>) -> PyDepth<'py> {
+    let (bids, asks) = book.get_order_book_depth(levels.unwrap_or(usize::MAX));
+    (levels_array(py, bids), levels_array(py, asks))
+}
+
+fn py_trades(trades: Vec<Trade>) -> Vec<PyTrade> {
+    trades.into_iter().map(|inner| PyTrade { inner }).collect()
+}
+
+// ----- Order and Trade -----
+#[pyclass(name = "Order")]
+#[derive(Clone)]
+pub struct PyOrder {
+    inner: Order,
+}
+
+#[pymethods]
+impl PyOrder {
+    #[new]
+    #[pyo3(signature = (id, user, side, price, qty, timestamp=None))]
+    fn new(
+        id: u64,
+        user: String,
+        side: &str,
+        price: f64,
+        qty: f64,
+        timestamp: Option<u64>,
+    ) -> PyResult<Self> {
+        Ok(Self {
+            inner: Order {
+                id,
+                user,
+                side: side_from(side)?,
+                price: Price(price),
+                qty,
+                timestamp: timestamp.unwrap_or_else(current_timestamp),
+                iceberg: None,
+            },
+        })
+    }
+
+    #[getter]
+    fn id(&self) -> u64 {
+        self.inner.id
+    }
+
+    #[getter]
+    fn user(&self) -> &str {
+        &self.inner.user
+    }
+
+    #[getter]
+    fn side(&self) -> &'static str {
+        side_name(self.inner.side)
+    }
+
+    #[getter]
+    fn price(&self) -> f64 {
+        self.inner.price.0
+    }
+
+    #[getter]
+    fn qty(&self) -> f64 {
+        self.inner.qty
+    }
+
+    #[getter]
+    fn timestamp(&self) -> u64 {
+        self.inner.timestamp
+    }
+
+    fn __repr__(&self) -> String {
+        let o = &self.inner;
+        format!(
+            "Order(id={}, user='{}', side='{}', price={}, qty={})",
+            o.id,
+            o.user,
+            side_name(o.side),
+            o.price.0,
+            o.qty
+        )
+    }
+}
+
+#[pyclass(name = "Trade", frozen)]
+pub struct PyTrade {
+    inner: Trade,
+}
+
+#[pymethods]
+impl PyTrade {
+    #[getter]
+    fn buyer(&self) -> &str {
+        &self.inner.buyer
+    }
+
+    #[getter]
+    fn seller(&self) -> &str {
+        &self.inner.seller
+    }
+
+    #[getter]
+    fn price(&self) -> f64 {
+        self.inner.price.0
+    }
+
+    #[getter]
+    fn qty(&self) -> f64 {
+        self.inner.qty
+    }
+
+    #[getter]
+    fn market_id(&self) -> &str {
+        &self.inner.market_id
+    }
+
+    #[getter]
+    fn outcome(&self) -> OutcomeId {
+        self.inner.outcome
+    }
+
+    #[getter]
+    fn timestamp(&self) -> u64 {
+        self.inner.timestamp
+    }
+
+    fn __repr__(&self) -> String {
+        let t = &self.inner;
+        format!(
+            "Trade(buyer='{}', seller='{}', price={}, qty={})",
+            t.buyer, t.seller, t.price.0, t.qty
+        )
+    }
+}
+
+// ----- Order book -----
+// A standalone book, or a snapshot of one taken from the engine
+#[pyclass(name = "OrderBook")]
+pub struct PyOrderBook {
+    inner: OrderBook,
+}
+
+#[pymethods]
+impl PyOrderBook {
+    #[new]
+    #[pyo3(signature = (market_id, outcome=YES))]
+    fn new(market_id: &str, outcome: OutcomeId) -> Self {
+        Self {
+            inner: OrderBook::new(market_id, outcome),
+        }
+    }
+
+    // Rest an order without matching; call `match_orders` to cross the book
+    fn add_order(&mut self, order: &PyOrder) {
+        self.inner.add_order(order.inner.clone());
+    }
+
+    fn cancel_order(&mut self, order_id: u64) -> bool {
+        self.inner.cancel_order(order_id)
+    }
+
+    fn match_orders(&mut self) -> Vec<PyTrade> {
+        py_trades(self.inner.match_orders())
+    }
+
+    // (best bid, best ask)
+    fn top_of_book(&self) -> (f64, f64) {
+        let (bid, ask) = self.inner.get_top_of_book();
+        (bid.0, ask.0)
+    }
+
+    #[pyo3(signature = (levels=None))]
+    fn depth<'py>(&self, py: Python<'py>, levels: Option<usize>) -> PyDepth<'py> {
+        depth_arrays(py, &self.inner, levels)
+    }
+}
+
+// ----- Matching engine -----
+#[pyclass(name = "MatchingEngine")]
+pub struct PyMatchingEngine {
+    inner: MatchingEngine,
+}
+
+impl PyMatchingEngine {
+    fn book(&self, market_id: &str, outcome: OutcomeId) -> PyResult<&OrderBook> {
+        self.inner.book(market_id, outcome).ok_or_else(|| {
+            py_error(EngineError::UnknownOutcome {
+                market_id: market_id.to_string(),
+                outcome,
+            })
+        })
+    }
+}
+
+#[pymethods]
+impl PyMatchingEngine {
+    #[new]
+    fn new() -> Self {
+        Self {
+            inner: MatchingEngine::new(),
+        }
+    }
+
+    fn add_admin(&mut self, admin: &str) {
+        self.inner.add_admin(admin);
+    }
+
+    fn create_market(&mut self, market_id: &str) {
+        self.inner.create_market(market_id);
+    }
+
+    // command: "open", "halt", "resume", "close" or "resolve" (with a winner)
+    #[pyo3(signature = (caller, market_id, command, winner=None))]
+    fn market_command(
+        &mut self,
+        caller: &str,
+        market_id: &str,
+        command: &str,
+        winner: Option<OutcomeId>,
+    ) -> PyResult<String> {
+        let command = match (command, winner) {
+            ("open", _) => MarketCommand::Open,
+            ("halt", _) => MarketCommand::Halt,
+            ("resume", _) => MarketCommand::Resume,
+            ("close", _) => MarketCommand::Close,
+            ("resolve", Some(winner)) => MarketCommand::Resolve { winner },
+            _ => return Err(PyValueError::new_err(format!("bad command {command}"))),
+        };
+        self.inner
+            .apply_market_command(caller, market_id, command)
+            .map(|state| format!("{state:?}"))
+            .map_err(py_error)
+    }
+
+    fn place_order(
+        &mut self,
+        market_id: &str,
+        outcome: OutcomeId,
+        order: &PyOrder,
+    ) -> PyResult<Vec<PyTrade>> {
+        self.inner
+            .place_order(market_id, outcome, order.inner.clone())
+            .map(py_trades)
+            .map_err(py_error)
+    }
+
+    fn cancel_order(&mut self, market_id: &str, outcome: OutcomeId, order_id: u64) -> bool {
+        self.inner.cancel_order(market_id, outcome, order_id)
+    }
+
+    // Ids of the cancelled orders
+    #[pyo3(signature = (user, market_id=None))]
+    fn cancel_all(&mut self, user: &str, market_id: Option<&str>) -> Vec<u64> {
+        self.inner
+            .cancel_all(user, market_id)
+            .iter()
+            .map(|o| o.id)
+            .collect()
+    }
+
+    // Engine time in seconds: the clock set by the backtest, or the system clock
+    #[getter]
+    fn now(&self) -> u64 {
+        self.inner.now()
+    }
+
+    // Pin the engine's clock (auctions, halts, sessions), or None for the system clock
+    #[pyo3(signature = (now=None))]
+    fn set_clock(&mut self, now: Option<u64>) {
+        self.inner.clock = now;
+    }
+
+    // Drive the engine's timed work from the backtest's own clock, which
+    // from then on is the engine's clock too
+    fn tick(&mut self, now: u64) -> Vec<PyTrade> {
+        self.inner.clock = Some(now);
+        let trades = self
+            .inner
+            .tick(now)
+            .into_iter()
+            .filter_map(|event| match event {
+                crate::EngineEvent::Trade(trade) => Some(trade),
+                _ => None,
+            })
+            .collect();
+        py_trades(trades)
+    }
+
+    fn top_of_book(&self, market_id: &str, outcome: OutcomeId) -> PyResult<(f64, f64)> {
+        let (bid, ask) = self.book(market_id, outcome)?.get_top_of_book();
+        Ok((bid.0, ask.0))
+    }
+
+    #[pyo3(signature = (market_id, outcome, levels=None))]
+    fn depth<'py>(
+        &self,
+        py: Python<'py>,
+        market_id: &str,
+        outcome: OutcomeId,
+        levels: Option<usize>,
+    ) -> PyResult<PyDepth<'py>> {
+        Ok(depth_arrays(py, self.book(market_id, outcome)?, levels))
+    }
+
+    // A copy of one outcome book, free to experiment on
+    fn snapshot(&self, market_id: &str, outcome: OutcomeId) -> PyResult<PyOrderBook> {
+        Ok(PyOrderBook {
+            inner: self.book(market_id, outcome)?.clone(),
+        })
+    }
+}
+
+#[pymodule(name = "clob")]
+fn py_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyOrder>()?;
+    m.add_class::<PyTrade>()?;
+    m.add_class::<PyOrderBook>()?;
+    m.add_class::<PyMatchingEngine>()?;
+    m.add("YES", YES)?;
+    m.add("NO", NO)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::PyDict;
+
+    #[test]
+    fn test_engine_from_python() {
+        Python::initialize();
+        Python::attach(|py| {
+            let module = PyModule::new(py, "clob").unwrap();
+            py_module(&module).unwrap();
+            let globals = PyDict::new(py);
+            globals.set_item("clob", module).unwrap();
+            py.run(
+                cr#"
+engine = clob.MatchingEngine()
+engine.add_admin("admin")
+engine.create_market("m")
+assert engine.market_command("admin", "m", "open") == "Open"
+engine.place_order("m", clob.YES, clob.Order(1, "maker", "Sell", 0.6, 10.0))
+trades = engine.place_order("m", clob.YES, clob.Order(2, "taker", "Buy", 0.7, 4.0))
+assert [(t.seller, t.price, t.qty) for t in trades] == [("maker", 0.6, 4.0)]
+assert engine.top_of_book("m", clob.YES) == (0.0, 0.6)
+assert engine.cancel_all("maker") == [1]
+try:
+    engine.place_order("m", clob.YES, clob.Order(3, "taker", "Buy", 1.5, 1.0))
+    raise AssertionError("bad price accepted")
+except ValueError:
+    pass
+engine.set_clock(1000)
+assert engine.now == 1000
+engine.tick(1060)
+assert engine.now == 1060
+"#,
+                Some(&globals),
+                None,
+            )
+            .unwrap();
+        });
+    }
+}
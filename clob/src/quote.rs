@@ -0,0 +1,383 @@
+use crate::error::EngineError;
+use crate::market::OutcomeId;
+use crate::{check_price, MatchingEngine, Order, OrderBook, Price, Side, Trade};
+use std::collections::BTreeMap;
+
+// --------------------- Mass Quotes ---------------------
+// Quote orders get engine-assigned ids from the upper half of the id space so
+// they never collide with client ids
+pub const QUOTE_ID_BASE: u64 = 1 << 63;
+
+const QTY_EPSILON: f64 = 1e-9;
+
+// Client orders must keep below the quote id range
+pub(crate) fn check_client_id(id: u64) -> Result<(), EngineError> {
+    if id >= QUOTE_ID_BASE {
+        return Err(EngineError::ReservedOrderId(id));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QuoteLevel {
+    pub price: Price,
+    pub qty: f64,
+}
+
+// Full two-sided ladder a maker wants resting on one book
+#[derive(Clone, Debug, PartialEq)]
+pub struct Quote {
+    pub market_id: String,
+    pub outcome: OutcomeId,
+    pub user: String,
+    pub bids: Vec<QuoteLevel>,
+    pub asks: Vec<QuoteLevel>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QuoteResult {
+    pub added: Vec<u64>,     // new orders for new levels or size increases
+    pub amended: Vec<u64>,   // reduced in place, keeping queue priority
+    pub cancelled: Vec<u64>, // levels no longer quoted, or reduced to nothing
+    pub trades: Vec<Trade>,  // fills of added orders that crossed the book
+}
+
+impl OrderBook {
+    // Shrink `user`'s orders at one level to `target`, taking size from their
+    // newest orders first so the oldest keep their place in the queue
+    fn reduce_level(
+        &mut self,
+        user: &str,
+        side: Side,
+        price: Price,
+        target: f64,
+        result: &mut QuoteResult,
+    ) {
+        let levels = match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        };
+        let Some(orders) = levels.get_mut(&price) else {
+            return;
+        };
+        let mut excess = orders
+            .iter()
+            .filter(|o| o.user == user)
+            .map(Order::total_qty)
+            .sum::<f64>()
+            - target;
+        for order in orders.iter_mut().rev().filter(|o| o.user == user) {
+            if excess <= QTY_EPSILON {
+                break;
+            }
+            let cut = order.total_qty().min(excess);
+            order.reduce_by(cut);
+            excess -= cut;
+            if order.total_qty() <= QTY_EPSILON {
+                result.cancelled.push(order.id);
+            } else {
+                result.amended.push(order.id);
+            }
+        }
+        orders.retain(|o| o.total_qty() > QTY_EPSILON);
+        if orders.is_empty() {
+            levels.remove(&price);
+        }
+    }
+
+    // Resting size of `user` per price on one side, hidden reserve included
+    fn user_levels(&self, user: &str, side: Side) -> BTreeMap<Price, f64> {
+        let levels = match side {
+            Side::Buy => &self.bids,
+            Side::Sell => &self.asks,
+        };
+        let mut sizes = BTreeMap::new();
+        for (price, orders) in levels {
+            let qty: f64 = orders
+                .iter()
+                .filter(|o| o.user == user)
+                .map(Order::total_qty)
+                .sum();
+            if qty > 0.0 {
+                sizes.insert(*price, qty);
+            }
+        }
+        sizes
+    }
+}
+
+impl MatchingEngine {
+    // Replace a maker's ladder on one book. Only the difference to what already
+    // rests is applied: reductions amend in place, increases add a new order
+    // behind the existing size, and unquoted levels are cancelled. The quote
+    // applies in full or not at all.
+    pub fn quote(&mut self, quote: Quote) -> Result<QuoteResult, EngineError> {
+        let levels = quote.bids.iter().chain(&quote.asks);
+        for level in levels.clone() {
+            check_price(level.price)?;
+        }
+        if levels.clone().any(|l| !(l.qty >= 0.0 && l.qty.is_finite())) {
+            return Err(EngineError::InvalidQuote("bad size".to_string()));
+        }
+        let (mut bid_prices, mut ask_prices): (Vec<Price>, Vec<Price>) = (
+            quote.bids.iter().map(|l| l.price).collect(),
+            quote.asks.iter().map(|l| l.price).collect(),
+        );
+        bid_prices.sort();
+        bid_prices.dedup();
+        ask_prices.sort();
+        ask_prices.dedup();
+        if bid_prices.len() != quote.bids.len() || ask_prices.len() != quote.asks.len() {
+            return Err(EngineError::InvalidQuote("price quoted twice".to_string()));
+        }
+        self.ensure_open(&quote.market_id, quote.outcome)?;
+        if self.markets[&quote.market_id].domain.is_some() {
+            return Err(EngineError::SignatureRequired(quote.market_id));
+        }
+
+        // An addition can still fail, e.g. on a circuit breaker: undo the whole
+        // quote then, like a rejected batch. A tripped breaker stays tripped.
+        let market_id = quote.market_id.clone();
+        self.undo_on_error(&market_id, |engine| engine.apply_quote(&quote))
+    }
+
+    fn apply_quote(&mut self, quote: &Quote) -> Result<QuoteResult, EngineError> {
+        let mut result = QuoteResult::default();
+        let mut additions = Vec::new();
+        let book = &self.markets[&quote.market_id].books[&quote.outcome];
+        let resting = [Side::Buy, Side::Sell].map(|side| book.user_levels(&quote.user, side));
+        for (side, levels) in [Side::Buy, Side::Sell].iter().zip(&resting) {
+            for &price in levels.keys() {
+                self.log_level(&quote.market_id, quote.outcome, *side, price);
+            }
+        }
+        let book = self
+            .markets
+            .get_mut(&quote.market_id)
+            .and_then(|m| m.books.get_mut(&quote.outcome))
+            .unwrap();
+
+        let ladders = [(Side::Buy, &quote.bids), (Side::Sell, &quote.asks)];
+        for ((side, ladder), resting) in ladders.into_iter().zip(&resting) {
+            for (&price, &qty) in resting {
+                let target = ladder
+                    .iter()
+                    .find(|l| l.price == price)
+                    .map_or(0.0, |l| l.qty);
+                if target < qty - QTY_EPSILON {
+                    book.reduce_level(&quote.user, side, price, target, &mut result);
+                }
+            }
+            for level in ladder {
+                let current = resting.get(&level.price).copied().unwrap_or(0.0);
+                if level.qty > current + QTY_EPSILON {
+                    additions.push((side, level.price, level.qty - current));
+                }
+            }
+        }
+
+        for (side, price, qty) in additions {
+            self.next_quote_id += 1;
+            let order = Order {
+                id: QUOTE_ID_BASE + self.next_quote_id,
+                user: quote.user.clone(),
+                side,
+                price,
+                qty,
+                timestamp: self.now(),
+                iceberg: None,
+            };
+            result.added.push(order.id);
+            result
+                .trades
+                .extend(self.match_order(&quote.market_id, quote.outcome, order)?);
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_test_order;
+    use crate::{CircuitBreaker, MarketCommand, MarketConfig, MarketState, YES};
+
+    fn level(price: f64, qty: f64) -> QuoteLevel {
+        QuoteLevel {
+            price: Price(price),
+            qty,
+        }
+    }
+
+    fn quote(bids: Vec<QuoteLevel>, asks: Vec<QuoteLevel>) -> Quote {
+        Quote {
+            market_id: "m".to_string(),
+            outcome: YES,
+            user: "mm".to_string(),
+            bids,
+            asks,
+        }
+    }
+
+    #[test]
+    fn test_quote_diffs_against_resting_orders() {
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        engine.create_market("m");
+        engine
+            .apply_market_command("admin", "m", MarketCommand::Open)
+            .unwrap();
+
+        let first = engine
+            .quote(quote(
+                vec![level(0.40, 100.0), level(0.39, 100.0)],
+                vec![level(0.60, 100.0)],
+            ))
+            .unwrap();
+        assert_eq!(first.added.len(), 3);
+        let bid_040 = first.added[0];
+        engine
+            .place_yes_order("m", create_test_order(1, "alice", Side::Buy, 0.40, 10.0))
+            .unwrap();
+
+        // Reduce 0.40, drop 0.39, grow 0.60, add 0.62
+        let second = engine
+            .quote(quote(
+                vec![level(0.40, 60.0)],
+                vec![level(0.60, 150.0), level(0.62, 20.0)],
+            ))
+            .unwrap();
+        assert_eq!(second.amended, vec![bid_040]);
+        assert_eq!(second.cancelled.len(), 1);
+        assert_eq!(second.added.len(), 2);
+
+        // The reduced bid keeps its place ahead of alice
+        let book = engine.book("m", YES).unwrap();
+        let level_040 = &book.bids[&Price(0.40)];
+        assert_eq!((level_040[0].id, level_040[0].qty), (bid_040, 60.0));
+        assert_eq!(level_040[1].user, "alice");
+        assert!(!book.bids.contains_key(&Price(0.39)));
+        let level_060: Vec<f64> = book.asks[&Price(0.60)].iter().map(|o| o.qty).collect();
+        assert_eq!(level_060, vec![100.0, 50.0]);
+
+        // Re-sending the same ladder changes nothing
+        let same = engine
+            .quote(quote(
+                vec![level(0.40, 60.0)],
+                vec![level(0.60, 150.0), level(0.62, 20.0)],
+            ))
+            .unwrap();
+        assert_eq!(same, QuoteResult::default());
+
+        assert!(matches!(
+            engine.quote(quote(vec![level(0.4, 1.0), level(0.4, 2.0)], vec![])),
+            Err(EngineError::InvalidQuote(_))
+        ));
+    }
+
+    #[test]
+    fn test_failed_quote_is_undone() {
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        engine.create_market_with_config(
+            "m",
+            MarketConfig {
+                circuit_breaker: Some(CircuitBreaker {
+                    max_move: 1.0,
+                    window_secs: 60,
+                    band: 0.1,
+                    cool_down_secs: 30,
+                    auction_secs: 10,
+                }),
+                ..Default::default()
+            },
+        );
+        engine
+            .apply_market_command("admin", "m", MarketCommand::Open)
+            .unwrap();
+        // Reference price 0.5, then asks at 0.55 and 0.7
+        engine
+            .place_yes_order("m", create_test_order(1, "alice", Side::Buy, 0.5, 10.0))
+            .unwrap();
+        engine
+            .place_yes_order("m", create_test_order(2, "bob", Side::Sell, 0.5, 10.0))
+            .unwrap();
+        engine
+            .place_yes_order("m", create_test_order(3, "carol", Side::Sell, 0.55, 10.0))
+            .unwrap();
+        engine
+            .place_yes_order("m", create_test_order(4, "carol", Side::Sell, 0.7, 10.0))
+            .unwrap();
+        engine
+            .quote(quote(vec![level(0.40, 50.0)], vec![]))
+            .unwrap();
+
+        // Dropping 0.40 and adding 0.45 go through before the 0.8 bid breaks the band
+        assert_eq!(
+            engine.quote(quote(vec![level(0.45, 10.0), level(0.8, 20.0)], vec![])),
+            Err(EngineError::CircuitBreakerTripped("m".to_string()))
+        );
+        let book = engine.book("m", YES).unwrap();
+        let bids: Vec<(Price, f64)> = book
+            .bids
+            .iter()
+            .map(|(&price, orders)| (price, orders[0].qty))
+            .collect();
+        assert_eq!(bids, vec![(Price(0.40), 50.0)]);
+        assert_eq!(book.asks.len(), 2);
+        assert_eq!(engine.market_state("m"), Some(MarketState::Halted));
+    }
+
+    #[test]
+    fn test_quote_counts_iceberg_reserve() {
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        engine.create_market("m");
+        engine
+            .apply_market_command("admin", "m", MarketCommand::Open)
+            .unwrap();
+        let iceberg = create_test_order(1, "mm", Side::Buy, 0.4, 30.0)
+            .with_iceberg(10.0)
+            .unwrap();
+        engine.place_yes_order("m", iceberg).unwrap();
+
+        // 30 rest in all, so quoting 20 cuts 10 from the reserve
+        let result = engine.quote(quote(vec![level(0.4, 20.0)], vec![])).unwrap();
+        assert_eq!(result.amended, vec![1]);
+        assert!(result.added.is_empty());
+        let order = &engine.book("m", YES).unwrap().bids[&Price(0.4)][0];
+        assert_eq!((order.qty, order.total_qty()), (10.0, 20.0));
+
+        // Quoting 5 leaves less than one slice: the reserve goes, then the slice shrinks
+        let result = engine.quote(quote(vec![level(0.4, 5.0)], vec![])).unwrap();
+        assert_eq!(result.amended, vec![1]);
+        let order = &engine.book("m", YES).unwrap().bids[&Price(0.4)][0];
+        assert_eq!((order.qty, order.total_qty()), (5.0, 5.0));
+    }
+
+    #[test]
+    fn test_client_ids_stay_below_quote_ids() {
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        engine.create_market("m");
+        engine
+            .apply_market_command("admin", "m", MarketCommand::Open)
+            .unwrap();
+        assert_eq!(
+            engine.place_yes_order(
+                "m",
+                create_test_order(QUOTE_ID_BASE + 1, "alice", Side::Buy, 0.4, 1.0)
+            ),
+            Err(EngineError::ReservedOrderId(QUOTE_ID_BASE + 1))
+        );
+        let added = engine
+            .quote(quote(vec![level(0.4, 1.0)], vec![]))
+            .unwrap()
+            .added;
+        assert!(added[0] > QUOTE_ID_BASE);
+        assert!(matches!(
+            engine.quote(quote(vec![level(0.4, f64::NAN)], vec![])),
+            Err(EngineError::InvalidQuote(_))
+        ));
+    }
+}
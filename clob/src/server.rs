@@ -0,0 +1,795 @@
+use crate::ffi::{outcome_label, parse_outcome};
+use crate::{
+    level_changes, BasketPricer, DepthLevels, EngineError, EngineEvent, MarketCommand,
+    MatchingEngine, Order, OrderBook, OutcomeId, Price, Side, Trade,
+};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tower_http::cors::CorsLayer;
+
+// --------------------- HTTP + WebSocket Server ---------------------
+// The REST routes the frontend used to reach through the Node backend, served
+// straight from the engine, plus a `/ws` feed of book deltas and trades.
+
+// Operator identity the server opens markets as
+pub const SERVER_ADMIN: &str = "clob-server";
+
+const DEPTH_LEVELS: usize = 50; // levels per book in REST snapshots
+const FEED_CAPACITY: usize = 1024; // feed messages buffered per slow subscriber
+const ORDER_HISTORY: usize = 10_000; // orders kept once they stop resting
+const TRADE_HISTORY: usize = 10_000; // trades kept for `/api/trades`
+const QTY_EPSILON: f64 = 1e-9;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderRecord {
+    pub id: u64,
+    pub user: String,
+    pub market_id: String,
+    pub market: String, // outcome label, "YES"/"NO" on binary markets
+    pub side: String,   // "Buy" or "Sell"
+    pub price: f64,
+    pub qty: f64,
+    pub filled_qty: f64, // summed from the trades naming this order
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewOrder {
+    pub market_id: String,
+    pub market: String,
+    pub side: String,
+    pub price: f64,
+    pub qty: f64,
+    pub user: String,
+    pub display_qty: Option<f64>, // iceberg: show at most this much of `qty`
+}
+
+// Everything behind the lock: the engine plus the bookkeeping the REST API
+// needs on top of it
+struct Venue {
+    engine: MatchingEngine,
+    orders: BTreeMap<u64, OrderRecord>, // orders placed through the API, oldest first
+    trades: VecDeque<Trade>,            // oldest first
+    next_order_id: u64,
+}
+
+pub struct ServerState {
+    venue: Mutex<Venue>,
+    // Settles band markets. Kept apart from the engine so feed calls never
+    // hold up trading.
+    pricer: Mutex<Option<BasketPricer>>,
+    admin_token: Option<String>, // bearer token for creating markets; none disables it
+    feed: broadcast::Sender<String>, // JSON messages for `/ws` subscribers
+}
+
+// Book levels an action may change: per market and outcome, the bid and ask
+// prices it can reach
+type Scope = Vec<(
+    (String, OutcomeId),
+    RangeInclusive<Price>,
+    RangeInclusive<Price>,
+)>;
+
+// (bids, asks) within a scope, keyed by market and outcome
+type Depth = BTreeMap<(String, OutcomeId), (DepthLevels, DepthLevels)>;
+
+const EVERY_PRICE: RangeInclusive<Price> = RangeInclusive::new(Price(0.0), Price(1.0));
+
+impl ServerState {
+    pub fn new(mut engine: MatchingEngine, admin_token: Option<String>) -> Arc<Self> {
+        engine.add_admin(SERVER_ADMIN);
+        let pricer = engine.pricer.take();
+        let (feed, _) = broadcast::channel(FEED_CAPACITY);
+        Arc::new(Self {
+            venue: Mutex::new(Venue {
+                engine,
+                orders: BTreeMap::new(),
+                trades: VecDeque::new(),
+                next_order_id: 1,
+            }),
+            pricer: Mutex::new(pricer),
+            admin_token,
+            feed,
+        })
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<String> {
+        self.feed.subscribe()
+    }
+
+    // Create a market, open for trading; one that exists is left as it is
+    pub fn open_market(&self, market_id: &str) -> Result<(), EngineError> {
+        let engine = &mut self.venue.lock().unwrap().engine;
+        if !engine.markets.contains_key(market_id) {
+            engine.create_market(market_id);
+            engine.apply_market_command(SERVER_ADMIN, market_id, MarketCommand::Open)?;
+        }
+        Ok(())
+    }
+
+    // Run the engine's timed work (auctions, batches, session expiry,
+    // settlement) and publish whatever it changed
+    pub fn tick(&self, now: u64) {
+        let settling = {
+            let mut venue = self.venue.lock().unwrap();
+            let scope = tick_scope(&venue.engine, now);
+            let before = depth(&venue.engine, &scope);
+            let events = venue.engine.tick(now);
+            self.publish(&mut venue, before, &scope, trades(events));
+            venue.engine.settling_band_markets(now)
+        };
+        if settling.is_empty() {
+            return;
+        }
+
+        // The feed is asked without the venue locked; the engine checks again
+        // that each market can still settle when the prices come back
+        let prices: Vec<_> = match self.pricer.lock().unwrap().as_mut() {
+            Some(pricer) => settling
+                .into_iter()
+                .map(|(market_id, band)| {
+                    let price = pricer.calculate_weighted_basket_price(
+                        &band.symbols,
+                        &band.weights_1e18,
+                        band.settle_ts,
+                    );
+                    (market_id, price)
+                })
+                .collect(),
+            None => return,
+        };
+        let engine = &mut self.venue.lock().unwrap().engine;
+        for (market_id, price) in prices {
+            let result = price.and_then(|price| engine.settle_band_market(&market_id, price));
+            engine.record_settlement(market_id, result);
+        }
+    }
+
+    // Record trades, then send them and the changed book levels to subscribers
+    fn publish(&self, venue: &mut Venue, before: Depth, scope: &Scope, trades: Vec<Trade>) {
+        for trade in &trades {
+            let _ = self
+                .feed
+                .send(json!({ "type": "trade", "trade": trade_json(trade) }).to_string());
+            for order_id in [trade.buy_order_id, trade.sell_order_id] {
+                if let Some(order) = venue
+                    .orders
+                    .get_mut(&order_id)
+                    .filter(|o| o.market_id == trade.market_id)
+                {
+                    order.filled_qty += trade.qty;
+                }
+            }
+        }
+        venue.trades.extend(trades);
+
+        let after = depth(&venue.engine, scope);
+        for (key, (bids, asks)) in &after {
+            let (old_bids, old_asks) = before.get(key).cloned().unwrap_or_default();
+            let bids = level_changes(&old_bids, bids);
+            let asks = level_changes(&old_asks, asks);
+            if bids.is_empty() && asks.is_empty() {
+                continue;
+            }
+            let message = json!({
+                "type": "book",
+                "marketId": key.0,
+                "market": outcome_label(key.1),
+                "bids": levels_json(&bids),
+                "asks": levels_json(&asks),
+            });
+            let _ = self.feed.send(message.to_string());
+        }
+        venue.prune();
+    }
+}
+
+impl Venue {
+    // Forget the oldest trades and the oldest orders that stopped resting
+    // once the history is full
+    fn prune(&mut self) {
+        let excess = self.trades.len().saturating_sub(TRADE_HISTORY);
+        self.trades.drain(..excess);
+
+        let excess = self.orders.len().saturating_sub(ORDER_HISTORY);
+        if excess == 0 {
+            return;
+        }
+        let finished: Vec<u64> = self
+            .orders
+            .values()
+            .filter(|o| remaining(&self.engine, o).is_none())
+            .map(|o| o.id)
+            .take(excess)
+            .collect();
+        for id in finished {
+            self.orders.remove(&id);
+        }
+    }
+}
+
+// The levels within a scope, however deep in the book, so a level that
+// leaves the top of the book is not mistaken for an emptied one
+fn depth(engine: &MatchingEngine, scope: &Scope) -> Depth {
+    scope
+        .iter()
+        .filter_map(|(key, bids, asks)| {
+            let book = engine.book(&key.0, key.1)?;
+            let within = |levels: &BTreeMap<Price, Vec<Order>>, prices: &RangeInclusive<Price>| {
+                levels
+                    .range(prices.clone())
+                    .map(|(&price, orders)| (price, orders.iter().map(|o| o.qty).sum()))
+                    .collect()
+            };
+            Some((
+                key.clone(),
+                (within(&book.bids, bids), within(&book.asks, asks)),
+            ))
+        })
+        .collect()
+}
+
+// A new order changes its own level and the opposite levels it can trade with
+fn order_scope(market_id: &str, outcome: OutcomeId, side: Side, price: Price) -> Scope {
+    let (bids, asks) = match side {
+        Side::Buy => (price..=price, Price(0.0)..=price),
+        Side::Sell => (price..=Price(1.0), price..=price),
+    };
+    vec![((market_id.to_string(), outcome), bids, asks)]
+}
+
+// Whole books of the markets whose auction, batch or trading end is due, or
+// of every market once a session lapses and takes its quotes with it. Empty
+// books have nothing for the tick to change.
+fn tick_scope(engine: &MatchingEngine, now: u64) -> Scope {
+    let lapsing = engine.sessions.values().any(|s| s.expired(now));
+    engine
+        .markets
+        .values()
+        .filter(|m| lapsing || m.auction_due(now) || m.batch_due(now) || m.trading_ended(now))
+        .flat_map(|m| m.books.values())
+        .filter(|book| !book.bids.is_empty() || !book.asks.is_empty())
+        .map(|book| {
+            let key = (book.market_id.clone(), book.outcome);
+            (key, EVERY_PRICE, EVERY_PRICE)
+        })
+        .collect()
+}
+
+fn trades(events: Vec<EngineEvent>) -> Vec<Trade> {
+    events
+        .into_iter()
+        .filter_map(|event| match event {
+            EngineEvent::Trade(trade) => Some(trade),
+            _ => None,
+        })
+        .collect()
+}
+
+fn levels_json(levels: &DepthLevels) -> Vec<Value> {
+    levels
+        .iter()
+        .map(|(price, qty)| json!({ "price": price.0, "totalQty": qty }))
+        .collect()
+}
+
+fn trade_json(trade: &Trade) -> Value {
+    json!({
+        "buyer": trade.buyer,
+        "seller": trade.seller,
+        "qty": trade.qty,
+        "price": trade.price.0,
+        "marketId": trade.market_id,
+        "market": outcome_label(trade.outcome),
+        "timestamp": trade.timestamp,
+    })
+}
+
+fn resting(book: &OrderBook, order_id: u64) -> Option<&Order> {
+    book.bids
+        .values()
+        .chain(book.asks.values())
+        .flatten()
+        .find(|o| o.id == order_id)
+}
+
+// What is still resting of an order; none once it left the book
+fn remaining(engine: &MatchingEngine, order: &OrderRecord) -> Option<f64> {
+    parse_outcome(&order.market)
+        .and_then(|outcome| engine.book(&order.market_id, outcome))
+        .and_then(|book| resting(book, order.id))
+        .map(Order::total_qty)
+}
+
+// ----- Errors -----
+#[derive(Debug)]
+pub struct ApiError(StatusCode, String);
+
+impl From<EngineError> for ApiError {
+    fn from(error: EngineError) -> Self {
+        let status = match error {
+            EngineError::MarketNotFound(_) => StatusCode::NOT_FOUND,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        ApiError(status, error.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+fn bad_request(message: &str) -> ApiError {
+    ApiError(StatusCode::BAD_REQUEST, message.to_string())
+}
+
+fn not_found(message: &str) -> ApiError {
+    ApiError(StatusCode::NOT_FOUND, message.to_string())
+}
+
+type ApiResult = Result<Json<Value>, ApiError>;
+
+// Compares every byte, so the time taken does not tell how much matched
+fn same_token(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+// ----- Routes -----
+pub fn router(state: Arc<ServerState>) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/api/markets", get(list_markets).post(create_market))
+        .route("/api/markets/{market_id}/orderbook", get(order_book))
+        .route("/api/orders", post(place_order))
+        .route("/api/orders/{order_id}", delete(cancel_order))
+        .route("/api/users/{account}/orders", get(user_orders))
+        .route("/api/trades", get(recent_trades))
+        .route("/ws", get(feed))
+        .layer(CorsLayer::permissive())
+        .with_state(state)
+}
+
+async fn health() -> Json<Value> {
+    Json(json!({
+        "status": "OK",
+        "timestamp": crate::current_timestamp(),
+        "clobEngine": "Rust",
+    }))
+}
+
+async fn list_markets(State(state): State<Arc<ServerState>>) -> Json<Value> {
+    let venue = state.venue.lock().unwrap();
+    let markets: Vec<Value> = venue
+        .engine
+        .markets
+        .values()
+        .map(|m| {
+            json!({
+                "id": m.id,
+                "state": format!("{:?}", m.state),
+                "outcomes": m.outcomes.iter().map(|&o| outcome_label(o)).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+    Json(Value::Array(markets))
+}
+
+// Operators only: `Authorization: Bearer <admin token>`
+async fn create_market(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> ApiResult {
+    let Some(token) = &state.admin_token else {
+        return Err(ApiError(
+            StatusCode::FORBIDDEN,
+            "Market creation is disabled".to_string(),
+        ));
+    };
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if !bearer.is_some_and(|bearer| same_token(bearer, token)) {
+        return Err(ApiError(
+            StatusCode::UNAUTHORIZED,
+            "Admin token required".to_string(),
+        ));
+    }
+    let market_id = body["marketId"]
+        .as_str()
+        .filter(|id| !id.is_empty())
+        .ok_or_else(|| bad_request("Market ID is required"))?;
+    state.open_market(market_id)?;
+    Ok(Json(json!({
+        "message": "Market created successfully",
+        "marketId": market_id,
+    })))
+}
+
+async fn order_book(
+    State(state): State<Arc<ServerState>>,
+    Path(market_id): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+) -> ApiResult {
+    let market = query.get("market").map_or("YES", String::as_str);
+    let outcome = parse_outcome(market).ok_or_else(|| bad_request("Unknown market"))?;
+    let venue = state.venue.lock().unwrap();
+    let book = venue
+        .engine
+        .book(&market_id, outcome)
+        .ok_or_else(|| not_found("Market not found"))?;
+    let (bids, asks) = book.get_order_book_depth(DEPTH_LEVELS);
+    Ok(Json(json!({
+        "marketId": market_id,
+        "market": market,
+        "bids": levels_json(&bids),
+        "asks": levels_json(&asks),
+        "timestamp": crate::current_timestamp(),
+    })))
+}
+
+async fn place_order(
+    State(state): State<Arc<ServerState>>,
+    Json(new): Json<NewOrder>,
+) -> ApiResult {
+    if !(0.0..=1.0).contains(&new.price) || new.qty <= 0.0 {
+        return Err(bad_request("Invalid price or quantity"));
+    }
+    let outcome = parse_outcome(&new.market).ok_or_else(|| bad_request("Unknown market"))?;
+    let side = match new.side.as_str() {
+        "Buy" => Side::Buy,
+        "Sell" => Side::Sell,
+        _ => return Err(bad_request("Side must be Buy or Sell")),
+    };
+
+    let mut venue = state.venue.lock().unwrap();
+    let id = venue.next_order_id;
+    let record = OrderRecord {
+        id,
+        user: new.user,
+        market_id: new.market_id,
+        market: new.market,
+        side: new.side,
+        price: new.price,
+        qty: new.qty,
+        filled_qty: 0.0,
+        timestamp: crate::current_timestamp(),
+    };
+    let order = Order {
+        id,
+        user: record.user.clone(),
+        side,
+        price: Price(record.price),
+        qty: record.qty,
+        timestamp: record.timestamp,
+        iceberg: None,
+    };
+    let order = match new.display_qty {
+        Some(display_qty) => order.with_iceberg(display_qty)?,
+        None => order,
+    };
+
+    let scope = order_scope(&record.market_id, outcome, side, order.price);
+    let before = depth(&venue.engine, &scope);
+    let trades = venue
+        .engine
+        .place_order(&record.market_id, outcome, order)?;
+    venue.next_order_id += 1;
+    let trades_json: Vec<Value> = trades.iter().map(trade_json).collect();
+    venue.orders.insert(id, record);
+    state.publish(&mut venue, before, &scope, trades);
+
+    Ok(Json(json!({
+        "message": "Order placed successfully",
+        "orderId": id,
+        "trades": trades_json,
+    })))
+}
+
+async fn cancel_order(
+    State(state): State<Arc<ServerState>>,
+    Path(order_id): Path<u64>,
+) -> ApiResult {
+    let mut venue = state.venue.lock().unwrap();
+    let record = venue
+        .orders
+        .get(&order_id)
+        .cloned()
+        .ok_or_else(|| not_found("Order not found"))?;
+    let outcome = parse_outcome(&record.market).unwrap_or_default();
+
+    // Only the order's own level changes
+    let price = Price(record.price);
+    let scope = vec![(
+        (record.market_id.clone(), outcome),
+        price..=price,
+        price..=price,
+    )];
+    let before = depth(&venue.engine, &scope);
+    if !venue
+        .engine
+        .cancel_order(&record.market_id, outcome, order_id)
+    {
+        return Err(bad_request("Order is no longer resting"));
+    }
+    state.publish(&mut venue, before, &scope, Vec::new());
+    Ok(Json(json!({ "message": "Order cancelled successfully" })))
+}
+
+// Orders an account placed, with what is still resting of each. An order
+// that left the book without trading in full was cancelled, whether by the
+// user, a mass cancel, session expiry or the market closing.
+async fn user_orders(
+    State(state): State<Arc<ServerState>>,
+    Path(account): Path<String>,
+) -> Json<Value> {
+    let venue = state.venue.lock().unwrap();
+    let orders: Vec<Value> = venue
+        .orders
+        .values()
+        .filter(|o| o.user == account)
+        .map(|o| {
+            let remaining = remaining(&venue.engine, o);
+            let mut order = serde_json::to_value(o).unwrap();
+            order["status"] = json!(match remaining {
+                Some(_) => "active",
+                None if o.filled_qty >= o.qty - QTY_EPSILON => "filled",
+                None => "cancelled",
+            });
+            order["remainingQty"] = json!(remaining.unwrap_or(0.0));
+            order
+        })
+        .collect();
+    Json(Value::Array(orders))
+}
+
+// Most recent trades first, optionally filtered by market and outcome
+async fn recent_trades(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Json<Value> {
+    let limit = query
+        .get("limit")
+        .and_then(|l| l.parse().ok())
+        .unwrap_or(100);
+    let outcome = query.get("market").and_then(|m| parse_outcome(m));
+    let venue = state.venue.lock().unwrap();
+    let trades: Vec<Value> = venue
+        .trades
+        .iter()
+        .rev()
+        .filter(|t| query.get("marketId").is_none_or(|id| &t.market_id == id))
+        .filter(|t| outcome.is_none_or(|o| t.outcome == o))
+        .take(limit)
+        .map(trade_json)
+        .collect();
+    Json(Value::Array(trades))
+}
+
+async fn feed(ws: WebSocketUpgrade, State(state): State<Arc<ServerState>>) -> Response {
+    let messages = state.subscribe();
+    ws.on_upgrade(move |socket| forward(socket, messages))
+}
+
+// A subscriber that falls behind has missed deltas, so it is disconnected and
+// has to resync from the REST snapshot
+async fn forward(mut socket: WebSocket, mut messages: broadcast::Receiver<String>) {
+    while let Ok(message) = messages.recv().await {
+        if socket.send(Message::Text(message.into())).await.is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{band, BandMarket, CsvReplayFeed, MarketConfig, MarketState, WITHIN};
+
+    fn new_order(user: &str, side: &str, price: f64, qty: f64) -> NewOrder {
+        NewOrder {
+            market_id: "m".to_string(),
+            market: "YES".to_string(),
+            side: side.to_string(),
+            price,
+            qty,
+            user: user.to_string(),
+            display_qty: None,
+        }
+    }
+
+    async fn open_market(state: &Arc<ServerState>) {
+        state.open_market("m").unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_orders_trade_and_publish() {
+        let state = ServerState::new(MatchingEngine::new(), None);
+        let mut feed = state.subscribe();
+
+        // Markets have to be created before they take orders
+        let unknown = place_order(
+            State(state.clone()),
+            Json(new_order("alice", "Sell", 0.6, 10.0)),
+        )
+        .await;
+        assert!(matches!(unknown, Err(ApiError(StatusCode::NOT_FOUND, _))));
+        open_market(&state).await;
+
+        let Json(placed) = place_order(
+            State(state.clone()),
+            Json(new_order("alice", "Sell", 0.6, 10.0)),
+        )
+        .await
+        .unwrap();
+        let ask_id = placed["orderId"].as_u64().unwrap();
+        let book: Value = feed.recv().await.unwrap().parse().unwrap();
+        assert_eq!(book["type"], "book");
+        assert_eq!(book["asks"], json!([{ "price": 0.6, "totalQty": 10.0 }]));
+
+        let Json(placed) = place_order(
+            State(state.clone()),
+            Json(new_order("bob", "Buy", 0.6, 4.0)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(placed["trades"][0]["seller"], "alice");
+        let trade: Value = feed.recv().await.unwrap().parse().unwrap();
+        assert_eq!(trade["trade"]["qty"], 4.0);
+        let book: Value = feed.recv().await.unwrap().parse().unwrap();
+        assert_eq!(book["asks"], json!([{ "price": 0.6, "totalQty": 6.0 }]));
+
+        let Json(orders) = user_orders(State(state.clone()), Path("alice".to_string())).await;
+        assert_eq!(orders[0]["status"], "active");
+        assert_eq!(orders[0]["remainingQty"], 6.0);
+
+        let Json(cancelled) = cancel_order(State(state.clone()), Path(ask_id))
+            .await
+            .unwrap();
+        assert_eq!(cancelled["message"], "Order cancelled successfully");
+        assert!(cancel_order(State(state.clone()), Path(ask_id))
+            .await
+            .is_err());
+        let book: Value = feed.recv().await.unwrap().parse().unwrap();
+        assert_eq!(book["asks"], json!([{ "price": 0.6, "totalQty": 0.0 }]));
+    }
+
+    #[tokio::test]
+    async fn test_order_status_follows_trades() {
+        let state = ServerState::new(MatchingEngine::new(), None);
+        open_market(&state).await;
+        for order in [
+            new_order("alice", "Sell", 0.6, 10.0),
+            new_order("bob", "Buy", 0.6, 4.0),
+        ] {
+            let _ = place_order(State(state.clone()), Json(order))
+                .await
+                .unwrap();
+        }
+
+        // Closing the market pulls alice's rest: cancelled, not filled
+        state
+            .venue
+            .lock()
+            .unwrap()
+            .engine
+            .apply_market_command(SERVER_ADMIN, "m", MarketCommand::Close)
+            .unwrap();
+        let Json(orders) = user_orders(State(state.clone()), Path("alice".to_string())).await;
+        assert_eq!(orders[0]["status"], "cancelled");
+        assert_eq!(orders[0]["filledQty"], 4.0);
+        let Json(orders) = user_orders(State(state.clone()), Path("bob".to_string())).await;
+        assert_eq!(orders[0]["status"], "filled");
+    }
+
+    #[tokio::test]
+    async fn test_deltas_cover_levels_beyond_the_snapshot() {
+        let state = ServerState::new(MatchingEngine::new(), None);
+        open_market(&state).await;
+        for i in 1..=DEPTH_LEVELS {
+            let order = new_order("alice", "Buy", i as f64 / 100.0, 1.0);
+            let _ = place_order(State(state.clone()), Json(order))
+                .await
+                .unwrap();
+        }
+
+        // A better bid pushes 0.01 out of the top levels, but it still rests
+        let mut feed = state.subscribe();
+        let order = new_order("alice", "Buy", 0.9, 1.0);
+        let _ = place_order(State(state.clone()), Json(order))
+            .await
+            .unwrap();
+        let book: Value = feed.recv().await.unwrap().parse().unwrap();
+        assert_eq!(book["bids"], json!([{ "price": 0.9, "totalQty": 1.0 }]));
+    }
+
+    #[tokio::test]
+    async fn test_market_creation_needs_admin_token() {
+        let create = |state: &Arc<ServerState>, bearer: Option<&str>| {
+            let mut headers = HeaderMap::new();
+            if let Some(bearer) = bearer {
+                let value = format!("Bearer {}", bearer).parse().unwrap();
+                headers.insert(header::AUTHORIZATION, value);
+            }
+            create_market(
+                State(state.clone()),
+                headers,
+                Json(json!({ "marketId": "m" })),
+            )
+        };
+
+        let state = ServerState::new(MatchingEngine::new(), None);
+        let disabled = create(&state, Some("secret")).await;
+        assert!(matches!(disabled, Err(ApiError(StatusCode::FORBIDDEN, _))));
+
+        let state = ServerState::new(MatchingEngine::new(), Some("secret".to_string()));
+        let anonymous = create(&state, None).await;
+        assert!(matches!(
+            anonymous,
+            Err(ApiError(StatusCode::UNAUTHORIZED, _))
+        ));
+        let wrong = create(&state, Some("secreT")).await;
+        assert!(matches!(wrong, Err(ApiError(StatusCode::UNAUTHORIZED, _))));
+        assert!(create(&state, Some("secret")).await.is_ok());
+        assert!(state.venue.lock().unwrap().engine.markets.contains_key("m"));
+    }
+
+    #[test]
+    fn test_tick_settles_band_markets() {
+        let mut engine = MatchingEngine::new();
+        let band = BandMarket::new(
+            vec!["SPY".to_string()],
+            vec![band::ONE_E18],
+            400 * band::ONE_E18,
+            500,
+            2_000,
+        )
+        .unwrap();
+        engine
+            .create_band_market("0xmarket", band, MarketConfig::default())
+            .unwrap();
+        let feed = CsvReplayFeed::from_csv(
+            "SPY,41000000000,8,1990
+",
+        )
+        .unwrap();
+        engine.set_price_feed(Box::new(feed));
+        let state = ServerState::new(engine, None);
+        state
+            .venue
+            .lock()
+            .unwrap()
+            .engine
+            .apply_market_command(SERVER_ADMIN, "0xmarket", MarketCommand::Open)
+            .unwrap();
+
+        // Closes at the settle time, then settles from the pricer the server holds
+        state.tick(2_000);
+        let venue = state.venue.lock().unwrap();
+        assert_eq!(
+            venue.engine.market_state("0xmarket"),
+            Some(MarketState::Resolved)
+        );
+        assert_eq!(venue.engine.payout("0xmarket", WITHIN), Some(1.0));
+    }
+}
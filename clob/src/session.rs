@@ -0,0 +1,319 @@
+use crate::error::EngineError;
+use crate::market::OutcomeId;
+use crate::quote::{Quote, QuoteResult};
+use crate::signing::same_user;
+use crate::{EngineEvent, MatchingEngine, Order, Trade};
+
+// --------------------- Sessions ---------------------
+pub type SessionId = u64;
+
+// Dead-man's switch: orders placed through a session are cancelled once its
+// heartbeat lapses for longer than `timeout_secs`. Sessions cover orders from
+// `place_session_order` and `session_quote` only: stop orders wait off the
+// book until triggered and stay untouched, and cancels need no session.
+#[derive(Clone, Debug)]
+pub struct Session {
+    pub user: String,
+    pub timeout_secs: u64,
+    pub last_heartbeat: u64,
+    orders: Vec<(String, OutcomeId, u64)>, // (market_id, outcome, order id) placed through this session
+}
+
+impl Session {
+    pub fn expired(&self, now: u64) -> bool {
+        now > self.last_heartbeat + self.timeout_secs
+    }
+}
+
+impl MatchingEngine {
+    pub fn open_session(&mut self, user: &str, timeout_secs: u64) -> SessionId {
+        let now = self.now();
+        self.next_session += 1;
+        self.sessions.insert(
+            self.next_session,
+            Session {
+                user: user.to_string(),
+                timeout_secs,
+                last_heartbeat: now,
+                orders: Vec::new(),
+            },
+        );
+        self.next_session
+    }
+
+    // Keep the session alive and forget orders that have since filled or been cancelled
+    pub fn heartbeat(&mut self, session_id: SessionId) -> Result<(), EngineError> {
+        let now = self.now();
+        let session = self
+            .sessions
+            .get_mut(&session_id)
+            .ok_or(EngineError::SessionNotFound(session_id))?;
+        session.last_heartbeat = now;
+        let markets = &self.markets;
+        let user = &session.user;
+        // Order ids are client-chosen, so another user's order may share one
+        session.orders.retain(|(market_id, outcome, id)| {
+            markets
+                .get(market_id)
+                .and_then(|m| m.books.get(outcome))
+                .is_some_and(|book| {
+                    book.bids
+                        .values()
+                        .chain(book.asks.values())
+                        .flatten()
+                        .any(|o| o.id == *id && &o.user == user)
+                })
+        });
+        Ok(())
+    }
+
+    pub fn place_session_order(
+        &mut self,
+        session_id: SessionId,
+        market_id: &str,
+        outcome: OutcomeId,
+        order: Order,
+    ) -> Result<Vec<Trade>, EngineError> {
+        self.check_session_user(session_id, &order.user)?;
+        let order_id = order.id;
+        let trades = self.place_order(market_id, outcome, order)?;
+        let session = self.sessions.get_mut(&session_id).unwrap();
+        session
+            .orders
+            .push((market_id.to_string(), outcome, order_id));
+        Ok(trades)
+    }
+
+    // Quote through a session: the orders the quote adds go with it
+    pub fn session_quote(
+        &mut self,
+        session_id: SessionId,
+        quote: Quote,
+    ) -> Result<QuoteResult, EngineError> {
+        self.check_session_user(session_id, &quote.user)?;
+        let (market_id, outcome) = (quote.market_id.clone(), quote.outcome);
+        let result = self.quote(quote)?;
+        let session = self.sessions.get_mut(&session_id).unwrap();
+        session.orders.extend(
+            result
+                .added
+                .iter()
+                .map(|&id| (market_id.clone(), outcome, id)),
+        );
+        Ok(result)
+    }
+
+    fn check_session_user(&self, session_id: SessionId, user: &str) -> Result<(), EngineError> {
+        let session = self
+            .sessions
+            .get(&session_id)
+            .ok_or(EngineError::SessionNotFound(session_id))?;
+        if !same_user(&session.user, user) {
+            return Err(EngineError::SessionUserMismatch {
+                session: session_id,
+                user: user.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    // End a session on purpose, cancelling its orders
+    pub fn close_session(&mut self, session_id: SessionId) -> Vec<Order> {
+        let Some(session) = self.sessions.remove(&session_id) else {
+            return Vec::new();
+        };
+        let mut cancelled = Vec::new();
+        for (market_id, outcome, id) in session.orders {
+            if let Some(book) = self
+                .markets
+                .get_mut(&market_id)
+                .and_then(|m| m.books.get_mut(&outcome))
+            {
+                cancelled.extend(book.cancel_where(|o| o.id == id && o.user == session.user));
+            }
+        }
+        cancelled
+    }
+
+    pub(crate) fn expire_sessions(&mut self, now: u64) -> Vec<EngineEvent> {
+        let mut lapsed: Vec<SessionId> = self
+            .sessions
+            .iter()
+            .filter(|(_, session)| session.expired(now))
+            .map(|(&id, _)| id)
+            .collect();
+        lapsed.sort();
+        lapsed
+            .into_iter()
+            .map(|session| {
+                let cancelled = self.close_session(session);
+                EngineEvent::SessionExpired {
+                    session,
+                    cancelled: cancelled.iter().map(|o| o.id).collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::create_test_order;
+    use crate::quote::{Quote, QuoteLevel};
+    use crate::{
+        current_timestamp, EngineError, EngineEvent, MarketCommand, MatchingEngine, Price, Side,
+        YES,
+    };
+
+    #[test]
+    fn test_lapsed_session_cancels_its_orders() {
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        engine.create_market("m");
+        engine
+            .apply_market_command("admin", "m", MarketCommand::Open)
+            .unwrap();
+
+        let session = engine.open_session("bot", 5);
+        engine
+            .place_session_order(
+                session,
+                "m",
+                YES,
+                create_test_order(1, "bot", Side::Buy, 0.4, 10.0),
+            )
+            .unwrap();
+        engine
+            .place_session_order(
+                session,
+                "m",
+                YES,
+                create_test_order(2, "bot", Side::Sell, 0.6, 10.0),
+            )
+            .unwrap();
+        engine
+            .place_yes_order("m", create_test_order(3, "bot", Side::Buy, 0.3, 10.0))
+            .unwrap();
+        assert_eq!(
+            engine.place_session_order(
+                session,
+                "m",
+                YES,
+                create_test_order(4, "alice", Side::Buy, 0.3, 10.0)
+            ),
+            Err(EngineError::SessionUserMismatch {
+                session,
+                user: "alice".to_string()
+            })
+        );
+
+        // Order 2 fills before the lapse
+        engine
+            .place_yes_order("m", create_test_order(5, "alice", Side::Buy, 0.6, 10.0))
+            .unwrap();
+        engine.heartbeat(session).unwrap();
+        assert!(engine.tick(current_timestamp() + 1).is_empty());
+
+        let events = engine.tick(current_timestamp() + 10);
+        assert_eq!(
+            events,
+            vec![EngineEvent::SessionExpired {
+                session,
+                cancelled: vec![1]
+            }]
+        );
+        // Orders placed outside the session stay
+        assert_eq!(engine.get_top_of_book("m", YES).0, Price(0.3));
+        assert_eq!(
+            engine.heartbeat(session),
+            Err(EngineError::SessionNotFound(session))
+        );
+    }
+
+    #[test]
+    fn test_session_leaves_other_users_orders_with_the_same_id() {
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        engine.create_market("m");
+        engine
+            .apply_market_command("admin", "m", MarketCommand::Open)
+            .unwrap();
+
+        let session = engine.open_session("bot", 5);
+        engine
+            .place_session_order(
+                session,
+                "m",
+                YES,
+                create_test_order(1, "bot", Side::Buy, 0.4, 10.0),
+            )
+            .unwrap();
+        engine
+            .place_yes_order("m", create_test_order(1, "alice", Side::Buy, 0.3, 10.0))
+            .unwrap();
+
+        // Once the bot's order fills, alice's order must not keep it tracked
+        engine
+            .place_yes_order("m", create_test_order(2, "carol", Side::Sell, 0.4, 10.0))
+            .unwrap();
+        engine.heartbeat(session).unwrap();
+        assert!(engine.sessions[&session].orders.is_empty());
+
+        engine
+            .place_session_order(
+                session,
+                "m",
+                YES,
+                create_test_order(3, "bot", Side::Buy, 0.2, 10.0),
+            )
+            .unwrap();
+        engine
+            .place_yes_order("m", create_test_order(3, "alice", Side::Buy, 0.25, 10.0))
+            .unwrap();
+        let cancelled = engine.close_session(session);
+        assert_eq!(cancelled.len(), 1);
+        assert_eq!(cancelled[0].user, "bot");
+        let bids = &engine.book("m", YES).unwrap().bids;
+        assert_eq!(bids[&Price(0.3)][0].user, "alice");
+        assert_eq!(bids[&Price(0.25)][0].user, "alice");
+        assert!(!bids.contains_key(&Price(0.2)));
+    }
+
+    #[test]
+    fn test_lapsed_session_pulls_its_quotes() {
+        let mut engine = MatchingEngine::new();
+        engine.clock = Some(1_000);
+        engine.add_admin("admin");
+        engine.create_market("m");
+        engine
+            .apply_market_command("admin", "m", MarketCommand::Open)
+            .unwrap();
+
+        let session = engine.open_session("bot", 5);
+        let quote = |user: &str| Quote {
+            market_id: "m".to_string(),
+            outcome: YES,
+            user: user.to_string(),
+            bids: vec![QuoteLevel {
+                price: Price(0.4),
+                qty: 10.0,
+            }],
+            asks: vec![],
+        };
+        assert!(matches!(
+            engine.session_quote(session, quote("alice")),
+            Err(EngineError::SessionUserMismatch { .. })
+        ));
+        let added = engine.session_quote(session, quote("bot")).unwrap().added;
+
+        let events = engine.tick(1_010);
+        assert_eq!(
+            events,
+            vec![EngineEvent::SessionExpired {
+                session,
+                cancelled: added
+            }]
+        );
+        assert!(engine.book("m", YES).unwrap().bids.is_empty());
+    }
+}
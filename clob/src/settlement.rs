@@ -0,0 +1,277 @@
+use crate::abi::{address_word, encode_call, word, Address, Word};
+use crate::market::OutcomeId;
+use crate::signing::parse_address;
+use crate::Trade;
+use std::collections::BTreeMap;
+
+// --------------------- On-chain Settlement ---------------------
+pub const TRANSFER_CLAIMS: &str = "transferClaims(address,address,uint256,uint256)";
+pub const TRANSFER_COLLATERAL: &str = "transferCollateral(address,address,uint256)";
+
+pub type ClaimId = Word;
+
+// `ClaimIds.id` from the factory: (uint160(market) << 8) | outcomeCode
+pub fn claim_id(market: &Address, outcome: OutcomeId) -> ClaimId {
+    let mut id = [0u8; 32];
+    id[11..31].copy_from_slice(market);
+    id[31] = outcome;
+    id
+}
+
+// Contracts of one market, as wired by the factory. The vault only accepts
+// settlement calls from the market or the factory's settler.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SettlementTarget {
+    pub market: Address,
+    pub vault: Address,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SettlementConfig {
+    pub claim_units: f64,      // claim token units per share
+    pub collateral_units: f64, // collateral units per 1.0 of price, e.g. 1e6 for USDC
+    pub max_calls_per_batch: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Call {
+    pub to: Address,
+    pub data: Vec<u8>,
+}
+
+// What a settlement moves, for checking against the vault afterwards
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Reconciliation {
+    pub trades: usize,
+    pub shares: f64,
+    pub claim_deltas: BTreeMap<(Address, ClaimId), i128>, // net claim units per user and id
+    pub cash_deltas: BTreeMap<Address, i128>,             // net collateral units per user
+    pub skipped: Vec<Trade>, // trades of other markets or with non-address users
+}
+
+impl Reconciliation {
+    // Every claim id and the collateral net to zero: settlement only moves
+    // value between users
+    pub fn is_balanced(&self) -> bool {
+        let mut per_id: BTreeMap<&ClaimId, i128> = BTreeMap::new();
+        for ((_, id), delta) in &self.claim_deltas {
+            *per_id.entry(id).or_default() += delta;
+        }
+        per_id.values().all(|&total| total == 0) && self.cash_deltas.values().sum::<i128>() == 0
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Settlement {
+    pub batches: Vec<Vec<Call>>,
+    pub report: Reconciliation,
+}
+
+// Pair the users who pay with those who receive into transfers that settle
+// every delta. Balanced deltas need fewer transfers than there are users.
+fn pair_up(deltas: &BTreeMap<Address, i128>) -> Vec<(Address, Address, u128)> {
+    let mut payers = deltas
+        .iter()
+        .filter(|(_, &delta)| delta < 0)
+        .map(|(user, &delta)| (*user, delta.unsigned_abs()));
+    let mut payees = deltas
+        .iter()
+        .filter(|(_, &delta)| delta > 0)
+        .map(|(user, &delta)| (*user, delta.unsigned_abs()));
+
+    let mut transfers = Vec::new();
+    let (mut payer, mut payee) = (payers.next(), payees.next());
+    while let (Some((from, owed)), Some((to, due))) = (payer, payee) {
+        let amount = owed.min(due);
+        transfers.push((from, to, amount));
+        payer = if owed > amount {
+            Some((from, owed - amount))
+        } else {
+            payers.next()
+        };
+        payee = if due > amount {
+            Some((to, due - amount))
+        } else {
+            payees.next()
+        };
+    }
+    transfers
+}
+
+// Net a period's trades per user and claim id and encode the vault calls
+// that settle them. Claims and collateral both move inside the vault's
+// ledger, and every call moves a balance from one user to another, so no
+// batch can credit more than it debits.
+pub fn settle(
+    target: &SettlementTarget,
+    config: &SettlementConfig,
+    trades: &[Trade],
+) -> Settlement {
+    let mut report = Reconciliation::default();
+
+    for trade in trades {
+        let parties = (
+            parse_address(&trade.market_id),
+            parse_address(&trade.buyer),
+            parse_address(&trade.seller),
+        );
+        let (Ok(market), Ok(buyer), Ok(seller)) = parties else {
+            report.skipped.push(trade.clone());
+            continue;
+        };
+        if market != target.market {
+            report.skipped.push(trade.clone());
+            continue;
+        }
+
+        let id = claim_id(&market, trade.outcome);
+        let claims = (trade.qty * config.claim_units).round() as i128;
+        let cash = (trade.qty * trade.price.0 * config.collateral_units).round() as i128;
+        *report.claim_deltas.entry((buyer, id)).or_default() += claims;
+        *report.claim_deltas.entry((seller, id)).or_default() -= claims;
+        *report.cash_deltas.entry(buyer).or_default() -= cash;
+        *report.cash_deltas.entry(seller).or_default() += cash;
+        report.trades += 1;
+        report.shares += trade.qty;
+    }
+    report.claim_deltas.retain(|_, delta| *delta != 0);
+    report.cash_deltas.retain(|_, delta| *delta != 0);
+
+    let mut per_id: BTreeMap<ClaimId, BTreeMap<Address, i128>> = BTreeMap::new();
+    for (&(user, id), &delta) in &report.claim_deltas {
+        per_id.entry(id).or_default().insert(user, delta);
+    }
+    let mut calls = Vec::new();
+    for (id, deltas) in &per_id {
+        calls.extend(pair_up(deltas).into_iter().map(|(from, to, amount)| Call {
+            to: target.vault,
+            data: encode_call(
+                TRANSFER_CLAIMS,
+                &[address_word(&from), address_word(&to), *id, word(amount)],
+            ),
+        }));
+    }
+    calls.extend(
+        pair_up(&report.cash_deltas)
+            .into_iter()
+            .map(|(from, to, amount)| Call {
+                to: target.vault,
+                data: encode_call(
+                    TRANSFER_COLLATERAL,
+                    &[address_word(&from), address_word(&to), word(amount)],
+                ),
+            }),
+    );
+    let batches = calls
+        .chunks(config.max_calls_per_batch.max(1))
+        .map(<[Call]>::to_vec)
+        .collect();
+    Settlement { batches, report }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abi::selector;
+    use crate::signing::format_address;
+    use crate::{Price, NO, YES};
+
+    const MARKET: Address = [0xaa; 20];
+    const ALICE: Address = [0x01; 20];
+    const BOB: Address = [0x02; 20];
+    const CAROL: Address = [0x03; 20];
+
+    fn trade(buyer: &Address, seller: &Address, outcome: OutcomeId, qty: f64, price: f64) -> Trade {
+        Trade {
+            buyer: format_address(buyer),
+            seller: format_address(seller),
+            buy_order_id: 1,
+            sell_order_id: 2,
+            qty,
+            price: Price(price),
+            market_id: format_address(&MARKET),
+            outcome,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn test_claim_id_matches_factory() {
+        let id = claim_id(&MARKET, NO);
+        assert_eq!(&id[..11], &[0u8; 11]);
+        assert_eq!(&id[11..31], &MARKET);
+        assert_eq!(id[31], 2);
+    }
+
+    #[test]
+    fn test_nets_trades_into_batches() {
+        let target = SettlementTarget {
+            market: MARKET,
+            vault: [0xbb; 20],
+        };
+        let config = SettlementConfig {
+            claim_units: 1.0,
+            collateral_units: 1_000_000.0,
+            max_calls_per_batch: 3,
+        };
+        let mut other_market = trade(&ALICE, &BOB, YES, 1.0, 0.5);
+        other_market.market_id = "test_market".to_string();
+        let trades = vec![
+            trade(&ALICE, &BOB, YES, 10.0, 0.6),
+            trade(&BOB, &ALICE, YES, 4.0, 0.5),
+            trade(&CAROL, &BOB, NO, 5.0, 0.4),
+            other_market,
+        ];
+
+        let settlement = settle(&target, &config, &trades);
+        let report = &settlement.report;
+        assert_eq!(report.trades, 3);
+        assert_eq!(report.skipped.len(), 1);
+        assert!(report.is_balanced());
+        assert_eq!(report.claim_deltas[&(ALICE, claim_id(&MARKET, YES))], 6);
+        assert_eq!(report.claim_deltas[&(BOB, claim_id(&MARKET, YES))], -6);
+        // alice pays 6.00 and receives 2.00; carol pays 2.00
+        assert_eq!(report.cash_deltas[&ALICE], -4_000_000);
+        assert_eq!(report.cash_deltas[&CAROL], -2_000_000);
+        assert_eq!(report.cash_deltas[&BOB], 6_000_000);
+
+        // bob's YES and NO claims to alice and carol, then alice's and
+        // carol's cash to bob, all on the vault
+        let calls: Vec<&Call> = settlement.batches.iter().flatten().collect();
+        assert_eq!(settlement.batches.len(), 2);
+        assert_eq!(calls.len(), 4);
+        assert!(calls.iter().all(|c| c.to == target.vault));
+        let selectors: Vec<[u8; 4]> = calls
+            .iter()
+            .map(|c| c.data[..4].try_into().unwrap())
+            .collect();
+        assert_eq!(
+            selectors,
+            [
+                TRANSFER_CLAIMS,
+                TRANSFER_CLAIMS,
+                TRANSFER_COLLATERAL,
+                TRANSFER_COLLATERAL
+            ]
+            .map(selector)
+        );
+
+        let first = &calls[0].data;
+        assert_eq!(&first[4 + 12..4 + 32], &BOB);
+        assert_eq!(&first[4 + 32 + 12..4 + 64], &ALICE);
+        assert_eq!(first[4 + 64 + 31], YES);
+        assert_eq!(first[4 + 96..], word(6));
+
+        let carol_pays = &calls[3].data;
+        assert_eq!(carol_pays.len(), 4 + 3 * 32);
+        assert_eq!(&carol_pays[4 + 12..4 + 32], &CAROL);
+        assert_eq!(&carol_pays[4 + 32 + 12..4 + 64], &BOB);
+        assert_eq!(carol_pays[4 + 64..], word(2_000_000));
+    }
+
+    #[test]
+    fn test_pair_up_settles_every_delta() {
+        let deltas = BTreeMap::from([(ALICE, -5), (BOB, 3), (CAROL, 2), ([0x04; 20], 0)]);
+        assert_eq!(pair_up(&deltas), vec![(ALICE, BOB, 3), (ALICE, CAROL, 2)]);
+    }
+}
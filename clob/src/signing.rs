@@ -0,0 +1,249 @@
+use crate::abi::{address_word, keccak, word, Address};
+use crate::error::EngineError;
+use crate::market::OutcomeId;
+use crate::{Order, Price, Side};
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+
+// --------------------- EIP-712 Signed Orders ---------------------
+pub const ORDER_TYPE: &str = "Order(uint256 id,address maker,string marketId,uint8 outcome,uint8 side,uint256 price,uint256 quantity,uint256 nonce,uint256 expiry)";
+const DOMAIN_TYPE: &str =
+    "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+
+pub fn parse_address(address: &str) -> Result<Address, EngineError> {
+    let bad = || EngineError::InvalidSignature(format!("bad address {}", address));
+    let bytes = hex::decode(address.strip_prefix("0x").unwrap_or(address)).map_err(|_| bad())?;
+    bytes.try_into().map_err(|_| bad())
+}
+
+pub fn format_address(address: &Address) -> String {
+    format!("0x{}", hex::encode(address))
+}
+
+// Users named by an address match in any letter case, e.g. checksummed
+pub(crate) fn same_user(a: &str, b: &str) -> bool {
+    a == b || matches!((parse_address(a), parse_address(b)), (Ok(a), Ok(b)) if a == b)
+}
+
+// One spelling per user: addresses in lowercase, other names as given
+pub(crate) fn user_key(user: &str) -> String {
+    parse_address(user).map_or_else(|_| user.to_string(), |address| format_address(&address))
+}
+
+// Signing domain of one market: signatures for one contract or chain are
+// useless on any other
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Eip712Domain {
+    pub name: String,
+    pub version: String,
+    pub chain_id: u64,
+    pub verifying_contract: Address, // market or vault contract
+}
+
+impl Eip712Domain {
+    pub fn separator(&self) -> [u8; 32] {
+        let mut encoded = Vec::with_capacity(5 * 32);
+        encoded.extend(keccak(DOMAIN_TYPE.as_bytes()));
+        encoded.extend(keccak(self.name.as_bytes()));
+        encoded.extend(keccak(self.version.as_bytes()));
+        encoded.extend(word(self.chain_id as u128));
+        encoded.extend(address_word(&self.verifying_contract));
+        keccak(&encoded)
+    }
+}
+
+// Order as signed by the maker's wallet. Price and quantity are signed as
+// 1e18 fixed point so the digest does not depend on float formatting.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignedOrder {
+    pub id: u64,
+    pub maker: Address,
+    pub market_id: String,
+    pub outcome: OutcomeId,
+    pub side: Side,
+    pub price_1e18: u128,
+    pub qty_1e18: u128,
+    pub nonce: u64,
+    pub expiry: u64,         // unix seconds; the order is rejected from this time on
+    pub signature: [u8; 65], // r || s || v, v either 0/1 or 27/28
+}
+
+impl SignedOrder {
+    pub fn struct_hash(&self) -> [u8; 32] {
+        let side = match self.side {
+            Side::Buy => 0,
+            Side::Sell => 1,
+        };
+        let mut encoded = Vec::with_capacity(10 * 32);
+        encoded.extend(keccak(ORDER_TYPE.as_bytes()));
+        encoded.extend(word(self.id as u128));
+        encoded.extend(address_word(&self.maker));
+        encoded.extend(keccak(self.market_id.as_bytes()));
+        encoded.extend(word(self.outcome as u128));
+        encoded.extend(word(side));
+        encoded.extend(word(self.price_1e18));
+        encoded.extend(word(self.qty_1e18));
+        encoded.extend(word(self.nonce as u128));
+        encoded.extend(word(self.expiry as u128));
+        keccak(&encoded)
+    }
+
+    // Hash the wallet signs: keccak256("\x19\x01" || domainSeparator || structHash)
+    pub fn digest(&self, domain: &Eip712Domain) -> [u8; 32] {
+        let mut encoded = Vec::with_capacity(2 + 2 * 32);
+        encoded.extend([0x19, 0x01]);
+        encoded.extend(domain.separator());
+        encoded.extend(self.struct_hash());
+        keccak(&encoded)
+    }
+
+    pub fn recover_signer(&self, domain: &Eip712Domain) -> Result<Address, EngineError> {
+        let invalid = || EngineError::InvalidSignature(format!("order {}", self.id));
+        let signature = Signature::from_slice(&self.signature[..64]).map_err(|_| invalid())?;
+        let recovery_id = match self.signature[64] {
+            v @ (0 | 1) => RecoveryId::from_byte(v),
+            v @ (27 | 28) => RecoveryId::from_byte(v - 27),
+            _ => None,
+        }
+        .ok_or_else(invalid)?;
+        let key = VerifyingKey::recover_from_prehash(&self.digest(domain), &signature, recovery_id)
+            .map_err(|_| invalid())?;
+        Ok(public_key_address(&key))
+    }
+
+    // Check the signature and expiry and return the order for the book
+    pub fn verify(&self, domain: &Eip712Domain, now: u64) -> Result<Order, EngineError> {
+        if self.recover_signer(domain)? != self.maker {
+            return Err(EngineError::InvalidSignature(format!(
+                "order {} not signed by {}",
+                self.id,
+                format_address(&self.maker)
+            )));
+        }
+        if now >= self.expiry {
+            return Err(EngineError::OrderExpired {
+                order_id: self.id,
+                expiry: self.expiry,
+            });
+        }
+        Ok(Order {
+            id: self.id,
+            user: format_address(&self.maker),
+            side: self.side,
+            price: Price(self.price_1e18 as f64 / 1e18),
+            qty: self.qty_1e18 as f64 / 1e18,
+            timestamp: now,
+            iceberg: None,
+        })
+    }
+}
+
+// Ethereum address of a public key: last 20 bytes of keccak256(x || y)
+pub fn public_key_address(key: &VerifyingKey) -> Address {
+    let point = key.to_encoded_point(false);
+    let hash = keccak(&point.as_bytes()[1..]);
+    hash[12..].try_into().unwrap()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use k256::ecdsa::SigningKey;
+
+    pub(crate) fn wallet(seed: u8) -> SigningKey {
+        SigningKey::from_slice(&[seed; 32]).unwrap()
+    }
+
+    pub(crate) fn sign(
+        mut order: SignedOrder,
+        key: &SigningKey,
+        domain: &Eip712Domain,
+    ) -> SignedOrder {
+        let (signature, recovery_id) = key.sign_prehash_recoverable(&order.digest(domain)).unwrap();
+        order.signature[..64].copy_from_slice(&signature.to_bytes());
+        order.signature[64] = 27 + recovery_id.to_byte();
+        order
+    }
+
+    pub(crate) fn domain() -> Eip712Domain {
+        Eip712Domain {
+            name: "EtfPredictionMarket".to_string(),
+            version: "1".to_string(),
+            chain_id: 1,
+            verifying_contract: [0x11; 20],
+        }
+    }
+
+    pub(crate) fn unsigned(id: u64, key: &SigningKey, market_id: &str, nonce: u64) -> SignedOrder {
+        SignedOrder {
+            id,
+            maker: public_key_address(key.verifying_key()),
+            market_id: market_id.to_string(),
+            outcome: crate::YES,
+            side: Side::Buy,
+            price_1e18: 600_000_000_000_000_000,
+            qty_1e18: 10_000_000_000_000_000_000,
+            nonce,
+            expiry: 1_000,
+            signature: [0; 65],
+        }
+    }
+
+    #[test]
+    fn test_known_address() {
+        // Private key 1 is the generator point
+        let key = SigningKey::from_slice(&{
+            let mut k = [0u8; 32];
+            k[31] = 1;
+            k
+        })
+        .unwrap();
+        assert_eq!(
+            format_address(&public_key_address(key.verifying_key())),
+            "0x7e5f4552091a69125d5dfcb7b8c2659029395bdf"
+        );
+    }
+
+    #[test]
+    fn test_recover_and_tamper() {
+        let key = wallet(7);
+        let order = sign(unsigned(1, &key, "m", 0), &key, &domain());
+        let verified = order.verify(&domain(), 999).unwrap();
+        assert_eq!(verified.user, format_address(&order.maker));
+        assert_eq!((verified.price, verified.qty), (Price(0.6), 10.0));
+
+        // Any change to the signed fields or the domain breaks the signature
+        let mut tampered = order.clone();
+        tampered.price_1e18 += 1;
+        assert!(matches!(
+            tampered.verify(&domain(), 0),
+            Err(EngineError::InvalidSignature(_))
+        ));
+        // The id is signed too, so a relayer cannot replay the order under another id
+        let mut renumbered = order.clone();
+        renumbered.id = 2;
+        assert!(renumbered.verify(&domain(), 0).is_err());
+
+        // v is 0/1 or 27/28 and nothing else
+        let mut low_v = order.clone();
+        low_v.signature[64] -= 27;
+        assert_eq!(low_v.recover_signer(&domain()).unwrap(), order.maker);
+        for v in [2, 26, 29, 255] {
+            let mut bad_v = order.clone();
+            bad_v.signature[64] = v;
+            assert!(bad_v.recover_signer(&domain()).is_err());
+        }
+        let other_chain = Eip712Domain {
+            chain_id: 5,
+            ..domain()
+        };
+        assert!(order.verify(&other_chain, 0).is_err());
+
+        assert_eq!(
+            order.verify(&domain(), 1_000).unwrap_err(),
+            EngineError::OrderExpired {
+                order_id: 1,
+                expiry: 1_000
+            }
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{
+    check_price, current_timestamp, DepthLevels, EngineError, MarketState, MatchingEngine, Order,
+    OrderBook, OutcomeId, Price, Side,
+};
+
+// --------------------- What-If Preview ---------------------
+// Run an order against a copy of the book to see what it would do, without
+// touching the real one
+
+// Stands in for the previewed order's id so its fills can be told apart
+const PREVIEW_ID: u64 = u64::MAX;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Preview {
+    pub fills: DepthLevels, // price and size of each fill, in matching order
+    pub filled_qty: f64,
+    pub avg_price: Option<f64>, // none if nothing would fill
+    pub slippage: f64,          // how much worse than the best opposite price on average
+    pub resting_qty: f64,       // what would be left on the book
+}
+
+impl OrderBook {
+    pub fn preview(&self, mut order: Order) -> Preview {
+        order.id = PREVIEW_ID;
+        let qty = order.qty;
+        let side = order.side;
+        let (best_bid, best_ask) = (self.bids.keys().next_back(), self.asks.keys().next());
+        let best = match side {
+            Side::Buy => best_ask.copied(),
+            Side::Sell => best_bid.copied(),
+        };
+
+        // During a call period (and in every frequent batch) the order waits
+        // for the uncross and fills at its single price
+        let mut book = self.clone();
+        book.add_order(order);
+        let trades = if book.in_auction {
+            book.clear_batch(current_timestamp())
+        } else {
+            book.match_orders()
+        };
+
+        let fills: DepthLevels = trades
+            .iter()
+            .filter(|t| t.buy_order_id == PREVIEW_ID || t.sell_order_id == PREVIEW_ID)
+            .map(|t| (t.price, t.qty))
+            .collect();
+        let filled_qty: f64 = fills.iter().map(|(_, qty)| qty).sum();
+        let notional: f64 = fills.iter().map(|(price, qty)| price.0 * qty).sum();
+        let avg_price = (filled_qty > 0.0).then(|| notional / filled_qty);
+        let slippage = match (avg_price, best) {
+            (Some(avg), Some(Price(best))) => match side {
+                Side::Buy => avg - best,
+                Side::Sell => best - avg,
+            },
+            _ => 0.0,
+        };
+        Preview {
+            fills,
+            filled_qty,
+            avg_price,
+            slippage: slippage.max(0.0),
+            resting_qty: (qty - filled_qty).max(0.0),
+        }
+    }
+}
+
+impl MatchingEngine {
+    // What `place_order` would fill right now, or at the next uncross of a
+    // call or batch, ignoring circuit breakers and stops the fills might trigger
+    pub fn preview_order(
+        &self,
+        market_id: &str,
+        outcome: OutcomeId,
+        order: Order,
+    ) -> Result<Preview, EngineError> {
+        check_price(order.price)?;
+        let market = self
+            .markets
+            .get(market_id)
+            .ok_or_else(|| EngineError::MarketNotFound(market_id.to_string()))?;
+        let book = market
+            .books
+            .get(&outcome)
+            .ok_or_else(|| EngineError::UnknownOutcome {
+                market_id: market_id.to_string(),
+                outcome,
+            })?;
+        let state = if market.trading_ended(self.now()) {
+            MarketState::Closed
+        } else {
+            market.state
+        };
+        if !state.accepts_orders() {
+            return Err(EngineError::MarketNotOpen {
+                market_id: market_id.to_string(),
+                state,
+            });
+        }
+        Ok(book.preview(order))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_test_order;
+    use crate::{MarketCommand, MarketConfig, MatchingMode, YES};
+
+    #[test]
+    fn test_preview_walks_the_book_without_trading() {
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        engine.create_market("m");
+        engine
+            .apply_market_command("admin", "m", MarketCommand::Open)
+            .unwrap();
+        engine
+            .place_order(
+                "m",
+                YES,
+                create_test_order(1, "maker", Side::Sell, 0.5, 4.0),
+            )
+            .unwrap();
+        engine
+            .place_order(
+                "m",
+                YES,
+                create_test_order(2, "maker", Side::Sell, 0.6, 4.0),
+            )
+            .unwrap();
+
+        let preview = engine
+            .preview_order(
+                "m",
+                YES,
+                create_test_order(3, "taker", Side::Buy, 0.6, 10.0),
+            )
+            .unwrap();
+        assert_eq!(preview.fills, vec![(Price(0.5), 4.0), (Price(0.6), 4.0)]);
+        assert_eq!(preview.filled_qty, 8.0);
+        assert!((preview.avg_price.unwrap() - 0.55).abs() < 1e-9);
+        assert!((preview.slippage - 0.05).abs() < 1e-9);
+        assert_eq!(preview.resting_qty, 2.0);
+
+        // The real book is untouched
+        let (_, asks) = engine.book("m", YES).unwrap().get_order_book_depth(10);
+        assert_eq!(asks.len(), 2);
+        assert!(matches!(
+            engine.preview_order("m", YES, create_test_order(4, "taker", Side::Buy, 1.5, 1.0)),
+            Err(EngineError::InvalidPrice(_))
+        ));
+
+        engine
+            .apply_market_command("admin", "m", MarketCommand::Halt)
+            .unwrap();
+        assert!(matches!(
+            engine.preview_order("m", YES, create_test_order(5, "taker", Side::Buy, 0.6, 1.0)),
+            Err(EngineError::MarketNotOpen { .. })
+        ));
+    }
+
+    #[test]
+    fn test_preview_fills_at_the_batch_price() {
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        engine.create_market_with_config(
+            "m",
+            MarketConfig {
+                matching: MatchingMode::FrequentBatch { interval_secs: 1 },
+                ..Default::default()
+            },
+        );
+        engine
+            .apply_market_command("admin", "m", MarketCommand::Open)
+            .unwrap();
+        engine
+            .place_order(
+                "m",
+                YES,
+                create_test_order(1, "maker", Side::Sell, 0.5, 4.0),
+            )
+            .unwrap();
+        engine
+            .place_order("m", YES, create_test_order(2, "other", Side::Buy, 0.7, 2.0))
+            .unwrap();
+
+        // The higher bid fills first; both pay the batch's single clearing price
+        let preview = engine
+            .preview_order("m", YES, create_test_order(2, "taker", Side::Buy, 0.6, 3.0))
+            .unwrap();
+        assert_eq!(preview.filled_qty, 2.0);
+        assert_eq!(preview.fills, vec![(Price(0.5), 2.0)]);
+        assert_eq!(preview.resting_qty, 1.0);
+    }
+}
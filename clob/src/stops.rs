@@ -0,0 +1,119 @@
+use crate::{Order, Price, Side};
+
+// --------------------- Stop Orders ---------------------
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopKind {
+    Market, // injected as an immediate-or-cancel order at any price
+    Limit,  // injected as a limit order at `order.price`
+}
+
+// Conditional order kept outside the visible book until the last trade crosses `trigger`
+#[derive(Clone, Debug)]
+pub struct StopOrder {
+    pub order: Order,
+    pub trigger: Price,
+    pub kind: StopKind,
+}
+
+impl StopOrder {
+    // Buy stops fire when the market trades at or above the trigger, sell stops at or below
+    pub fn is_triggered(&self, last_price: Price) -> bool {
+        match self.order.side {
+            Side::Buy => last_price >= self.trigger,
+            Side::Sell => last_price <= self.trigger,
+        }
+    }
+
+    // The order injected into the book once triggered
+    pub fn into_order(self) -> Order {
+        let mut order = self.order;
+        if self.kind == StopKind::Market {
+            order.price = match order.side {
+                Side::Buy => Price(1.0),
+                Side::Sell => Price(0.0),
+            };
+        }
+        order
+    }
+}
+
+// Pending stop orders of one book, in arrival order
+#[derive(Clone, Debug, Default)]
+pub struct TriggerBook {
+    pub stops: Vec<StopOrder>,
+}
+
+impl TriggerBook {
+    pub fn add(&mut self, stop: StopOrder) {
+        self.stops.push(stop);
+    }
+
+    pub fn cancel(&mut self, order_id: u64) -> bool {
+        let before = self.stops.len();
+        self.stops.retain(|s| s.order.id != order_id);
+        self.stops.len() != before
+    }
+
+    // Remove and return every stop triggered by `last_price`. Buy stops come
+    // first (lowest trigger first), then sell stops (highest trigger first);
+    // stops with the same trigger keep their arrival order.
+    pub fn take_triggered(&mut self, last_price: Price) -> Vec<StopOrder> {
+        let (mut triggered, pending): (Vec<StopOrder>, Vec<StopOrder>) = self
+            .stops
+            .drain(..)
+            .partition(|s| s.is_triggered(last_price));
+        self.stops = pending;
+
+        triggered.sort_by(|a, b| match (a.order.side, b.order.side) {
+            (Side::Buy, Side::Sell) => std::cmp::Ordering::Less,
+            (Side::Sell, Side::Buy) => std::cmp::Ordering::Greater,
+            (Side::Buy, Side::Buy) => a.trigger.cmp(&b.trigger),
+            (Side::Sell, Side::Sell) => b.trigger.cmp(&a.trigger),
+        });
+        triggered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_test_order;
+
+    fn stop(id: u64, side: Side, trigger: f64, kind: StopKind) -> StopOrder {
+        StopOrder {
+            order: create_test_order(id, &format!("user{}", id), side, 0.5, 10.0),
+            trigger: Price(trigger),
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_take_triggered_is_deterministic() {
+        let mut triggers = TriggerBook::default();
+        triggers.add(stop(1, Side::Buy, 0.6, StopKind::Limit));
+        triggers.add(stop(2, Side::Buy, 0.5, StopKind::Market));
+        triggers.add(stop(3, Side::Sell, 0.7, StopKind::Limit));
+        triggers.add(stop(4, Side::Buy, 0.5, StopKind::Limit));
+        triggers.add(stop(5, Side::Buy, 0.8, StopKind::Limit));
+
+        let ids: Vec<u64> = triggers
+            .take_triggered(Price(0.6))
+            .iter()
+            .map(|s| s.order.id)
+            .collect();
+        assert_eq!(ids, vec![2, 4, 1, 3]);
+        assert_eq!(triggers.stops.len(), 1);
+        assert!(triggers.cancel(5));
+        assert!(!triggers.cancel(5));
+    }
+
+    #[test]
+    fn test_stop_market_takes_any_price() {
+        let buy = stop(1, Side::Buy, 0.6, StopKind::Market).into_order();
+        let sell = stop(2, Side::Sell, 0.4, StopKind::Market).into_order();
+        let limit = stop(3, Side::Sell, 0.4, StopKind::Limit).into_order();
+        assert_eq!(buy.price, Price(1.0));
+        assert_eq!(sell.price, Price(0.0));
+        assert_eq!(limit.price, Price(0.5));
+    }
+}
@@ -0,0 +1,185 @@
+use crate::circuit_breaker::PriceHistory;
+use crate::error::EngineError;
+use crate::market::{MarketState, OutcomeId};
+use crate::stops::TriggerBook;
+use crate::{MatchingEngine, Order, Price, Side};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+// --------------------- Undo Log ---------------------
+// What an all-or-nothing action on one market is about to change, saved on
+// first touch, so a rejection puts back exactly that instead of copying the
+// whole market up front. A breaker the action tripped stays tripped.
+
+#[derive(Debug)]
+pub(crate) struct UndoLog {
+    market_id: String,
+    state: MarketState,
+    levels: BTreeMap<(OutcomeId, Side, Price), Option<Vec<Order>>>, // none: the level was empty
+    last_trade_prices: BTreeMap<OutcomeId, Option<Price>>,
+    histories: BTreeMap<OutcomeId, Option<PriceHistory>>,
+    triggers: BTreeMap<OutcomeId, Option<TriggerBook>>,
+    unsettled: HashMap<String, Option<f64>>,
+    unsettled_claims: HashMap<(String, String, OutcomeId), Option<f64>>,
+}
+
+impl MatchingEngine {
+    // Run an action on one market; if it fails, undo what it changed there.
+    // Inside another such action the outer one keeps the log.
+    pub(crate) fn undo_on_error<T>(
+        &mut self,
+        market_id: &str,
+        action: impl FnOnce(&mut Self) -> Result<T, EngineError>,
+    ) -> Result<T, EngineError> {
+        let Some(state) = self.markets.get(market_id).map(|m| m.state) else {
+            return action(self);
+        };
+        if self.undo.is_some() {
+            return action(self);
+        }
+        self.undo = Some(UndoLog {
+            market_id: market_id.to_string(),
+            state,
+            levels: BTreeMap::new(),
+            last_trade_prices: BTreeMap::new(),
+            histories: BTreeMap::new(),
+            triggers: BTreeMap::new(),
+            unsettled: HashMap::new(),
+            unsettled_claims: HashMap::new(),
+        });
+        let result = action(self);
+        let log = self.undo.take().unwrap();
+        if result.is_err() {
+            self.apply_undo(log);
+        }
+        result
+    }
+
+    // Before an order is added and matched: its own level, the opposite
+    // levels it can trade with, and what its trades update
+    pub(crate) fn log_order(&mut self, market_id: &str, outcome: OutcomeId, order: &Order) {
+        let Some(log) = self.undo.as_mut().filter(|log| log.market_id == market_id) else {
+            return;
+        };
+        let market = &self.markets[market_id];
+        let book = &market.books[&outcome];
+        let (own, opposite) = match order.side {
+            Side::Buy => (&book.bids, book.asks.range(..=order.price)),
+            Side::Sell => (&book.asks, book.bids.range(order.price..)),
+        };
+        log.levels
+            .entry((outcome, order.side, order.price))
+            .or_insert_with(|| own.get(&order.price).cloned());
+        let other = match order.side {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        };
+        for (&price, orders) in opposite {
+            log.levels
+                .entry((outcome, other, price))
+                .or_insert_with(|| Some(orders.clone()));
+        }
+        log.last_trade_prices
+            .entry(outcome)
+            .or_insert(book.last_trade_price);
+        log.histories
+            .entry(outcome)
+            .or_insert_with(|| market.price_history.get(&outcome).cloned());
+    }
+
+    // Before a level is amended in place
+    pub(crate) fn log_level(
+        &mut self,
+        market_id: &str,
+        outcome: OutcomeId,
+        side: Side,
+        price: Price,
+    ) {
+        let Some(log) = self.undo.as_mut().filter(|log| log.market_id == market_id) else {
+            return;
+        };
+        let book = &self.markets[market_id].books[&outcome];
+        let levels = match side {
+            Side::Buy => &book.bids,
+            Side::Sell => &book.asks,
+        };
+        log.levels
+            .entry((outcome, side, price))
+            .or_insert_with(|| levels.get(&price).cloned());
+    }
+
+    // Before stops are taken out to be injected
+    pub(crate) fn log_triggers(&mut self, market_id: &str, outcome: OutcomeId) {
+        let Some(log) = self.undo.as_mut().filter(|log| log.market_id == market_id) else {
+            return;
+        };
+        let triggers = &self.markets[market_id].triggers;
+        log.triggers
+            .entry(outcome)
+            .or_insert_with(|| triggers.get(&outcome).cloned());
+    }
+
+    // Before a trade adds to what its buyer and seller owe
+    pub(crate) fn log_unsettled(&mut self, buyer: &str, sold: &(String, String, OutcomeId)) {
+        let Some(log) = self.undo.as_mut() else {
+            return;
+        };
+        log.unsettled
+            .entry(buyer.to_string())
+            .or_insert_with(|| self.unsettled.get(buyer).copied());
+        log.unsettled_claims
+            .entry(sold.clone())
+            .or_insert_with(|| self.unsettled_claims.get(sold).copied());
+    }
+
+    fn apply_undo(&mut self, log: UndoLog) {
+        for (key, owed) in log.unsettled {
+            restore(&mut self.unsettled, key, owed);
+        }
+        for (key, owed) in log.unsettled_claims {
+            restore(&mut self.unsettled_claims, key, owed);
+        }
+
+        let market = self.markets.get_mut(&log.market_id).unwrap();
+        // Closing cleared the books: there is nothing left to put back
+        if matches!(market.state, MarketState::Closed | MarketState::Resolved) {
+            return;
+        }
+        for ((outcome, side, price), orders) in log.levels {
+            let book = market.books.get_mut(&outcome).unwrap();
+            let levels = match side {
+                Side::Buy => &mut book.bids,
+                Side::Sell => &mut book.asks,
+            };
+            match orders {
+                Some(orders) => levels.insert(price, orders),
+                None => levels.remove(&price),
+            };
+        }
+        for (outcome, price) in log.last_trade_prices {
+            market.books.get_mut(&outcome).unwrap().last_trade_price = price;
+        }
+        for (outcome, triggers) in log.triggers {
+            match triggers {
+                Some(triggers) => market.triggers.insert(outcome, triggers),
+                None => market.triggers.remove(&outcome),
+            };
+        }
+        // A trip starts the breaker's history afresh; that stays too
+        if market.state == log.state {
+            for (outcome, history) in log.histories {
+                match history {
+                    Some(history) => market.price_history.insert(outcome, history),
+                    None => market.price_history.remove(&outcome),
+                };
+            }
+        }
+    }
+}
+
+fn restore<K: Hash + Eq>(map: &mut HashMap<K, f64>, key: K, value: Option<f64>) {
+    match value {
+        Some(value) => map.insert(key, value),
+        None => map.remove(&key),
+    };
+}
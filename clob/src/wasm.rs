@@ -0,0 +1,293 @@
+// --------------------- WebAssembly Binding ---------------------
+// The engine for the browser: a local simulation the page drives itself, and
+// what-if previews of an order before it is sent to the real venue. The page
+// owns the clock (`setClock`), so nothing here needs SystemTime or threads.
+
+use crate::ffi::{outcome_label, parse_outcome};
+use crate::{
+    check_price, EngineEvent, MarketCommand, MatchingEngine, Order, OutcomeId, Price, Side, Trade,
+};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+// Markets of the simulation are opened on behalf of this admin
+const SIM_ADMIN: &str = "wasm";
+
+// Owner of the orders standing in for levels loaded with `loadDepth`
+const DEPTH_USER: &str = "depth";
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsTrade {
+    buyer: String,
+    seller: String,
+    price: f64,
+    qty: f64,
+    market: String,
+    market_id: String,
+    timestamp: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Placed {
+    order_id: u64,
+    trades: Vec<JsTrade>,
+}
+
+#[derive(Serialize)]
+struct Depth {
+    bids: Vec<(f64, f64)>, // [price, qty], best first
+    asks: Vec<(f64, f64)>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsPreview {
+    fills: Vec<(f64, f64)>,
+    filled_qty: f64,
+    avg_price: Option<f64>,
+    slippage: f64,
+    resting_qty: f64,
+}
+
+fn js_trade(trade: &Trade) -> JsTrade {
+    JsTrade {
+        buyer: trade.buyer.clone(),
+        seller: trade.seller.clone(),
+        price: trade.price.0,
+        qty: trade.qty,
+        market: outcome_label(trade.outcome),
+        market_id: trade.market_id.clone(),
+        timestamp: trade.timestamp,
+    }
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsError> {
+    serde_wasm_bindgen::to_value(value).map_err(|e| JsError::new(&e.to_string()))
+}
+
+fn outcome(market: &str) -> Result<OutcomeId, JsError> {
+    parse_outcome(market).ok_or_else(|| JsError::new(&format!("unknown outcome {market}")))
+}
+
+fn side(side: &str) -> Result<Side, JsError> {
+    match side {
+        "Buy" => Ok(Side::Buy),
+        "Sell" => Ok(Side::Sell),
+        other => Err(JsError::new(&format!("unknown side {other}"))),
+    }
+}
+
+fn levels(levels: Vec<(Price, f64)>) -> Vec<(f64, f64)> {
+    levels
+        .into_iter()
+        .map(|(price, qty)| (price.0, qty))
+        .collect()
+}
+
+#[wasm_bindgen(js_name = Engine)]
+pub struct WasmEngine {
+    engine: MatchingEngine,
+    next_order_id: u64,
+}
+
+impl Default for WasmEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen(js_class = Engine)]
+impl WasmEngine {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        let mut engine = MatchingEngine::new();
+        engine.add_admin(SIM_ADMIN);
+        Self {
+            engine,
+            next_order_id: 1,
+        }
+    }
+
+    // Seconds since the epoch, e.g. `Date.now() / 1000`. Each engine keeps
+    // its own clock, so two simulations on a page never share one.
+    #[wasm_bindgen(js_name = setClock)]
+    pub fn set_clock(&mut self, now: f64) {
+        self.engine.clock = Some(now as u64);
+    }
+
+    // Create a market already open for trading
+    #[wasm_bindgen(js_name = createMarket)]
+    pub fn create_market(&mut self, market_id: &str) -> Result<(), JsError> {
+        self.engine.create_market(market_id);
+        self.engine
+            .apply_market_command(SIM_ADMIN, market_id, MarketCommand::Open)
+            .map(|_| ())
+            .map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    // Replace one book with a depth snapshot of the real venue, e.g. the
+    // `/api/markets/:id/orderbook` levels as `[price, qty]` pairs, so previews
+    // run against it. Creates and opens the market if needed.
+    #[wasm_bindgen(js_name = loadDepth)]
+    pub fn load_depth(
+        &mut self,
+        market_id: &str,
+        market: &str,
+        bids: JsValue,
+        asks: JsValue,
+    ) -> Result<(), JsError> {
+        let outcome = outcome(market)?;
+        let bids: Vec<(f64, f64)> =
+            serde_wasm_bindgen::from_value(bids).map_err(|e| JsError::new(&e.to_string()))?;
+        let asks: Vec<(f64, f64)> =
+            serde_wasm_bindgen::from_value(asks).map_err(|e| JsError::new(&e.to_string()))?;
+        let levels = bids
+            .iter()
+            .map(|level| (Side::Buy, level))
+            .chain(asks.iter().map(|level| (Side::Sell, level)));
+        let mut orders = Vec::new();
+        for (side, &(price, qty)) in levels {
+            check_price(Price(price)).map_err(|e| JsError::new(&e.to_string()))?;
+            if qty <= 0.0 {
+                return Err(JsError::new(&format!("bad level size {qty} at {price}")));
+            }
+            orders.push(Order {
+                id: self.next_order_id + orders.len() as u64,
+                user: DEPTH_USER.to_string(),
+                side,
+                price: Price(price),
+                qty,
+                timestamp: self.engine.now(),
+                iceberg: None,
+            });
+        }
+
+        if !self.engine.markets.contains_key(market_id) {
+            self.create_market(market_id)?;
+        }
+        let book = self
+            .engine
+            .markets
+            .get_mut(market_id)
+            .and_then(|m| m.books.get_mut(&outcome))
+            .ok_or_else(|| JsError::new(&format!("unknown outcome {market}")))?;
+        book.bids.clear();
+        book.asks.clear();
+        self.next_order_id += orders.len() as u64;
+        // Straight onto the book: a snapshot is loaded as it was, never matched
+        for order in orders {
+            book.add_order(order);
+        }
+        Ok(())
+    }
+
+    // Returns `{ orderId, trades }`
+    #[wasm_bindgen(js_name = placeOrder)]
+    pub fn place_order(
+        &mut self,
+        market_id: &str,
+        market: &str,
+        user: &str,
+        side_name: &str,
+        price: f64,
+        qty: f64,
+    ) -> Result<JsValue, JsError> {
+        let order = Order {
+            id: self.next_order_id,
+            user: user.to_string(),
+            side: side(side_name)?,
+            price: Price(price),
+            qty,
+            timestamp: self.engine.now(),
+            iceberg: None,
+        };
+        let trades = self
+            .engine
+            .place_order(market_id, outcome(market)?, order)
+            .map_err(|e| JsError::new(&e.to_string()))?;
+        self.next_order_id += 1;
+        to_js(&Placed {
+            order_id: self.next_order_id - 1,
+            trades: trades.iter().map(js_trade).collect(),
+        })
+    }
+
+    #[wasm_bindgen(js_name = cancelOrder)]
+    pub fn cancel_order(
+        &mut self,
+        market_id: &str,
+        market: &str,
+        order_id: f64,
+    ) -> Result<bool, JsError> {
+        Ok(self
+            .engine
+            .cancel_order(market_id, outcome(market)?, order_id as u64))
+    }
+
+    // `{ bids, asks }` as `[price, qty]` pairs, best first
+    pub fn depth(
+        &self,
+        market_id: &str,
+        market: &str,
+        levels_wanted: Option<usize>,
+    ) -> Result<JsValue, JsError> {
+        let book = self
+            .engine
+            .book(market_id, outcome(market)?)
+            .ok_or_else(|| JsError::new(&format!("market {market_id} not found")))?;
+        let (bids, asks) = book.get_order_book_depth(levels_wanted.unwrap_or(usize::MAX));
+        to_js(&Depth {
+            bids: levels(bids),
+            asks: levels(asks),
+        })
+    }
+
+    // Expected fills and slippage of an order, leaving the book as it is
+    pub fn preview(
+        &self,
+        market_id: &str,
+        market: &str,
+        side_name: &str,
+        price: f64,
+        qty: f64,
+    ) -> Result<JsValue, JsError> {
+        let order = Order {
+            id: 0,
+            user: String::new(),
+            side: side(side_name)?,
+            price: Price(price),
+            qty,
+            timestamp: self.engine.now(),
+            iceberg: None,
+        };
+        let preview = self
+            .engine
+            .preview_order(market_id, outcome(market)?, order)
+            .map_err(|e| JsError::new(&e.to_string()))?;
+        to_js(&JsPreview {
+            fills: levels(preview.fills),
+            filled_qty: preview.filled_qty,
+            avg_price: preview.avg_price,
+            slippage: preview.slippage,
+            resting_qty: preview.resting_qty,
+        })
+    }
+
+    // Advance the simulation to `now` (seconds), returning the trades the
+    // engine's timed work produced
+    pub fn tick(&mut self, now: f64) -> Result<JsValue, JsError> {
+        self.set_clock(now);
+        let trades: Vec<JsTrade> = self
+            .engine
+            .tick(now as u64)
+            .iter()
+            .filter_map(|event| match event {
+                EngineEvent::Trade(trade) => Some(js_trade(trade)),
+                _ => None,
+            })
+            .collect();
+        to_js(&trades)
+    }
+}
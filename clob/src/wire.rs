@@ -0,0 +1,463 @@
+use crate::error::EngineError;
+use crate::market::OutcomeId;
+use crate::Side;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+
+// --------------------- Binary Order Entry ---------------------
+// Length-prefixed frames with a fixed layout per message type, all integers
+// and floats little-endian:
+//
+//   len: u16 | type: u8 | body
+//
+// `len` counts the type byte and the body. Strings are NUL-padded to their
+// field width.
+//
+//   0x01 Logon            user[64], token[64]
+//   0x02 NewOrder         client_order_id u64, market_id[32], outcome u8, side u8, price f64, qty f64
+//   0x03 Cancel           client_order_id u64
+//   0x04 Amend            client_order_id u64, price f64, qty f64
+//   0x81 ExecutionReport  client_order_id u64, order_id u64, exec_type u8, side u8, reason u8,
+//                         last_price f64, last_qty f64, leaves_qty f64
+//   0x82 BookUpdate       market_id[32], outcome u8, side u8, price f64, qty f64
+
+pub const USER_LEN: usize = 64;
+pub const TOKEN_LEN: usize = 64;
+pub const MARKET_ID_LEN: usize = 32;
+
+const LOGON: u8 = 0x01;
+const NEW_ORDER: u8 = 0x02;
+const CANCEL: u8 = 0x03;
+const AMEND: u8 = 0x04;
+const EXECUTION_REPORT: u8 = 0x81;
+const BOOK_UPDATE: u8 = 0x82;
+
+// Why an order or request was rejected
+pub mod reason {
+    pub const NONE: u8 = 0;
+    pub const UNKNOWN_ORDER: u8 = 1;
+    pub const DUPLICATE_ID: u8 = 2;
+    pub const NOT_LOGGED_ON: u8 = 3;
+    pub const MARKET_NOT_FOUND: u8 = 4;
+    pub const MARKET_NOT_OPEN: u8 = 5;
+    pub const INVALID_PRICE: u8 = 6;
+    pub const INVALID_QTY: u8 = 7;
+    pub const CIRCUIT_BREAKER: u8 = 8;
+    pub const OTHER: u8 = 255;
+}
+
+pub fn reason_code(error: &EngineError) -> u8 {
+    match error {
+        EngineError::MarketNotFound(_) | EngineError::UnknownOutcome { .. } => {
+            reason::MARKET_NOT_FOUND
+        }
+        EngineError::MarketNotOpen { .. } => reason::MARKET_NOT_OPEN,
+        EngineError::InvalidPrice(_) => reason::INVALID_PRICE,
+        EngineError::CircuitBreakerTripped(_) => reason::CIRCUIT_BREAKER,
+        _ => reason::OTHER,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExecType {
+    New = 0,
+    Fill = 1,
+    Cancelled = 2,
+    Replaced = 3,
+    Rejected = 4,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NewOrder {
+    pub client_order_id: u64,
+    pub market_id: String,
+    pub outcome: OutcomeId,
+    pub side: Side,
+    pub price: f64,
+    pub qty: f64,
+}
+
+// New price and remaining size of a resting order
+#[derive(Clone, Debug, PartialEq)]
+pub struct Amend {
+    pub client_order_id: u64,
+    pub price: f64,
+    pub qty: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExecReport {
+    pub client_order_id: u64,
+    pub order_id: u64, // engine id, 0 if rejected before reaching the book
+    pub exec_type: ExecType,
+    pub side: Side,
+    pub reason: u8,      // `reason` code of a rejection
+    pub last_price: f64, // fill price of a Fill
+    pub last_qty: f64,   // fill size of a Fill
+    pub leaves_qty: f64, // still resting after this report
+}
+
+// Aggregate size now resting at one level; 0 means the level is gone
+#[derive(Clone, Debug, PartialEq)]
+pub struct BookUpdate {
+    pub market_id: String,
+    pub outcome: OutcomeId,
+    pub side: Side,
+    pub price: f64,
+    pub qty: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WireMessage {
+    Logon { user: String, token: String }, // token: the user's secret on the gateway
+    NewOrder(NewOrder),
+    Cancel { client_order_id: u64 },
+    Amend(Amend),
+    ExecReport(ExecReport),
+    BookUpdate(BookUpdate),
+}
+
+// ----- Codec -----
+// Total size of the first frame in `buf`, or None if it is not complete yet
+pub fn frame_len(buf: &[u8]) -> Option<usize> {
+    let len = u16::from_le_bytes([*buf.first()?, *buf.get(1)?]) as usize;
+    let total = 2 + len;
+    (buf.len() >= total).then_some(total)
+}
+
+fn malformed(reason: &str) -> EngineError {
+    EngineError::Wire(reason.to_string())
+}
+
+fn put_str(out: &mut Vec<u8>, value: &str, width: usize) -> Result<(), EngineError> {
+    if value.len() > width || value.contains('\0') {
+        return Err(malformed("string does not fit its field"));
+    }
+    out.extend_from_slice(value.as_bytes());
+    out.resize(out.len() + width - value.len(), 0);
+    Ok(())
+}
+
+fn side_byte(side: Side) -> u8 {
+    match side {
+        Side::Buy => 0,
+        Side::Sell => 1,
+    }
+}
+
+// Reads the fixed fields of one body in order
+struct Fields<'a> {
+    body: &'a [u8],
+}
+
+impl Fields<'_> {
+    fn take(&mut self, n: usize) -> &[u8] {
+        let (head, tail) = self.body.split_at(n);
+        self.body = tail;
+        head
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.take(1)[0]
+    }
+
+    fn u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.take(8).try_into().unwrap())
+    }
+
+    fn f64(&mut self) -> f64 {
+        f64::from_le_bytes(self.take(8).try_into().unwrap())
+    }
+
+    fn str(&mut self, width: usize) -> Result<String, EngineError> {
+        let raw = self.take(width);
+        let end = raw.iter().position(|&b| b == 0).unwrap_or(width);
+        String::from_utf8(raw[..end].to_vec()).map_err(|_| malformed("string is not UTF-8"))
+    }
+
+    fn side(&mut self) -> Result<Side, EngineError> {
+        match self.u8() {
+            0 => Ok(Side::Buy),
+            1 => Ok(Side::Sell),
+            _ => Err(malformed("unknown side")),
+        }
+    }
+}
+
+impl WireMessage {
+    fn body_len(msg_type: u8) -> Option<usize> {
+        match msg_type {
+            LOGON => Some(USER_LEN + TOKEN_LEN),
+            NEW_ORDER => Some(8 + MARKET_ID_LEN + 1 + 1 + 8 + 8),
+            CANCEL => Some(8),
+            AMEND => Some(8 + 8 + 8),
+            EXECUTION_REPORT => Some(8 + 8 + 1 + 1 + 1 + 8 + 8 + 8),
+            BOOK_UPDATE => Some(MARKET_ID_LEN + 1 + 1 + 8 + 8),
+            _ => None,
+        }
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, EngineError> {
+        let mut out = vec![0, 0];
+        match self {
+            WireMessage::Logon { user, token } => {
+                out.push(LOGON);
+                put_str(&mut out, user, USER_LEN)?;
+                put_str(&mut out, token, TOKEN_LEN)?;
+            }
+            WireMessage::NewOrder(order) => {
+                out.push(NEW_ORDER);
+                out.extend_from_slice(&order.client_order_id.to_le_bytes());
+                put_str(&mut out, &order.market_id, MARKET_ID_LEN)?;
+                out.push(order.outcome);
+                out.push(side_byte(order.side));
+                out.extend_from_slice(&order.price.to_le_bytes());
+                out.extend_from_slice(&order.qty.to_le_bytes());
+            }
+            WireMessage::Cancel { client_order_id } => {
+                out.push(CANCEL);
+                out.extend_from_slice(&client_order_id.to_le_bytes());
+            }
+            WireMessage::Amend(amend) => {
+                out.push(AMEND);
+                out.extend_from_slice(&amend.client_order_id.to_le_bytes());
+                out.extend_from_slice(&amend.price.to_le_bytes());
+                out.extend_from_slice(&amend.qty.to_le_bytes());
+            }
+            WireMessage::ExecReport(report) => {
+                out.push(EXECUTION_REPORT);
+                out.extend_from_slice(&report.client_order_id.to_le_bytes());
+                out.extend_from_slice(&report.order_id.to_le_bytes());
+                out.push(report.exec_type as u8);
+                out.push(side_byte(report.side));
+                out.push(report.reason);
+                out.extend_from_slice(&report.last_price.to_le_bytes());
+                out.extend_from_slice(&report.last_qty.to_le_bytes());
+                out.extend_from_slice(&report.leaves_qty.to_le_bytes());
+            }
+            WireMessage::BookUpdate(update) => {
+                out.push(BOOK_UPDATE);
+                put_str(&mut out, &update.market_id, MARKET_ID_LEN)?;
+                out.push(update.outcome);
+                out.push(side_byte(update.side));
+                out.extend_from_slice(&update.price.to_le_bytes());
+                out.extend_from_slice(&update.qty.to_le_bytes());
+            }
+        }
+        let len = (out.len() - 2) as u16;
+        out[..2].copy_from_slice(&len.to_le_bytes());
+        Ok(out)
+    }
+
+    // Decode one complete frame, length prefix included
+    pub fn decode(frame: &[u8]) -> Result<WireMessage, EngineError> {
+        if frame_len(frame) != Some(frame.len()) || frame.len() < 3 {
+            return Err(malformed("incomplete frame"));
+        }
+        let msg_type = frame[2];
+        let body = &frame[3..];
+        if WireMessage::body_len(msg_type) != Some(body.len()) {
+            return Err(malformed("unknown message type or wrong length"));
+        }
+
+        let mut fields = Fields { body };
+        let msg = match msg_type {
+            LOGON => WireMessage::Logon {
+                user: fields.str(USER_LEN)?,
+                token: fields.str(TOKEN_LEN)?,
+            },
+            NEW_ORDER => WireMessage::NewOrder(NewOrder {
+                client_order_id: fields.u64(),
+                market_id: fields.str(MARKET_ID_LEN)?,
+                outcome: fields.u8(),
+                side: fields.side()?,
+                price: fields.f64(),
+                qty: fields.f64(),
+            }),
+            CANCEL => WireMessage::Cancel {
+                client_order_id: fields.u64(),
+            },
+            AMEND => WireMessage::Amend(Amend {
+                client_order_id: fields.u64(),
+                price: fields.f64(),
+                qty: fields.f64(),
+            }),
+            EXECUTION_REPORT => WireMessage::ExecReport(ExecReport {
+                client_order_id: fields.u64(),
+                order_id: fields.u64(),
+                exec_type: match fields.u8() {
+                    0 => ExecType::New,
+                    1 => ExecType::Fill,
+                    2 => ExecType::Cancelled,
+                    3 => ExecType::Replaced,
+                    4 => ExecType::Rejected,
+                    _ => return Err(malformed("unknown exec type")),
+                },
+                side: fields.side()?,
+                reason: fields.u8(),
+                last_price: fields.f64(),
+                last_qty: fields.f64(),
+                leaves_qty: fields.f64(),
+            }),
+            _ => WireMessage::BookUpdate(BookUpdate {
+                market_id: fields.str(MARKET_ID_LEN)?,
+                outcome: fields.u8(),
+                side: fields.side()?,
+                price: fields.f64(),
+                qty: fields.f64(),
+            }),
+        };
+        Ok(msg)
+    }
+}
+
+// ----- Client -----
+// Blocking client for quoting bots: logs on when created, then sends requests
+// and reads reports and book updates in arrival order
+pub struct WireClient<S: Read + Write> {
+    stream: S,
+    buf: Vec<u8>,
+}
+
+fn invalid_data(error: EngineError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+impl WireClient<TcpStream> {
+    pub fn connect(addr: impl ToSocketAddrs, user: &str, token: &str) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        WireClient::new(stream, user, token)
+    }
+}
+
+#[cfg(unix)]
+impl WireClient<std::os::unix::net::UnixStream> {
+    pub fn connect_unix(
+        path: impl AsRef<std::path::Path>,
+        user: &str,
+        token: &str,
+    ) -> io::Result<Self> {
+        WireClient::new(std::os::unix::net::UnixStream::connect(path)?, user, token)
+    }
+}
+
+impl<S: Read + Write> WireClient<S> {
+    pub fn new(stream: S, user: &str, token: &str) -> io::Result<Self> {
+        let mut client = Self {
+            stream,
+            buf: Vec::new(),
+        };
+        client.send(&WireMessage::Logon {
+            user: user.to_string(),
+            token: token.to_string(),
+        })?;
+        Ok(client)
+    }
+
+    pub fn send(&mut self, msg: &WireMessage) -> io::Result<()> {
+        self.stream.write_all(&msg.encode().map_err(invalid_data)?)
+    }
+
+    pub fn new_order(&mut self, order: NewOrder) -> io::Result<()> {
+        self.send(&WireMessage::NewOrder(order))
+    }
+
+    pub fn cancel(&mut self, client_order_id: u64) -> io::Result<()> {
+        self.send(&WireMessage::Cancel { client_order_id })
+    }
+
+    pub fn amend(&mut self, client_order_id: u64, price: f64, qty: f64) -> io::Result<()> {
+        self.send(&WireMessage::Amend(Amend {
+            client_order_id,
+            price,
+            qty,
+        }))
+    }
+
+    // Block until the next message arrives
+    pub fn recv(&mut self) -> io::Result<WireMessage> {
+        let mut chunk = [0u8; 4096];
+        loop {
+            if let Some(len) = frame_len(&self.buf) {
+                let frame: Vec<u8> = self.buf.drain(..len).collect();
+                return WireMessage::decode(&frame).map_err(invalid_data);
+            }
+            let n = self.stream.read(&mut chunk)?;
+            if n == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            self.buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::YES;
+
+    #[test]
+    fn test_round_trip_every_message() {
+        let messages = vec![
+            WireMessage::Logon {
+                user: "0xabc".to_string(),
+                token: "s3cret".to_string(),
+            },
+            WireMessage::NewOrder(NewOrder {
+                client_order_id: 7,
+                market_id: "spy-band".to_string(),
+                outcome: YES,
+                side: Side::Sell,
+                price: 0.615,
+                qty: 120.5,
+            }),
+            WireMessage::Cancel { client_order_id: 7 },
+            WireMessage::Amend(Amend {
+                client_order_id: 7,
+                price: 0.6,
+                qty: 50.0,
+            }),
+            WireMessage::ExecReport(ExecReport {
+                client_order_id: 7,
+                order_id: 1 << 61,
+                exec_type: ExecType::Fill,
+                side: Side::Buy,
+                reason: reason::NONE,
+                last_price: 0.6,
+                last_qty: 10.0,
+                leaves_qty: 40.0,
+            }),
+            WireMessage::BookUpdate(BookUpdate {
+                market_id: "spy-band".to_string(),
+                outcome: YES,
+                side: Side::Sell,
+                price: 0.6,
+                qty: 0.0,
+            }),
+        ];
+        for msg in messages {
+            let frame = msg.encode().unwrap();
+            assert_eq!(frame_len(&frame), Some(frame.len()));
+            assert_eq!(WireMessage::decode(&frame).unwrap(), msg);
+        }
+    }
+
+    #[test]
+    fn test_rejects_malformed_frames() {
+        let frame = WireMessage::Cancel { client_order_id: 1 }.encode().unwrap();
+        assert_eq!(frame, [9, 0, CANCEL, 1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(frame_len(&frame[..5]), None);
+        assert!(WireMessage::decode(&frame[..5]).is_err());
+
+        let mut unknown = frame.clone();
+        unknown[2] = 0x7f;
+        assert!(WireMessage::decode(&unknown).is_err());
+
+        let too_long = WireMessage::Logon {
+            user: "x".repeat(USER_LEN + 1),
+            token: String::new(),
+        };
+        assert!(too_long.encode().is_err());
+    }
+}
@@ -0,0 +1,780 @@
+use crate::fix_gateway::{same_secret, Outbox};
+use crate::wire::{
+    frame_len, reason, reason_code, BookUpdate, ExecReport, ExecType, NewOrder, WireMessage,
+};
+use crate::{
+    check_price, current_timestamp, EngineEvent, MatchingEngine, Order, OutcomeId, Price, Side,
+    Trade,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// --------------------- Binary Order Entry Gateway ---------------------
+// Runs binary protocol requests against the engine. Connections log on with a
+// user and the token registered for it. Execution reports go to every
+// connection logged on as the order's user; book updates go to all.
+
+// Binary protocol orders get engine ids from their own range, below FIX's
+pub const WIRE_ORDER_ID_BASE: u64 = 1 << 61;
+
+const QTY_EPSILON: f64 = 1e-9;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Recipient {
+    User(String),
+    All,
+}
+
+pub type Outbound = (Recipient, WireMessage);
+
+#[derive(Clone, Debug)]
+struct WireOrder {
+    user: String,
+    client_order_id: u64,
+    market_id: String,
+    outcome: OutcomeId,
+    side: Side,
+    price: f64,
+    leaves: f64, // size still resting as last reported
+}
+
+impl WireOrder {
+    // Trade this order, under engine id `id`, took part in
+    fn traded_in(&self, id: u64, trade: &Trade) -> bool {
+        let order_id = match self.side {
+            Side::Buy => trade.buy_order_id,
+            Side::Sell => trade.sell_order_id,
+        };
+        trade.market_id == self.market_id && trade.outcome == self.outcome && order_id == id
+    }
+}
+
+// Book levels as last broadcast, by book and side
+type Published = BTreeMap<(String, OutcomeId, Side), BTreeMap<Price, f64>>;
+
+pub struct WireGateway {
+    pub engine: MatchingEngine,
+    tokens: HashMap<String, String>,         // user -> logon token
+    orders: BTreeMap<u64, WireOrder>,        // open orders by engine id
+    client_ids: HashMap<(String, u64), u64>, // (user, client order id) -> engine id
+    published: Published,
+    next_order_id: u64,
+}
+
+impl WireGateway {
+    pub fn new(engine: MatchingEngine) -> Self {
+        Self {
+            engine,
+            tokens: HashMap::new(),
+            orders: BTreeMap::new(),
+            client_ids: HashMap::new(),
+            published: BTreeMap::new(),
+            next_order_id: WIRE_ORDER_ID_BASE,
+        }
+    }
+
+    // Let `user` log on with `token`
+    pub fn add_user(&mut self, user: &str, token: &str) {
+        self.tokens.insert(user.to_string(), token.to_string());
+    }
+
+    pub fn authenticates(&self, user: &str, token: &str) -> bool {
+        self.tokens
+            .get(user)
+            .is_some_and(|expected| same_secret(token, expected))
+    }
+
+    // Handle one request from a connection logged on as `user`
+    pub fn handle(&mut self, user: &str, msg: WireMessage) -> Vec<Outbound> {
+        match msg {
+            WireMessage::NewOrder(order) => self.new_order(user, order),
+            WireMessage::Cancel { client_order_id } => self.cancel(user, client_order_id),
+            WireMessage::Amend(amend) => {
+                self.amend(user, amend.client_order_id, amend.price, amend.qty)
+            }
+            // Logons are the transport's business; reports only flow outwards
+            _ => Vec::new(),
+        }
+    }
+
+    // The engine's timed work, reported like any other activity. Every book
+    // is then checked against what was last broadcast, which also picks up
+    // changes made on the engine directly.
+    pub fn on_timer(&mut self, now: u64) -> Vec<Outbound> {
+        let mut trades = Vec::new();
+        let mut touched = Vec::new();
+        for event in self.engine.tick(now) {
+            match event {
+                EngineEvent::Trade(trade) => trades.push(trade),
+                EngineEvent::SessionExpired { cancelled, .. } => touched.extend(cancelled),
+                // A closed market takes its resting orders with it
+                EngineEvent::StateChanged { market_id, .. } => touched.extend(
+                    self.orders
+                        .iter()
+                        .filter(|(_, o)| o.market_id == market_id)
+                        .map(|(&id, _)| id),
+                ),
+                _ => {}
+            }
+        }
+        touched.extend(
+            trades
+                .iter()
+                .flat_map(|t| [t.buy_order_id, t.sell_order_id]),
+        );
+        let mut out = self.apply_trades(&trades);
+        out.extend(self.cancel_gone(touched));
+        out.extend(self.sync_books());
+        out
+    }
+
+    fn new_order(&mut self, user: &str, order: NewOrder) -> Vec<Outbound> {
+        let reject = |reason| {
+            let report = rejection(order.client_order_id, order.side, reason);
+            vec![(Recipient::User(user.to_string()), report)]
+        };
+        if !valid_qty(order.qty) {
+            return reject(reason::INVALID_QTY);
+        }
+        if self
+            .client_ids
+            .contains_key(&(user.to_string(), order.client_order_id))
+        {
+            return reject(reason::DUPLICATE_ID);
+        }
+        let wire_order = WireOrder {
+            user: user.to_string(),
+            client_order_id: order.client_order_id,
+            market_id: order.market_id,
+            outcome: order.outcome,
+            side: order.side,
+            price: order.price,
+            leaves: order.qty,
+        };
+        self.submit(wire_order, ExecType::New)
+            .unwrap_or_else(reject)
+    }
+
+    fn cancel(&mut self, user: &str, client_order_id: u64) -> Vec<Outbound> {
+        let recipient = Recipient::User(user.to_string());
+        let Some(id) = self.lookup(user, client_order_id) else {
+            let report = rejection(client_order_id, Side::Buy, reason::UNKNOWN_ORDER);
+            return vec![(recipient, report)];
+        };
+        let order = self.orders[&id].clone();
+        if !self
+            .engine
+            .cancel_order(&order.market_id, order.outcome, id)
+        {
+            let report = rejection(client_order_id, order.side, reason::UNKNOWN_ORDER);
+            return vec![(recipient, report)];
+        }
+        let mut out = vec![self.cancelled(id, reason::NONE)];
+        out.extend(self.own_level(&order));
+        out
+    }
+
+    // Cancel and re-enter with the new price and remaining size; the amended
+    // order keeps its client id but joins the back of the queue
+    fn amend(&mut self, user: &str, client_order_id: u64, price: f64, qty: f64) -> Vec<Outbound> {
+        let recipient = Recipient::User(user.to_string());
+        let Some(id) = self.lookup(user, client_order_id) else {
+            let report = rejection(client_order_id, Side::Buy, reason::UNKNOWN_ORDER);
+            return vec![(recipient, report)];
+        };
+        let old = self.orders[&id].clone();
+        if !valid_qty(qty) || check_price(Price(price)).is_err() {
+            let reason = if valid_qty(qty) {
+                reason::INVALID_PRICE
+            } else {
+                reason::INVALID_QTY
+            };
+            return vec![(recipient, rejection(client_order_id, old.side, reason))];
+        }
+        if !self.engine.cancel_order(&old.market_id, old.outcome, id) {
+            let report = rejection(client_order_id, old.side, reason::UNKNOWN_ORDER);
+            return vec![(recipient, report)];
+        }
+        self.forget(id);
+
+        let amended = WireOrder {
+            price,
+            leaves: qty,
+            ..old.clone()
+        };
+        let mut out = match self.submit(amended, ExecType::Replaced) {
+            Ok(out) => out,
+            Err(reason) => {
+                // The original is gone either way
+                self.orders.insert(id, old.clone());
+                vec![self.cancelled(id, reason)]
+            }
+        };
+        out.extend(self.own_level(&old));
+        out
+    }
+
+    // Place an order and report it, its fills, the fills it caused and the
+    // levels it changed
+    fn submit(&mut self, order: WireOrder, exec_type: ExecType) -> Result<Vec<Outbound>, u8> {
+        let id = self.next_order_id;
+        let engine_order = Order {
+            id,
+            user: order.user.clone(),
+            side: order.side,
+            price: Price(order.price),
+            qty: order.leaves,
+            timestamp: current_timestamp(),
+            iceberg: None,
+        };
+        let trades = self
+            .engine
+            .place_order(&order.market_id, order.outcome, engine_order)
+            .map_err(|e| reason_code(&e))?;
+        self.next_order_id += 1;
+
+        self.client_ids
+            .insert((order.user.clone(), order.client_order_id), id);
+        self.orders.insert(id, order.clone());
+        let mut out = vec![self.report(id, exec_type, reason::NONE, 0.0, 0.0)];
+        out.extend(self.apply_trades(&trades));
+        let touched = trades
+            .iter()
+            .flat_map(|t| [t.buy_order_id, t.sell_order_id])
+            .chain([id]);
+        out.extend(self.cancel_gone(touched.collect()));
+        out.extend(self.crossed_levels(&order));
+        Ok(out)
+    }
+
+    // Fill our orders from the trades they took part in, at each trade's
+    // price and size
+    fn apply_trades(&mut self, trades: &[Trade]) -> Vec<Outbound> {
+        let mut out = Vec::new();
+        for trade in trades {
+            for id in [trade.buy_order_id, trade.sell_order_id] {
+                if self
+                    .orders
+                    .get(&id)
+                    .is_some_and(|order| order.traded_in(id, trade))
+                {
+                    out.push(self.fill(id, trade.qty, trade.price.0));
+                }
+            }
+        }
+        out
+    }
+
+    // Report the unfilled remainder of orders that are no longer resting as
+    // cancelled; run after their fills are applied
+    fn cancel_gone(&mut self, ids: Vec<u64>) -> Vec<Outbound> {
+        let mut out = Vec::new();
+        for id in ids {
+            let Some(order) = self.orders.get(&id) else {
+                continue; // not ours, or fully filled
+            };
+            if order.leaves - self.resting_qty(id) > QTY_EPSILON {
+                out.push(self.cancelled(id, reason::NONE));
+            }
+        }
+        out
+    }
+
+    // Size of an order still on its book, looked up at its own price level
+    fn resting_qty(&self, id: u64) -> f64 {
+        let order = &self.orders[&id];
+        self.level(
+            &order.market_id,
+            order.outcome,
+            order.side,
+            Price(order.price),
+        )
+        .and_then(|orders| orders.iter().find(|o| o.id == id))
+        .map_or(0.0, |o| o.total_qty())
+    }
+
+    fn level(
+        &self,
+        market_id: &str,
+        outcome: OutcomeId,
+        side: Side,
+        price: Price,
+    ) -> Option<&Vec<Order>> {
+        let book = self.engine.book(market_id, outcome)?;
+        match side {
+            Side::Buy => book.bids.get(&price),
+            Side::Sell => book.asks.get(&price),
+        }
+    }
+
+    fn lookup(&self, user: &str, client_order_id: u64) -> Option<u64> {
+        self.client_ids
+            .get(&(user.to_string(), client_order_id))
+            .copied()
+    }
+
+    fn forget(&mut self, id: u64) {
+        if let Some(order) = self.orders.remove(&id) {
+            self.client_ids.remove(&(order.user, order.client_order_id));
+        }
+    }
+
+    // ----- Book updates -----
+    // Broadcast the given levels of one book side where they differ from what
+    // was last sent
+    fn publish(
+        &mut self,
+        key: (String, OutcomeId, Side),
+        prices: BTreeSet<Price>,
+    ) -> Vec<Outbound> {
+        let mut out = Vec::new();
+        for price in prices {
+            let qty: f64 = self
+                .level(&key.0, key.1, key.2, price)
+                .map_or(0.0, |orders| orders.iter().map(|o| o.qty).sum());
+            let levels = self.published.entry(key.clone()).or_default();
+            if levels.get(&price).copied().unwrap_or(0.0) == qty {
+                continue;
+            }
+            if qty > 0.0 {
+                levels.insert(price, qty);
+            } else {
+                levels.remove(&price);
+            }
+            let update = BookUpdate {
+                market_id: key.0.clone(),
+                outcome: key.1,
+                side: key.2,
+                price: price.0,
+                qty,
+            };
+            out.push((Recipient::All, WireMessage::BookUpdate(update)));
+        }
+        out
+    }
+
+    fn own_level(&mut self, order: &WireOrder) -> Vec<Outbound> {
+        let key = (order.market_id.clone(), order.outcome, order.side);
+        self.publish(key, BTreeSet::from([Price(order.price)]))
+    }
+
+    // The order's own level and the levels on the other side it could have
+    // traded through
+    fn crossed_levels(&mut self, order: &WireOrder) -> Vec<Outbound> {
+        let limit = Price(order.price);
+        let opposite = match order.side {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        };
+        let key = (order.market_id.clone(), order.outcome, opposite);
+        let crossable = |levels: &mut dyn Iterator<Item = &Price>| -> Vec<Price> {
+            levels
+                .filter(|&&price| match order.side {
+                    Side::Buy => price <= limit,
+                    Side::Sell => price >= limit,
+                })
+                .copied()
+                .collect()
+        };
+        let mut prices: BTreeSet<Price> = BTreeSet::new();
+        if let Some(levels) = self.published.get(&key) {
+            prices.extend(crossable(&mut levels.keys()));
+        }
+        if let Some(book) = self.engine.book(&order.market_id, order.outcome) {
+            let levels = match opposite {
+                Side::Buy => &book.bids,
+                Side::Sell => &book.asks,
+            };
+            prices.extend(crossable(&mut levels.keys()));
+        }
+        let mut out = self.own_level(order);
+        out.extend(self.publish(key, prices));
+        out
+    }
+
+    // Compare every book with what was last broadcast
+    fn sync_books(&mut self) -> Vec<Outbound> {
+        let mut sides = Vec::new();
+        for book in self.engine.markets.values().flat_map(|m| m.books.values()) {
+            for (side, levels) in [(Side::Buy, &book.bids), (Side::Sell, &book.asks)] {
+                let key = (book.market_id.clone(), book.outcome, side);
+                let mut prices: BTreeSet<Price> = levels.keys().copied().collect();
+                if let Some(published) = self.published.get(&key) {
+                    prices.extend(published.keys());
+                }
+                sides.push((key, prices));
+            }
+        }
+        let mut out = Vec::new();
+        for (key, prices) in sides {
+            out.extend(self.publish(key, prices));
+        }
+        out
+    }
+
+    // ----- Execution reports -----
+    fn report(
+        &self,
+        id: u64,
+        exec_type: ExecType,
+        reason: u8,
+        last_price: f64,
+        last_qty: f64,
+    ) -> Outbound {
+        let order = &self.orders[&id];
+        let report = ExecReport {
+            client_order_id: order.client_order_id,
+            order_id: id,
+            exec_type,
+            side: order.side,
+            reason,
+            last_price,
+            last_qty,
+            leaves_qty: order.leaves,
+        };
+        (
+            Recipient::User(order.user.clone()),
+            WireMessage::ExecReport(report),
+        )
+    }
+
+    fn fill(&mut self, id: u64, qty: f64, price: f64) -> Outbound {
+        let order = self.orders.get_mut(&id).unwrap();
+        order.leaves = (order.leaves - qty).max(0.0);
+        let done = order.leaves <= QTY_EPSILON;
+        let report = self.report(id, ExecType::Fill, reason::NONE, price, qty);
+        if done {
+            self.forget(id);
+        }
+        report
+    }
+
+    fn cancelled(&mut self, id: u64, reason: u8) -> Outbound {
+        self.orders.get_mut(&id).unwrap().leaves = 0.0;
+        let report = self.report(id, ExecType::Cancelled, reason, 0.0, 0.0);
+        self.forget(id);
+        report
+    }
+}
+
+// Positive and finite; NaN fails every comparison, so test for it explicitly
+fn valid_qty(qty: f64) -> bool {
+    qty.is_finite() && qty > 0.0
+}
+
+fn rejection(client_order_id: u64, side: Side, reason: u8) -> WireMessage {
+    WireMessage::ExecReport(ExecReport {
+        client_order_id,
+        order_id: 0,
+        exec_type: ExecType::Rejected,
+        side,
+        reason,
+        last_price: 0.0,
+        last_qty: 0.0,
+        leaves_qty: 0.0,
+    })
+}
+
+// ----- Servers -----
+// Streams the gateway can serve: TCP and, on unix, Unix domain sockets
+trait Stream: Read + Write + Send + Sync + Sized + 'static {
+    fn duplicate(&self) -> io::Result<Self>;
+    fn close(&self);
+}
+
+impl Stream for TcpStream {
+    fn duplicate(&self) -> io::Result<Self> {
+        self.try_clone()
+    }
+
+    fn close(&self) {
+        let _ = self.shutdown(Shutdown::Both);
+    }
+}
+
+#[cfg(unix)]
+impl Stream for std::os::unix::net::UnixStream {
+    fn duplicate(&self) -> io::Result<Self> {
+        self.try_clone()
+    }
+
+    fn close(&self) {
+        let _ = self.shutdown(Shutdown::Both);
+    }
+}
+
+struct Connection {
+    user: String,
+    outbox: Outbox,
+}
+
+struct Shared {
+    gateway: WireGateway,
+    connections: HashMap<u64, Connection>,
+    next_connection: u64,
+}
+
+impl Shared {
+    fn deliver(&mut self, out: Vec<Outbound>) {
+        for (recipient, msg) in out {
+            let Ok(frame) = msg.encode() else {
+                continue;
+            };
+            for connection in self.connections.values() {
+                if recipient == Recipient::All
+                    || recipient == Recipient::User(connection.user.clone())
+                {
+                    connection.outbox.push(frame.clone());
+                }
+            }
+        }
+    }
+}
+
+// One gateway served over any number of listeners. Cloning shares the gateway.
+#[derive(Clone)]
+pub struct WireServer {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl WireServer {
+    // Wrap the gateway and start the timer thread that drives the engine's `tick`
+    pub fn start(gateway: WireGateway) -> Self {
+        let shared = Arc::new(Mutex::new(Shared {
+            gateway,
+            connections: HashMap::new(),
+            next_connection: 0,
+        }));
+        let timer = Arc::clone(&shared);
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_secs(1));
+            let mut shared = timer.lock().unwrap();
+            let out = shared.gateway.on_timer(current_timestamp());
+            shared.deliver(out);
+        });
+        Self { shared }
+    }
+
+    pub fn serve_tcp(&self, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            stream.set_nodelay(true)?;
+            let server = self.clone();
+            thread::spawn(move || server.handle_connection(stream));
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    pub fn serve_unix(&self, listener: std::os::unix::net::UnixListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let server = self.clone();
+            thread::spawn(move || server.handle_connection(stream));
+        }
+        Ok(())
+    }
+
+    fn handle_connection<S: Stream>(&self, mut stream: S) {
+        let mut connection = None; // id once logged on
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 4096];
+        'read: loop {
+            let n = match stream.read(&mut chunk) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n,
+            };
+            buf.extend_from_slice(&chunk[..n]);
+
+            while let Some(len) = frame_len(&buf) {
+                let frame: Vec<u8> = buf.drain(..len).collect();
+                // Frames are length-prefixed, so an unreadable one is just skipped
+                let Ok(msg) = WireMessage::decode(&frame) else {
+                    continue;
+                };
+                let mut shared = self.shared.lock().unwrap();
+                match (connection, msg) {
+                    (None, WireMessage::Logon { user, token }) => {
+                        if !shared.gateway.authenticates(&user, &token) {
+                            break 'read;
+                        }
+                        let (Ok(writer), Ok(closer)) = (stream.duplicate(), stream.duplicate())
+                        else {
+                            break 'read;
+                        };
+                        let outbox = Outbox::spawn(writer, move || closer.close());
+                        let id = shared.next_connection;
+                        shared.next_connection += 1;
+                        shared.connections.insert(id, Connection { user, outbox });
+                        connection = Some(id);
+                    }
+                    // Everything else needs a logon first
+                    (None, _) => break 'read,
+                    (Some(_), WireMessage::Logon { .. }) => {}
+                    (Some(id), msg) => {
+                        let user = shared.connections[&id].user.clone();
+                        let out = shared.gateway.handle(&user, msg);
+                        shared.deliver(out);
+                    }
+                }
+            }
+        }
+
+        if let Some(id) = connection {
+            self.shared.lock().unwrap().connections.remove(&id);
+        }
+        stream.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire::WireClient;
+    use crate::{MarketCommand, YES};
+
+    fn gateway() -> WireGateway {
+        let mut engine = MatchingEngine::new();
+        engine.add_admin("admin");
+        engine.create_market("m");
+        engine
+            .apply_market_command("admin", "m", MarketCommand::Open)
+            .unwrap();
+        let mut gateway = WireGateway::new(engine);
+        gateway.add_user("maker", "maker-token");
+        gateway.add_user("taker", "taker-token");
+        gateway
+    }
+
+    fn new_order(client_order_id: u64, side: Side, price: f64, qty: f64) -> WireMessage {
+        WireMessage::NewOrder(NewOrder {
+            client_order_id,
+            market_id: "m".to_string(),
+            outcome: YES,
+            side,
+            price,
+            qty,
+        })
+    }
+
+    fn reports(out: &[Outbound]) -> Vec<(&Recipient, &ExecReport)> {
+        out.iter()
+            .filter_map(|(to, msg)| match msg {
+                WireMessage::ExecReport(report) => Some((to, report)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fills_amends_and_book_updates() {
+        let mut gateway = gateway();
+        let out = gateway.handle("maker", new_order(1, Side::Sell, 0.6, 10.0));
+        assert_eq!(reports(&out)[0].1.exec_type, ExecType::New);
+        assert!(out.contains(&(
+            Recipient::All,
+            WireMessage::BookUpdate(BookUpdate {
+                market_id: "m".to_string(),
+                outcome: YES,
+                side: Side::Sell,
+                price: 0.6,
+                qty: 10.0,
+            })
+        )));
+
+        let out = gateway.handle("maker", new_order(1, Side::Sell, 0.6, 10.0));
+        assert_eq!(reports(&out)[0].1.reason, reason::DUPLICATE_ID);
+        for qty in [f64::NAN, f64::INFINITY, -1.0] {
+            let out = gateway.handle("maker", new_order(2, Side::Sell, 0.6, qty));
+            assert_eq!(reports(&out)[0].1.reason, reason::INVALID_QTY);
+        }
+
+        let amend = WireMessage::Amend(crate::wire::Amend {
+            client_order_id: 1,
+            price: 0.55,
+            qty: 8.0,
+        });
+        let out = gateway.handle("maker", amend);
+        assert_eq!(reports(&out)[0].1.exec_type, ExecType::Replaced);
+        // Old level gone, new level added
+        assert_eq!(out.len(), 3);
+
+        let out = gateway.handle("taker", new_order(1, Side::Buy, 0.7, 5.0));
+        let reports = reports(&out);
+        let taker = Recipient::User("taker".to_string());
+        let maker = Recipient::User("maker".to_string());
+        assert_eq!(reports[0].0, &taker);
+        assert_eq!(reports[1].0, &taker);
+        assert_eq!(reports[1].1.last_price, 0.55);
+        assert_eq!(reports[1].1.leaves_qty, 0.0);
+        assert_eq!(reports[2].0, &maker);
+        assert_eq!(reports[2].1.last_qty, 5.0);
+        assert_eq!(reports[2].1.leaves_qty, 3.0);
+    }
+
+    fn next_report<S: Read + Write>(client: &mut WireClient<S>) -> ExecReport {
+        loop {
+            if let WireMessage::ExecReport(report) = client.recv().unwrap() {
+                return report;
+            }
+        }
+    }
+
+    #[test]
+    fn test_clients_over_tcp_and_unix_socket() {
+        let server = WireServer::start(gateway());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let tcp = server.clone();
+        thread::spawn(move || tcp.serve_tcp(listener));
+
+        let mut intruder = WireClient::connect(addr, "maker", "guess").unwrap();
+        assert!(intruder.recv().is_err()); // hung up on
+
+        let mut maker = WireClient::connect(addr, "maker", "maker-token").unwrap();
+        maker
+            .new_order(NewOrder {
+                client_order_id: 1,
+                market_id: "m".to_string(),
+                outcome: YES,
+                side: Side::Sell,
+                price: 0.6,
+                qty: 10.0,
+            })
+            .unwrap();
+        assert_eq!(next_report(&mut maker).exec_type, ExecType::New);
+
+        #[cfg(unix)]
+        {
+            let path = std::env::temp_dir().join(format!("clob-wire-{}.sock", std::process::id()));
+            let _ = std::fs::remove_file(&path);
+            let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+            let unix = server.clone();
+            thread::spawn(move || unix.serve_unix(listener));
+
+            let mut taker = WireClient::connect_unix(&path, "taker", "taker-token").unwrap();
+            taker
+                .new_order(NewOrder {
+                    client_order_id: 1,
+                    market_id: "m".to_string(),
+                    outcome: YES,
+                    side: Side::Buy,
+                    price: 0.6,
+                    qty: 4.0,
+                })
+                .unwrap();
+            assert_eq!(next_report(&mut taker).exec_type, ExecType::New);
+            assert_eq!(next_report(&mut taker).exec_type, ExecType::Fill);
+            let fill = next_report(&mut maker);
+            assert_eq!((fill.exec_type, fill.leaves_qty), (ExecType::Fill, 6.0));
+            let _ = std::fs::remove_file(&path);
+        }
+
+        maker.cancel(1).unwrap();
+        assert_eq!(next_report(&mut maker).exec_type, ExecType::Cancelled);
+    }
+}
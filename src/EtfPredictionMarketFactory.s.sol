@@ -18,6 +18,7 @@ contract EtfPredictionMarketFactory {
     IBasketPricer public immutable pricer;
     ClaimTokens   public immutable claims;
     address       public immutable collateral;
+    address       public immutable settler;    // CLOB operator allowed to settle fills in every vault
 
     mapping(address => address[]) public marketsByCreator;
     address[] public allMarkets;
@@ -35,11 +36,13 @@ contract EtfPredictionMarketFactory {
         uint64 settleTs
     );
 
-    constructor(address _pricer, address _claims, address _collateral) {
+    constructor(address _pricer, address _claims, address _collateral, address _settler) {
         require(_pricer != address(0) && _claims != address(0) && _collateral != address(0), "zero addr");
+        require(_settler != address(0), "zero addr");
         pricer     = IBasketPricer(_pricer);
         claims     = ClaimTokens(_claims);
         collateral = _collateral;
+        settler    = _settler;
     }
 
     // ------------------------------ PUBLIC ------------------------------
@@ -119,6 +122,7 @@ contract EtfPredictionMarketFactory {
         PredictionMarketVault v = PredictionMarketVault(vault);
         v.setMarket(mkt);
         v.setClaims(address(claims));
+        v.setSettler(settler);
     }
 
     function _bookkeep(address mkt) internal {
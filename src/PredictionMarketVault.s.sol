@@ -15,6 +15,7 @@ contract PredictionMarketVault is Ownable, ReentrancyGuard, ERC1155Holder {
     IERC20   public immutable collateral;   // e.g. USDC
     IERC1155 public claims;                 // set once; ERC-1155 (ClaimTokens)
     address  public market;                 // set once; the EtfPredictionMarket
+    address  public settler;                // set once; the CLOB operator settling matched trades
 
     // --- accounting ---
     mapping(address => uint256) public balances;                  // user -> ERC-20 balance
@@ -22,18 +23,26 @@ contract PredictionMarketVault is Ownable, ReentrancyGuard, ERC1155Holder {
 
     event MarketSet(address indexed market);
     event ClaimsSet(address indexed claims);
+    event SettlerSet(address indexed settler);
     event Deposited(address indexed user, uint256 amount);
     event Withdrawn(address indexed user, uint256 amount);
     event ClaimsDeposited(address indexed user, uint256 indexed id, uint256 amount);
     event ClaimsWithdrawn(address indexed user, uint256 indexed id, uint256 amount);
     event ClaimsCredited(address indexed user, uint256 indexed id, uint256 amount);
     event ClaimsDebited(address indexed user, uint256 indexed id, uint256 amount);
+    event CollateralCredited(address indexed user, uint256 amount);
+    event CollateralDebited(address indexed user, uint256 amount);
 
     modifier onlyMarket() {
         require(msg.sender == market, "not market");
         _;
     }
 
+    modifier onlyMarketOrSettler() {
+        require(msg.sender == market || msg.sender == settler, "not market or settler");
+        _;
+    }
+
     constructor(address _collateral, address initialOwner) Ownable(initialOwner) {
         require(_collateral != address(0) && initialOwner != address(0), "zero addr");
         collateral = IERC20(_collateral);
@@ -53,6 +62,13 @@ contract PredictionMarketVault is Ownable, ReentrancyGuard, ERC1155Holder {
         emit ClaimsSet(c);
     }
 
+    /// @notice One-time wiring from the factory.
+    function setSettler(address s) external onlyOwner {
+        require(settler == address(0) && s != address(0), "settler set");
+        settler = s;
+        emit SettlerSet(s);
+    }
+
     // ----------------- ERC-20 cash I/O -----------------
 
     function deposit(uint256 amount) external nonReentrant {
@@ -108,6 +124,32 @@ contract PredictionMarketVault is Ownable, ReentrancyGuard, ERC1155Holder {
         emit ClaimsDebited(user, id, amount);
     }
 
+    // ----------------- Trade settlement (ledger only) -----------------
+    // Every settlement call moves a balance from one user to another, so the
+    // settler can never credit value the vault does not hold.
+
+    /// @notice Moves deposited claims between users (e.g., the seller's shares of a CLOB fill).
+    function transferClaims(address from, address to, uint256 id, uint256 amount) external onlyMarketOrSettler {
+        require(from != address(0) && to != address(0) && from != to && amount > 0, "bad args");
+        uint256 bal = claimBal[from][id];
+        require(bal >= amount, "insufficient");
+        unchecked { claimBal[from][id] = bal - amount; }
+        claimBal[to][id] += amount;
+        emit ClaimsDebited(from, id, amount);
+        emit ClaimsCredited(to, id, amount);
+    }
+
+    /// @notice Moves deposited collateral between users (e.g., the buyer's cost of a CLOB fill).
+    function transferCollateral(address from, address to, uint256 amount) external onlyMarketOrSettler {
+        require(from != address(0) && to != address(0) && from != to && amount > 0, "bad args");
+        uint256 bal = balances[from];
+        require(bal >= amount, "insufficient");
+        unchecked { balances[from] = bal - amount; }
+        balances[to] += amount;
+        emit CollateralDebited(from, amount);
+        emit CollateralCredited(to, amount);
+    }
+
     // -------------- views --------------
 
     function claimBalanceOf(address user, uint256 id) external view returns (uint256) {
@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.13;
+
+import {Test} from "forge-std/Test.sol";
+import {ERC20} from "@openzeppelin/contracts/token/ERC20/ERC20.sol";
+import {ClaimTokens} from "../src/ClaimTokens.s.sol";
+import {PredictionMarketVault} from "../src/PredictionMarketVault.s.sol";
+
+contract MockUSDC is ERC20 {
+    constructor() ERC20("USD Coin", "USDC") {}
+
+    function mint(address to, uint256 amount) external {
+        _mint(to, amount);
+    }
+}
+
+contract PredictionMarketVaultTest is Test {
+    MockUSDC public usdc;
+    ClaimTokens public claims;
+    PredictionMarketVault public vault;
+
+    address market = makeAddr("market");
+    address settler = makeAddr("settler");
+    address alice = makeAddr("alice");
+    address bob = makeAddr("bob");
+    uint256 constant ID = 1;
+
+    function setUp() public {
+        usdc = new MockUSDC();
+        claims = new ClaimTokens("", address(this));
+        vault = new PredictionMarketVault(address(usdc), address(this));
+        vault.setMarket(market);
+        vault.setClaims(address(claims));
+        vault.setSettler(settler);
+
+        // alice holds cash in the vault, bob holds claims
+        usdc.mint(alice, 100);
+        vm.startPrank(alice);
+        usdc.approve(address(vault), 100);
+        vault.deposit(100);
+        vm.stopPrank();
+
+        claims.setMinter(address(this), true);
+        claims.mint(bob, ID, 10);
+        vm.startPrank(bob);
+        claims.setApprovalForAll(address(vault), true);
+        vault.depositClaims(ID, 10);
+        vm.stopPrank();
+    }
+
+    function test_SettlerSettlesAFill() public {
+        vm.startPrank(settler);
+        vault.transferClaims(bob, alice, ID, 10);
+        vault.transferCollateral(alice, bob, 60);
+        vm.stopPrank();
+
+        assertEq(vault.claimBalanceOf(alice, ID), 10);
+        assertEq(vault.claimBalanceOf(bob, ID), 0);
+        assertEq(vault.collateralBalanceOf(alice), 40);
+        assertEq(vault.collateralBalanceOf(bob), 60);
+
+        // The proceeds are withdrawable and the vault still holds the rest
+        vm.prank(bob);
+        vault.withdraw(60);
+        assertEq(usdc.balanceOf(bob), 60);
+        assertEq(usdc.balanceOf(address(vault)), 40);
+    }
+
+    function test_RevertWhen_CreditIsUnbacked() public {
+        vm.startPrank(settler);
+        vm.expectRevert("insufficient");
+        vault.transferCollateral(bob, settler, 1);
+        vm.expectRevert("insufficient");
+        vault.transferClaims(alice, settler, ID, 1);
+        vm.stopPrank();
+        assertEq(vault.collateralBalanceOf(settler), 0);
+    }
+
+    function test_RevertWhen_SettlerMintsClaims() public {
+        vm.prank(settler);
+        vm.expectRevert("not market");
+        vault.creditClaims(settler, ID, 10);
+    }
+
+    function test_RevertWhen_CallerIsNotSettler() public {
+        vm.prank(alice);
+        vm.expectRevert("not market or settler");
+        vault.transferCollateral(alice, bob, 1);
+    }
+
+    function test_RevertWhen_TransferringToSelf() public {
+        vm.prank(settler);
+        vm.expectRevert("bad args");
+        vault.transferCollateral(alice, alice, 1);
+    }
+}